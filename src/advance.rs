@@ -0,0 +1,27 @@
+/*!
+Module containing [`CounterAdvance`], which controls where the counter ends up after a slice
+method (`fill`, `xor`, etc.) is given a length which isn't a multiple of the block size.
+
+Other ChaCha implementations disagree on this, so producing the same keystream as one of them
+over several calls depends on picking the matching policy with `set_counter_advance`. The block
+methods (`fill_block`, `get_block64`, etc.) always use whole blocks, and aren't affected by it.
+*/
+
+/// Position the counter moves to once a slice method has produced its final
+/// (possibly partial) block of output.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum CounterAdvance {
+    /// The counter moves just past the last block that was used (even partially),
+    /// and the rest of that block is discarded. This matches RFC 8439, libsodium,
+    /// and most other block-oriented implementations.
+    #[default]
+    Block,
+    /// The counter moves just past the last batch of [`BUF_LEN_U8`](crate::BUF_LEN_U8) bytes
+    /// that was used (even partially), exactly like the block methods do. Only useful for
+    /// matching code which only ever uses [`fill_block`](crate::ChaChaCore::fill_block).
+    Batch,
+    /// The next slice method continues from the byte right after the last one that was used,
+    /// so splitting a buffer across several calls produces the same output as a single call.
+    /// This matches seekable stream ciphers (such as those of the `cipher` crate).
+    Exact,
+}
@@ -0,0 +1,168 @@
+/*!
+Module containing [`ChaChaAny`], a ChaCha instance whose round count is chosen at runtime.
+
+The round count is normally a type parameter, so that the round loop can be fully unrolled for
+each backend. Protocols which negotiate the round count (and plugin systems which read it from
+configuration) don't know it until runtime though, so `ChaChaAny` holds one of the statically
+typed instances and dispatches to it with a single branch per call.
+*/
+
+use crate::rounds::*;
+use crate::variations::*;
+use crate::{ChaCha, Error};
+use core::fmt;
+
+enum Inner<V> {
+    R4(ChaCha<R4, V>),
+    R6(ChaCha<R6, V>),
+    R8(ChaCha<R8, V>),
+    R12(ChaCha<R12, V>),
+    R20(ChaCha<R20, V>),
+}
+
+macro_rules! dispatch {
+    ($inner:expr, $chacha:ident => $body:expr) => {
+        match $inner {
+            Inner::R4($chacha) => $body,
+            Inner::R6($chacha) => $body,
+            Inner::R8($chacha) => $body,
+            Inner::R12($chacha) => $body,
+            Inner::R20($chacha) => $body,
+        }
+    };
+}
+
+/// ChaCha instance of the `V` variant, with the amount of double rounds chosen at runtime.
+///
+/// ```
+/// use chachacha::{ChaCha12Djb, ChaChaAnyDjb};
+///
+/// let negotiated = 6;
+/// let mut chacha = ChaChaAnyDjb::new(negotiated, [69; 8], 0, [0; 3]).unwrap();
+/// assert_eq!(chacha.double_rounds(), 6);
+/// assert_eq!(
+///     chacha.get_block(),
+///     ChaCha12Djb::new([69; 8], 0, [0; 3]).get_block()
+/// );
+/// assert!(ChaChaAnyDjb::new(7, [69; 8], 0, [0; 3]).is_err());
+/// ```
+pub struct ChaChaAny<V> {
+    inner: Inner<V>,
+}
+
+/// [`ChaChaAny`] with a 64-bit counter and a 64-bit nonce.
+pub type ChaChaAnyDjb = ChaChaAny<Djb>;
+/// [`ChaChaAny`] with a 32-bit counter and a 96-bit nonce.
+pub type ChaChaAnyIetf = ChaChaAny<Ietf>;
+
+impl<V: Variant> ChaChaAny<V> {
+    /// Creates a new `ChaChaAny` instance with `double_rounds` double rounds, handling
+    /// `key`, `counter`, and `nonce` exactly like [`ChaCha20Djb::new`](crate::ChaCha20Djb::new).
+    ///
+    /// Returns [`Error::UnsupportedRounds`] unless `double_rounds` is 2, 3, 4, 6, or 10
+    /// (4, 6, 8, 12, or 20 rounds).
+    #[inline]
+    pub fn new(
+        double_rounds: usize,
+        key: [u32; 8],
+        counter: u64,
+        nonce: [u32; 3],
+    ) -> Result<Self, Error> {
+        let inner = match double_rounds {
+            2 => Inner::R4(ChaCha::new(key, counter, nonce)),
+            3 => Inner::R6(ChaCha::new(key, counter, nonce)),
+            4 => Inner::R8(ChaCha::new(key, counter, nonce)),
+            6 => Inner::R12(ChaCha::new(key, counter, nonce)),
+            10 => Inner::R20(ChaCha::new(key, counter, nonce)),
+            _ => return Err(Error::UnsupportedRounds),
+        };
+        Ok(Self { inner })
+    }
+
+    /// Returns the amount of double rounds used by `self`.
+    #[inline]
+    pub fn double_rounds(&self) -> usize {
+        match self.inner {
+            Inner::R4(_) => R4::COUNT,
+            Inner::R6(_) => R6::COUNT,
+            Inner::R8(_) => R8::COUNT,
+            Inner::R12(_) => R12::COUNT,
+            Inner::R20(_) => R20::COUNT,
+        }
+    }
+
+    /// See [`ChaCha20Djb::get_counter`](crate::ChaCha20Djb::get_counter).
+    #[inline]
+    pub fn get_counter(&self) -> u64 {
+        dispatch!(&self.inner, chacha => chacha.get_counter())
+    }
+
+    /// See [`ChaCha20Djb::set_counter`](crate::ChaCha20Djb::set_counter).
+    #[inline]
+    pub fn set_counter(&mut self, new_counter: u64) {
+        dispatch!(&mut self.inner, chacha => chacha.set_counter(new_counter))
+    }
+
+    /// See [`ChaCha20Djb::position`](crate::ChaCha20Djb::position).
+    #[inline]
+    pub fn position(&self) -> u128 {
+        dispatch!(&self.inner, chacha => chacha.position())
+    }
+
+    /// See [`ChaCha20Djb::seek`](crate::ChaCha20Djb::seek).
+    #[inline]
+    pub fn seek(&mut self, position: u128) {
+        dispatch!(&mut self.inner, chacha => chacha.seek(position))
+    }
+
+    /// See [`ChaCha20Djb::get_block`](crate::ChaCha20Djb::get_block).
+    #[inline]
+    pub fn get_block(&mut self) -> [u8; crate::BUF_LEN_U8] {
+        dispatch!(&mut self.inner, chacha => chacha.get_block())
+    }
+
+    /// See [`ChaCha20Djb::fill`](crate::ChaCha20Djb::fill).
+    #[inline]
+    pub fn fill(&mut self, dst: &mut [u8]) {
+        dispatch!(&mut self.inner, chacha => chacha.fill(dst))
+    }
+
+    /// See [`ChaCha20Djb::xor`](crate::ChaCha20Djb::xor).
+    #[inline]
+    pub fn xor(&mut self, dst: &mut [u8]) {
+        dispatch!(&mut self.inner, chacha => chacha.xor(dst))
+    }
+
+    /// See [`ChaCha20Djb::next_u32`](crate::ChaCha20Djb::next_u32).
+    #[inline]
+    pub fn next_u32(&mut self) -> u32 {
+        dispatch!(&mut self.inner, chacha => chacha.next_u32())
+    }
+
+    /// See [`ChaCha20Djb::next_u64`](crate::ChaCha20Djb::next_u64).
+    #[inline]
+    pub fn next_u64(&mut self) -> u64 {
+        dispatch!(&mut self.inner, chacha => chacha.next_u64())
+    }
+}
+
+impl<V> Clone for ChaChaAny<V> {
+    #[inline]
+    fn clone(&self) -> Self {
+        let inner = match &self.inner {
+            Inner::R4(chacha) => Inner::R4(chacha.clone()),
+            Inner::R6(chacha) => Inner::R6(chacha.clone()),
+            Inner::R8(chacha) => Inner::R8(chacha.clone()),
+            Inner::R12(chacha) => Inner::R12(chacha.clone()),
+            Inner::R20(chacha) => Inner::R20(chacha.clone()),
+        };
+        Self { inner }
+    }
+}
+
+impl<V: Variant> fmt::Debug for ChaChaAny<V> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        dispatch!(&self.inner, chacha => chacha.fmt(f))
+    }
+}
@@ -0,0 +1,157 @@
+/*!
+Module containing [`ChaChaAnyVariant`], a ChaCha instance whose counter/nonce layout is chosen at
+runtime.
+
+Code which stores keys and streams of both variants (key management services, generic containers,
+etc.) would otherwise have to be generic over the variant, or duplicate every code path. Much like
+[`ChaChaAny`](crate::ChaChaAny) does for the round count, this holds one of the statically typed
+instances and dispatches to it with a single branch per call.
+*/
+
+use crate::rounds::*;
+use crate::variations::*;
+use crate::{BUF_LEN_U8, ChaCha};
+use core::fmt;
+
+enum Inner<R> {
+    Djb(ChaCha<R, Djb>),
+    Ietf(ChaCha<R, Ietf>),
+}
+
+macro_rules! dispatch {
+    ($inner:expr, $chacha:ident => $body:expr) => {
+        match $inner {
+            Inner::Djb($chacha) => $body,
+            Inner::Ietf($chacha) => $body,
+        }
+    };
+}
+
+/// ChaCha instance with `R` rounds, with the variant chosen at runtime.
+///
+/// ```
+/// use chachacha::{ChaCha20AnyVariant, ChaCha20Ietf, Variants};
+///
+/// let mut chacha = ChaCha20AnyVariant::new(Variants::Ietf, [69; 8], 0, [1, 2, 3]);
+/// assert_eq!(chacha.variant(), Variants::Ietf);
+/// assert_eq!(
+///     chacha.get_block(),
+///     ChaCha20Ietf::new([69; 8], 0, [1, 2, 3]).get_block()
+/// );
+/// ```
+pub struct ChaChaAnyVariant<R> {
+    inner: Inner<R>,
+}
+
+/// [`ChaChaAnyVariant`] with 8 rounds.
+pub type ChaCha8AnyVariant = ChaChaAnyVariant<R8>;
+/// [`ChaChaAnyVariant`] with 12 rounds.
+pub type ChaCha12AnyVariant = ChaChaAnyVariant<R12>;
+/// [`ChaChaAnyVariant`] with 20 rounds.
+pub type ChaCha20AnyVariant = ChaChaAnyVariant<R20>;
+
+impl<R: DoubleRounds> ChaChaAnyVariant<R> {
+    /// Creates a new `ChaChaAnyVariant` instance of `variant`, handling `key`, `counter`,
+    /// and `nonce` exactly like [`ChaCha20Djb::new`](crate::ChaCha20Djb::new).
+    #[inline]
+    pub fn new(variant: Variants, key: [u32; 8], counter: u64, nonce: [u32; 3]) -> Self {
+        let inner = match variant {
+            Variants::Djb => Inner::Djb(ChaCha::new(key, counter, nonce)),
+            Variants::Ietf => Inner::Ietf(ChaCha::new(key, counter, nonce)),
+        };
+        Self { inner }
+    }
+
+    /// Returns the variant of `self`.
+    #[inline]
+    pub fn variant(&self) -> Variants {
+        match self.inner {
+            Inner::Djb(_) => Variants::Djb,
+            Inner::Ietf(_) => Variants::Ietf,
+        }
+    }
+
+    /// See [`ChaCha20Djb::get_counter`](crate::ChaCha20Djb::get_counter).
+    #[inline]
+    pub fn get_counter(&self) -> u64 {
+        dispatch!(&self.inner, chacha => chacha.get_counter())
+    }
+
+    /// See [`ChaCha20Djb::set_counter`](crate::ChaCha20Djb::set_counter).
+    #[inline]
+    pub fn set_counter(&mut self, new_counter: u64) {
+        dispatch!(&mut self.inner, chacha => chacha.set_counter(new_counter))
+    }
+
+    /// See [`ChaCha20Djb::get_stream`](crate::ChaCha20Djb::get_stream).
+    #[inline]
+    pub fn get_stream(&self) -> u128 {
+        dispatch!(&self.inner, chacha => chacha.get_stream())
+    }
+
+    /// See [`ChaCha20Djb::set_stream`](crate::ChaCha20Djb::set_stream).
+    #[inline]
+    pub fn set_stream(&mut self, new_stream: u128) {
+        dispatch!(&mut self.inner, chacha => chacha.set_stream(new_stream))
+    }
+
+    /// See [`ChaCha20Djb::position`](crate::ChaCha20Djb::position).
+    #[inline]
+    pub fn position(&self) -> u128 {
+        dispatch!(&self.inner, chacha => chacha.position())
+    }
+
+    /// See [`ChaCha20Djb::seek`](crate::ChaCha20Djb::seek).
+    #[inline]
+    pub fn seek(&mut self, position: u128) {
+        dispatch!(&mut self.inner, chacha => chacha.seek(position))
+    }
+
+    /// See [`ChaCha20Djb::get_block`](crate::ChaCha20Djb::get_block).
+    #[inline]
+    pub fn get_block(&mut self) -> [u8; BUF_LEN_U8] {
+        dispatch!(&mut self.inner, chacha => chacha.get_block())
+    }
+
+    /// See [`ChaCha20Djb::fill`](crate::ChaCha20Djb::fill).
+    #[inline]
+    pub fn fill(&mut self, dst: &mut [u8]) {
+        dispatch!(&mut self.inner, chacha => chacha.fill(dst))
+    }
+
+    /// See [`ChaCha20Djb::xor`](crate::ChaCha20Djb::xor).
+    #[inline]
+    pub fn xor(&mut self, dst: &mut [u8]) {
+        dispatch!(&mut self.inner, chacha => chacha.xor(dst))
+    }
+
+    /// See [`ChaCha20Djb::next_u32`](crate::ChaCha20Djb::next_u32).
+    #[inline]
+    pub fn next_u32(&mut self) -> u32 {
+        dispatch!(&mut self.inner, chacha => chacha.next_u32())
+    }
+
+    /// See [`ChaCha20Djb::next_u64`](crate::ChaCha20Djb::next_u64).
+    #[inline]
+    pub fn next_u64(&mut self) -> u64 {
+        dispatch!(&mut self.inner, chacha => chacha.next_u64())
+    }
+}
+
+impl<R> Clone for ChaChaAnyVariant<R> {
+    #[inline]
+    fn clone(&self) -> Self {
+        let inner = match &self.inner {
+            Inner::Djb(chacha) => Inner::Djb(chacha.clone()),
+            Inner::Ietf(chacha) => Inner::Ietf(chacha.clone()),
+        };
+        Self { inner }
+    }
+}
+
+impl<R: DoubleRounds> fmt::Debug for ChaChaAnyVariant<R> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        dispatch!(&self.inner, chacha => chacha.fmt(f))
+    }
+}
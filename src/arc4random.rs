@@ -0,0 +1,232 @@
+/*!
+Module containing [`Arc4Random`], a port of OpenBSD's `arc4random` family of functions.
+
+The semantics are copied directly from the OpenBSD implementation: output comes from a 1 KiB
+buffer of ChaCha20 keystream which is consumed from the back, and every time that buffer runs
+dry the first 40 bytes of the next buffer are used to rekey the instance (and are then erased).
+This gives backtracking resistance, since compromising the current state reveals nothing about
+output which has already been returned. On top of that, the instance is stirred with fresh OS
+entropy after roughly 1.5 MiB of output, or whenever a `fork()` is detected (on unix, behind the
+`std` feature).
+*/
+
+use crate::ChaCha20Djb;
+use crate::util::*;
+
+/// Size (in 8-bit integers) of the key used by `Arc4Random`.
+const KEY_LEN: usize = 32;
+/// Size (in 8-bit integers) of the IV used by `Arc4Random`.
+const IV_LEN: usize = 8;
+/// Size (in 8-bit integers) of the internal keystream buffer.
+const RS_BUF_LEN: usize = BUF_LEN_U8 * 4;
+/// Amount of output (in 8-bit integers) between stirs from OS entropy.
+const RS_COUNT: usize = 1_600_000;
+
+/// Buffered ChaCha20 generator with the same guarantees as OpenBSD's `arc4random`.
+///
+/// ```
+/// use chachacha::Arc4Random;
+///
+/// let mut rng = Arc4Random::new();
+/// let mut buf = [0; 100];
+/// rng.buf(&mut buf);
+/// assert!(rng.uniform(6) < 6);
+/// ```
+pub struct Arc4Random {
+    chacha: Option<ChaCha20Djb>,
+    buf: [u8; RS_BUF_LEN],
+    have: usize,
+    count: usize,
+    #[cfg(all(feature = "std", unix))]
+    pid: u32,
+}
+
+impl Default for Arc4Random {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Arc4Random {
+    /// Creates a new `Arc4Random` instance.
+    ///
+    /// The instance isn't keyed until output is first requested from it.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            chacha: None,
+            buf: [0; RS_BUF_LEN],
+            have: 0,
+            count: 0,
+            #[cfg(all(feature = "std", unix))]
+            pid: 0,
+        }
+    }
+
+    /// Equivalent of `arc4random`: returns a uniformly distributed `u32`.
+    ///
+    /// # Panics
+    ///
+    /// If a stir is required and the operating system is unable to provide entropy.
+    #[inline]
+    pub fn next_u32(&mut self) -> u32 {
+        self.stir_if_needed(size_of::<u32>());
+        if self.have < size_of::<u32>() {
+            self.rekey(None);
+        }
+        let start = RS_BUF_LEN - self.have;
+        let bytes = &mut self.buf[start..start + size_of::<u32>()];
+        let result = u32::from_ne_bytes(bytes.try_into().unwrap());
+        bytes.fill(0);
+        self.have -= size_of::<u32>();
+        result
+    }
+
+    /// Equivalent of `arc4random_buf`: fills `dst` with random bytes.
+    ///
+    /// # Panics
+    ///
+    /// If a stir is required and the operating system is unable to provide entropy.
+    #[inline]
+    pub fn buf(&mut self, mut dst: &mut [u8]) {
+        self.stir_if_needed(dst.len());
+        while !dst.is_empty() {
+            if self.have > 0 {
+                let len = dst.len().min(self.have);
+                let start = RS_BUF_LEN - self.have;
+                let keystream = &mut self.buf[start..start + len];
+                let (cur, rest) = dst.split_at_mut(len);
+                cur.copy_from_slice(keystream);
+                keystream.fill(0);
+                self.have -= len;
+                dst = rest;
+            }
+            if self.have == 0 {
+                self.rekey(None);
+            }
+        }
+    }
+
+    /// Equivalent of `arc4random_uniform`: returns a uniformly distributed
+    /// `u32` less than `upper_bound`, avoiding modulo bias.
+    ///
+    /// Returns 0 when `upper_bound` is less than 2.
+    ///
+    /// # Panics
+    ///
+    /// If a stir is required and the operating system is unable to provide entropy.
+    #[inline]
+    pub fn uniform(&mut self, upper_bound: u32) -> u32 {
+        if upper_bound < 2 {
+            return 0;
+        }
+        // 2**32 % x == (2**32 - x) % x
+        let min = upper_bound.wrapping_neg() % upper_bound;
+        loop {
+            let r = self.next_u32();
+            if r >= min {
+                return r % upper_bound;
+            }
+        }
+    }
+
+    /// Immediately mixes fresh OS entropy into the instance and discards
+    /// any buffered keystream.
+    ///
+    /// # Panics
+    ///
+    /// If the operating system is unable to provide entropy.
+    #[inline]
+    pub fn stir(&mut self) {
+        let mut rnd = [0; KEY_LEN + IV_LEN];
+        getrandom::fill(&mut rnd).expect("unable to stir from OS entropy");
+        if self.chacha.is_none() {
+            self.init(&rnd);
+        } else {
+            self.rekey(Some(&rnd));
+        }
+        rnd.fill(0);
+        // Invalidate the buffer.
+        self.buf.fill(0);
+        self.have = 0;
+        self.count = RS_COUNT;
+        #[cfg(all(feature = "std", unix))]
+        {
+            self.pid = std::process::id();
+        }
+    }
+
+    /// Creates an instance keyed from `seed` which will stir after `count` more bytes of output,
+    /// so tests can check its output against a plain `ChaCha20Djb` keyed the same way.
+    #[cfg(test)]
+    pub(crate) fn from_seed(seed: &[u8; KEY_LEN + IV_LEN], count: usize) -> Self {
+        let mut result = Self::new();
+        result.init(seed);
+        result.count = count;
+        #[cfg(all(feature = "std", unix))]
+        {
+            result.pid = std::process::id();
+        }
+        result
+    }
+
+    /// Returns how many more bytes can be requested before the next stir.
+    #[cfg(test)]
+    pub(crate) fn until_stir(&self) -> usize {
+        self.count
+    }
+
+    #[inline]
+    fn stir_if_needed(&mut self, len: usize) {
+        #[cfg(all(feature = "std", unix))]
+        if self.pid != std::process::id() {
+            self.chacha = None;
+        }
+        if self.chacha.is_none() || self.count <= len {
+            self.stir();
+        }
+        // The request is counted against the fresh allowance too, clamping at 0 if it's
+        // larger than the whole thing (so the next request stirs again).
+        self.count = self.count.saturating_sub(len);
+    }
+
+    #[inline]
+    fn init(&mut self, seed: &[u8; KEY_LEN + IV_LEN]) {
+        let mut key = [0; 8];
+        for (dst, src) in key.iter_mut().zip(seed.chunks_exact(size_of::<u32>())) {
+            *dst = u32::from_le_bytes(src.try_into().unwrap());
+        }
+        let iv = &seed[KEY_LEN..];
+        let nonce = [
+            u32::from_le_bytes(iv[..4].try_into().unwrap()),
+            u32::from_le_bytes(iv[4..].try_into().unwrap()),
+            0,
+        ];
+        self.chacha = Some(ChaCha20Djb::new(key, 0, nonce));
+        key.fill(0);
+    }
+
+    #[inline]
+    fn rekey(&mut self, dat: Option<&[u8; KEY_LEN + IV_LEN]>) {
+        let Some(chacha) = self.chacha.as_mut() else {
+            unreachable!("`Arc4Random` is always keyed before it's rekeyed")
+        };
+        // Encrypt the buffer in place, so anything still left in it is mixed into the new key.
+        chacha.xor(&mut self.buf);
+        // Mix in optional user provided data.
+        if let Some(dat) = dat {
+            self.buf
+                .iter_mut()
+                .zip(dat)
+                .for_each(|(dst, src)| *dst ^= src);
+        }
+        // Immediately reinit for backtracking resistance.
+        let mut seed = [0; KEY_LEN + IV_LEN];
+        seed.copy_from_slice(&self.buf[..KEY_LEN + IV_LEN]);
+        self.init(&seed);
+        seed.fill(0);
+        self.buf[..KEY_LEN + IV_LEN].fill(0);
+        self.have = RS_BUF_LEN - KEY_LEN - IV_LEN;
+    }
+}
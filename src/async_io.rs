@@ -0,0 +1,216 @@
+/*!
+Module containing [`AsyncEncryptingWriter`] and [`AsyncDecryptingReader`], the asynchronous
+counterparts of [`EncryptingWriter`](crate::EncryptingWriter) and
+[`DecryptingReader`](crate::DecryptingReader).
+
+Both implement the traits from `futures-io` and/or `tokio`, depending on which of those features
+are enabled, and keep the keystream position byte-exact in exactly the same way as the blocking
+versions. A poll which returns `Pending` (or an error) doesn't advance the keystream at all.
+*/
+
+use crate::ChaCha;
+use crate::rounds::*;
+use crate::util::*;
+use crate::variations::*;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use std::io::Result;
+
+/// Asynchronous writer which xors the data written to it with the keystream of a
+/// ChaCha instance with `R` rounds of the `V` variant, then writes it to `W`.
+pub struct AsyncEncryptingWriter<W, R, V> {
+    inner: W,
+    core: ChaCha<R, V>,
+}
+
+/// Asynchronous reader which xors the data read from `I` with the keystream
+/// of a ChaCha instance with `R` rounds of the `V` variant.
+pub struct AsyncDecryptingReader<I, R, V> {
+    inner: I,
+    core: ChaCha<R, V>,
+}
+
+// The ChaCha instance is never pinned, so only the inner stream matters
+// (the round and variant markers are only ever used as types).
+impl<W: Unpin, R, V> Unpin for AsyncEncryptingWriter<W, R, V> {}
+impl<I: Unpin, R, V> Unpin for AsyncDecryptingReader<I, R, V> {}
+
+macro_rules! adapter_methods {
+    ($inner:ident, $what:literal) => {
+        #[doc = concat!(
+            "Creates a new instance, which ", $what, " `inner` starting from the current position of `core`."
+        )]
+        #[inline]
+        pub fn new(inner: $inner, core: ChaCha<R, V>) -> Self {
+            Self { inner, core }
+        }
+
+        /// Returns the position (in bytes) in the keystream that the next operation will start at.
+        #[inline]
+        pub fn position(&self) -> u128 {
+            self.core.position()
+        }
+
+        /// Returns a reference to the inner stream.
+        #[inline]
+        pub fn get_ref(&self) -> &$inner {
+            &self.inner
+        }
+
+        /// Returns a mutable reference to the inner stream.
+        ///
+        /// Anything done directly through it bypasses the cipher.
+        #[inline]
+        pub fn get_mut(&mut self) -> &mut $inner {
+            &mut self.inner
+        }
+
+        /// Consumes `self`, returning the inner stream along with the ChaCha instance
+        /// positioned exactly where the next operation would have started.
+        #[inline]
+        pub fn into_parts(self) -> ($inner, ChaCha<R, V>) {
+            (self.inner, self.core)
+        }
+    };
+}
+
+impl<W, R, V> AsyncEncryptingWriter<W, R, V>
+where
+    R: DoubleRounds,
+    V: Variant,
+{
+    adapter_methods!(W, "writes to");
+
+    #[inline]
+    fn encrypt_with(
+        &mut self,
+        src: &[u8],
+        write: impl FnOnce(Pin<&mut W>, &[u8]) -> Poll<Result<usize>>,
+    ) -> Poll<Result<usize>>
+    where
+        W: Unpin,
+    {
+        let mut scratch = [0; BUF_LEN_U8];
+        let scratch = &mut scratch[..src.len().min(BUF_LEN_U8)];
+        scratch.copy_from_slice(&src[..scratch.len()]);
+        let start = self.core.position();
+        self.core.xor(scratch);
+        // Only advance past what the inner writer accepted, even if that's nothing.
+        let result = write(Pin::new(&mut self.inner), scratch);
+        let written = match &result {
+            Poll::Ready(Ok(written)) => *written,
+            _ => 0,
+        };
+        self.core.seek(start + written as u128);
+        result
+    }
+}
+
+impl<I, R, V> AsyncDecryptingReader<I, R, V>
+where
+    R: DoubleRounds,
+    V: Variant,
+{
+    adapter_methods!(I, "reads from");
+
+    #[inline]
+    fn decrypt(&mut self, dst: &mut [u8]) {
+        let start = self.core.position();
+        self.core.xor(dst);
+        // `xor` always finishes on a block boundary, so step back to the exact byte.
+        self.core.seek(start + dst.len() as u128);
+    }
+}
+
+#[cfg(feature = "futures-io")]
+impl<W, R, V> futures_io::AsyncWrite for AsyncEncryptingWriter<W, R, V>
+where
+    W: futures_io::AsyncWrite + Unpin,
+    R: DoubleRounds,
+    V: Variant,
+{
+    #[inline]
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, src: &[u8]) -> Poll<Result<usize>> {
+        self.get_mut()
+            .encrypt_with(src, |inner, scratch| inner.poll_write(cx, scratch))
+    }
+
+    #[inline]
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    #[inline]
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_close(cx)
+    }
+}
+
+#[cfg(feature = "futures-io")]
+impl<I, R, V> futures_io::AsyncRead for AsyncDecryptingReader<I, R, V>
+where
+    I: futures_io::AsyncRead + Unpin,
+    R: DoubleRounds,
+    V: Variant,
+{
+    #[inline]
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        dst: &mut [u8],
+    ) -> Poll<Result<usize>> {
+        let this = self.get_mut();
+        let result = Pin::new(&mut this.inner).poll_read(cx, dst);
+        if let Poll::Ready(Ok(len)) = result {
+            this.decrypt(&mut dst[..len]);
+        }
+        result
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<W, R, V> tokio::io::AsyncWrite for AsyncEncryptingWriter<W, R, V>
+where
+    W: tokio::io::AsyncWrite + Unpin,
+    R: DoubleRounds,
+    V: Variant,
+{
+    #[inline]
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, src: &[u8]) -> Poll<Result<usize>> {
+        self.get_mut()
+            .encrypt_with(src, |inner, scratch| inner.poll_write(cx, scratch))
+    }
+
+    #[inline]
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<I, R, V> tokio::io::AsyncRead for AsyncDecryptingReader<I, R, V>
+where
+    I: tokio::io::AsyncRead + Unpin,
+    R: DoubleRounds,
+    V: Variant,
+{
+    #[inline]
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        dst: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<Result<()>> {
+        let this = self.get_mut();
+        let filled = dst.filled().len();
+        let result = Pin::new(&mut this.inner).poll_read(cx, dst);
+        if let Poll::Ready(Ok(())) = result {
+            this.decrypt(&mut dst.filled_mut()[filled..]);
+        }
+        result
+    }
+}
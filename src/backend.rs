@@ -0,0 +1,109 @@
+/*!
+Module containing everything needed to plug a custom implementation of ChaCha into [`ChaChaCore`],
+for architectures (or instruction sets) that this crate doesn't support yet.
+
+A backend is a type implementing [`Machine`], which holds [`DEPTH`] ChaCha matrices at once, each
+one block further along than the last. [`ChaChaCore`] drives it, handling the counter, buffering,
+and every higher level API, so the backend only needs to provide the raw block function.
+
+Only available with the `unstable-backend` feature. **Nothing in this module is covered by
+semver**, since the trait changes whenever the built-in backends need something new from it.
+
+```
+use chachacha::ChaCha20Djb;
+use chachacha::backend::*;
+use core::ops::Add;
+
+/// The most naive backend possible, processing one matrix at a time.
+#[derive(Clone)]
+struct Scalar([[u32; 16]; DEPTH]);
+
+impl Add for Scalar {
+    type Output = Self;
+
+    fn add(mut self, rhs: Self) -> Self {
+        for (a, b) in self.0.iter_mut().flatten().zip(rhs.0.iter().flatten()) {
+            *a = a.wrapping_add(*b);
+        }
+        self
+    }
+}
+
+fn load(state: &ChaChaNaked) -> [u32; 16] {
+    let rows = [state.row_a, state.row_b, state.row_c, state.row_d];
+    let words = rows.map(|row| unsafe { row.u32x4 });
+    core::array::from_fn(|i| words[i / 4][i % 4])
+}
+
+fn add_djb(m: &mut [u32; 16], amount: u64) {
+    let counter = (m[12] as u64 | (m[13] as u64) << 32).wrapping_add(amount);
+    [m[12], m[13]] = [counter as u32, (counter >> 32) as u32];
+}
+
+fn quarter_round(m: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+    m[a] = m[a].wrapping_add(m[b]); m[d] = (m[d] ^ m[a]).rotate_left(16);
+    m[c] = m[c].wrapping_add(m[d]); m[b] = (m[b] ^ m[c]).rotate_left(12);
+    m[a] = m[a].wrapping_add(m[b]); m[d] = (m[d] ^ m[a]).rotate_left(8);
+    m[c] = m[c].wrapping_add(m[d]); m[b] = (m[b] ^ m[c]).rotate_left(7);
+}
+
+impl Machine for Scalar {
+    fn new_djb(state: &ChaChaNaked) -> Self {
+        let base = load(state);
+        Self(core::array::from_fn(|i| {
+            let mut m = base;
+            add_djb(&mut m, i as u64);
+            m
+        }))
+    }
+
+    fn new_ietf(state: &ChaChaNaked) -> Self {
+        let base = load(state);
+        Self(core::array::from_fn(|i| {
+            let mut m = base;
+            m[12] = m[12].wrapping_add(i as u32);
+            m
+        }))
+    }
+
+    fn increment_djb(&mut self) {
+        self.0.iter_mut().for_each(|m| add_djb(m, DEPTH as u64));
+    }
+
+    fn increment_ietf(&mut self) {
+        self.0.iter_mut().for_each(|m| m[12] = m[12].wrapping_add(DEPTH as u32));
+    }
+
+    fn double_round(&mut self) {
+        for m in &mut self.0 {
+            quarter_round(m, 0, 4, 8, 12);
+            quarter_round(m, 1, 5, 9, 13);
+            quarter_round(m, 2, 6, 10, 14);
+            quarter_round(m, 3, 7, 11, 15);
+            quarter_round(m, 0, 5, 10, 15);
+            quarter_round(m, 1, 6, 11, 12);
+            quarter_round(m, 2, 7, 8, 13);
+            quarter_round(m, 3, 4, 9, 14);
+        }
+    }
+
+    fn fetch_result(self, buf: &mut [u8; BUF_LEN_U8]) {
+        for (dst, word) in buf.chunks_exact_mut(4).zip(self.0.iter().flatten()) {
+            dst.copy_from_slice(&word.to_le_bytes());
+        }
+    }
+}
+
+let mut custom = ChaChaCore::<Scalar, R20, Djb>::new([69; 8], 0, [1, 2, 3]);
+let mut builtin = ChaCha20Djb::new([69; 8], 0, [1, 2, 3]);
+let (mut a, mut b) = ([0; 1000], [0; 1000]);
+custom.fill(&mut a);
+builtin.fill(&mut b);
+assert_eq!(a, b);
+```
+*/
+
+pub use crate::chacha::ChaChaCore;
+pub use crate::rounds::{DoubleRounds, R4, R6, R8, R12, R20, Rounds};
+pub use crate::util::{BUF_LEN_U8, ChaChaNaked, DEPTH, MATRIX_SIZE_U8, Machine, Row};
+pub use crate::variations::{Djb, Ietf, Variant, Variants, XDjb};
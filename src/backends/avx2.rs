@@ -1,4 +1,6 @@
+use crate::rounds::*;
 use crate::util::*;
+use crate::variations::*;
 #[cfg(target_arch = "x86")]
 use core::arch::x86::*;
 #[cfg(target_arch = "x86_64")]
@@ -7,7 +9,11 @@ use core::mem::transmute;
 use core::ops::Add;
 
 const HALF_DEPTH: usize = DEPTH / 2;
+/// Number of `Matrix`es [`Machine::apply`] keeps in flight at once (8 blocks), so the
+/// dependency chains within the quarter rounds of one overlap with those of the other.
+const INTERLEAVE: usize = 2;
 
+/// AVX2 backend, processing two ChaCha instances per 256-bit register.
 #[derive(Clone)]
 #[repr(C)]
 pub struct Matrix {
@@ -17,7 +23,7 @@ pub struct Matrix {
 impl Add for Matrix {
     type Output = Self;
 
-    #[inline]
+    #[inline(always)]
     fn add(mut self, rhs: Self) -> Self::Output {
         unsafe {
             for i in 0..self.state.len() {
@@ -40,13 +46,20 @@ macro_rules! rotate_left_epi32 {
 }
 
 impl Matrix {
-    #[inline]
+    #[inline(always)]
     fn quarter_round(&mut self) {
         unsafe {
+            // Byte indices (within each 32-bit lane) to gather for the rotations by 16 and 8.
+            let rotate_16 = _mm256_broadcastsi128_si256(_mm_setr_epi8(
+                2, 3, 0, 1, 6, 7, 4, 5, 10, 11, 8, 9, 14, 15, 12, 13,
+            ));
+            let rotate_8 = _mm256_broadcastsi128_si256(_mm_setr_epi8(
+                3, 0, 1, 2, 7, 4, 5, 6, 11, 8, 9, 10, 15, 12, 13, 14,
+            ));
             for [a, b, c, d] in self.state.iter_mut() {
                 *a = _mm256_add_epi32(*a, *b);
                 *d = _mm256_xor_si256(*d, *a);
-                *d = rotate_left_epi32!(*d, 16);
+                *d = _mm256_shuffle_epi8(*d, rotate_16);
 
                 *c = _mm256_add_epi32(*c, *d);
                 *b = _mm256_xor_si256(*b, *c);
@@ -54,7 +67,7 @@ impl Matrix {
 
                 *a = _mm256_add_epi32(*a, *b);
                 *d = _mm256_xor_si256(*d, *a);
-                *d = rotate_left_epi32!(*d, 8);
+                *d = _mm256_shuffle_epi8(*d, rotate_8);
 
                 *c = _mm256_add_epi32(*c, *d);
                 *b = _mm256_xor_si256(*b, *c);
@@ -63,7 +76,7 @@ impl Matrix {
         }
     }
 
-    #[inline]
+    #[inline(always)]
     fn make_diagonal(&mut self) {
         unsafe {
             for [a, _, c, d] in self.state.iter_mut() {
@@ -74,7 +87,7 @@ impl Matrix {
         }
     }
 
-    #[inline]
+    #[inline(always)]
     fn unmake_diagonal(&mut self) {
         unsafe {
             for [a, _, c, d] in self.state.iter_mut() {
@@ -87,12 +100,12 @@ impl Matrix {
 }
 
 impl Machine for Matrix {
-    #[inline]
+    #[inline(always)]
     fn new_djb(state: &ChaChaNaked) -> Self {
         unsafe {
             let mut result = Matrix {
                 state: [[
-                    _mm256_broadcastsi128_si256(transmute(ROW_A)),
+                    _mm256_broadcastsi128_si256(transmute(state.row_a)),
                     _mm256_broadcastsi128_si256(transmute(state.row_b)),
                     _mm256_broadcastsi128_si256(transmute(state.row_c)),
                     _mm256_broadcastsi128_si256(transmute(state.row_d)),
@@ -106,12 +119,12 @@ impl Machine for Matrix {
         }
     }
 
-    #[inline]
+    #[inline(always)]
     fn new_ietf(state: &ChaChaNaked) -> Self {
         unsafe {
             let mut result = Matrix {
                 state: [[
-                    _mm256_broadcastsi128_si256(transmute(ROW_A)),
+                    _mm256_broadcastsi128_si256(transmute(state.row_a)),
                     _mm256_broadcastsi128_si256(transmute(state.row_b)),
                     _mm256_broadcastsi128_si256(transmute(state.row_c)),
                     _mm256_broadcastsi128_si256(transmute(state.row_d)),
@@ -125,7 +138,7 @@ impl Machine for Matrix {
         }
     }
 
-    #[inline]
+    #[inline(always)]
     fn increment_djb(&mut self) {
         unsafe {
             let increment = _mm256_set_epi64x(0, DEPTH as i64, 0, DEPTH as i64);
@@ -134,7 +147,7 @@ impl Machine for Matrix {
         }
     }
 
-    #[inline]
+    #[inline(always)]
     fn increment_ietf(&mut self) {
         unsafe {
             let increment = _mm256_set_epi32(0, 0, 0, DEPTH as i32, 0, 0, 0, DEPTH as i32);
@@ -143,7 +156,7 @@ impl Machine for Matrix {
         }
     }
 
-    #[inline]
+    #[inline(always)]
     fn double_round(&mut self) {
         // Column rounds
         self.quarter_round();
@@ -153,7 +166,15 @@ impl Machine for Matrix {
         self.unmake_diagonal();
     }
 
-    #[inline]
+    #[inline(always)]
+    fn apply<R: DoubleRounds, V: Variant, const XOR: bool>(
+        state: &ChaChaNaked,
+        bufs: &mut [[u8; BUF_LEN_U8]],
+    ) {
+        apply_interleaved::<Self, R, V, XOR, INTERLEAVE>(state, bufs);
+    }
+
+    #[inline(always)]
     fn fetch_result(self, buf: &mut [u8; BUF_LEN_U8]) {
         unsafe {
             *buf = transmute([
@@ -1,4 +1,6 @@
+use crate::rounds::*;
 use crate::util::*;
+use crate::variations::*;
 #[cfg(target_arch = "x86")]
 use core::arch::x86::*;
 #[cfg(target_arch = "x86_64")]
@@ -6,6 +8,12 @@ use core::arch::x86_64::*;
 use core::mem::transmute;
 use core::ops::Add;
 
+/// Number of `Matrix`es [`Machine::apply`] keeps in flight at once (16 blocks). Each of them
+/// takes 4 of the 32 zmm registers, plus 4 more for the copy of its initial state, and going
+/// any further only pushes more of them onto the stack.
+const INTERLEAVE: usize = 4;
+
+/// AVX-512 backend, processing all four ChaCha instances in each 512-bit register.
 #[derive(Clone)]
 #[repr(C)]
 pub struct Matrix {
@@ -15,7 +23,7 @@ pub struct Matrix {
 impl Add for Matrix {
     type Output = Self;
 
-    #[inline]
+    #[inline(always)]
     fn add(mut self, rhs: Self) -> Self::Output {
         unsafe {
             for i in 0..self.state.len() {
@@ -27,7 +35,7 @@ impl Add for Matrix {
 }
 
 impl Matrix {
-    #[inline]
+    #[inline(always)]
     fn quarter_round(&mut self) {
         unsafe {
             self.state[0] = _mm512_add_epi32(self.state[0], self.state[1]);
@@ -48,7 +56,7 @@ impl Matrix {
         }
     }
 
-    #[inline]
+    #[inline(always)]
     fn make_diagonal(&mut self) {
         unsafe {
             self.state[0] = _mm512_shuffle_epi32(self.state[0], 0b_10_01_00_11);
@@ -57,7 +65,7 @@ impl Matrix {
         }
     }
 
-    #[inline]
+    #[inline(always)]
     fn unmake_diagonal(&mut self) {
         unsafe {
             self.state[2] = _mm512_shuffle_epi32(self.state[2], 0b_10_01_00_11);
@@ -68,12 +76,12 @@ impl Matrix {
 }
 
 impl Machine for Matrix {
-    #[inline]
+    #[inline(always)]
     fn new_djb(state: &ChaChaNaked) -> Self {
         unsafe {
             let mut result = Matrix {
                 state: [
-                    _mm512_broadcast_i32x4(transmute(ROW_A)),
+                    _mm512_broadcast_i32x4(transmute(state.row_a)),
                     _mm512_broadcast_i32x4(transmute(state.row_b)),
                     _mm512_broadcast_i32x4(transmute(state.row_c)),
                     _mm512_broadcast_i32x4(transmute(state.row_d)),
@@ -85,12 +93,12 @@ impl Machine for Matrix {
         }
     }
 
-    #[inline]
+    #[inline(always)]
     fn new_ietf(state: &ChaChaNaked) -> Self {
         unsafe {
             let mut result = Matrix {
                 state: [
-                    _mm512_broadcast_i32x4(transmute(ROW_A)),
+                    _mm512_broadcast_i32x4(transmute(state.row_a)),
                     _mm512_broadcast_i32x4(transmute(state.row_b)),
                     _mm512_broadcast_i32x4(transmute(state.row_c)),
                     _mm512_broadcast_i32x4(transmute(state.row_d)),
@@ -104,7 +112,7 @@ impl Machine for Matrix {
         }
     }
 
-    #[inline]
+    #[inline(always)]
     fn increment_djb(&mut self) {
         unsafe {
             self.state[3] = _mm512_add_epi64(
@@ -123,7 +131,7 @@ impl Machine for Matrix {
         }
     }
 
-    #[inline]
+    #[inline(always)]
     fn increment_ietf(&mut self) {
         unsafe {
             self.state[3] = _mm512_add_epi32(
@@ -150,7 +158,7 @@ impl Machine for Matrix {
         }
     }
 
-    #[inline]
+    #[inline(always)]
     fn double_round(&mut self) {
         // Column rounds
         self.quarter_round();
@@ -160,7 +168,15 @@ impl Machine for Matrix {
         self.unmake_diagonal();
     }
 
-    #[inline]
+    #[inline(always)]
+    fn apply<R: DoubleRounds, V: Variant, const XOR: bool>(
+        state: &ChaChaNaked,
+        bufs: &mut [[u8; BUF_LEN_U8]],
+    ) {
+        apply_interleaved::<Self, R, V, XOR, INTERLEAVE>(state, bufs);
+    }
+
+    #[inline(always)]
     fn fetch_result(self, buf: &mut [u8; BUF_LEN_U8]) {
         unsafe {
             *buf = transmute([
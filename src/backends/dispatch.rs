@@ -0,0 +1,395 @@
+/*!
+Backend which picks the implementation to use at runtime, rather than at compile time.
+
+Every operation except [`Machine::apply`] is handled by `Base` (the widest backend enabled at
+compile time), since those only ever produce a batch or two at once and aren't worth dispatching.
+[`Machine::apply`] is where the bulk of the output of the slice methods (`fill`, `xor`, etc.) is
+produced, so it's handed to whichever [`Backend`] is active each time it's called. On x86, the
+wider backends are compiled as `#[target_feature]` functions, so they can be used by binaries
+that weren't compiled for them. Every backend produces exactly the same output, so mixing them
+between calls (or even within one) makes no difference to the keystream.
+
+The active backend is the widest one the CPU supports (or the fastest one, with the `autotune`
+feature), unless overridden by [`force_backend`](crate::force_backend) or the `CHACHACHA_BACKEND`
+environment variable.
+Detection uses `cpuid` directly, so it's available without `std`, and only runs once.
+*/
+
+use super::soft;
+#[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
+use super::{avx2, avx512, sse2, ssse3};
+use crate::rounds::*;
+use crate::util::*;
+use crate::variations::*;
+use crate::{Backend, BackendReason};
+#[cfg(target_arch = "x86")]
+use core::arch::x86::*;
+#[cfg(target_arch = "x86_64")]
+use core::arch::x86_64::*;
+use core::ops::Add;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+cfg_if::cfg_if! {
+    if #[cfg(target_feature = "avx512f")] {
+        type Base = avx512::Matrix;
+    } else if #[cfg(target_feature = "avx2")] {
+        type Base = avx2::Matrix;
+    } else if #[cfg(target_feature = "ssse3")] {
+        type Base = ssse3::Matrix;
+    } else if #[cfg(target_feature = "sse2")] {
+        type Base = sse2::Matrix;
+    } else if #[cfg(all(target_feature = "neon", any(not(target_arch = "arm"), feature = "unstable-arm-neon")))] {
+        type Base = super::neon::Matrix;
+    } else {
+        type Base = soft::Matrix;
+    }
+}
+
+/// Backend used by [`Matrix::apply`] in the low bits, and the [`BackendReason`] it was picked
+/// for in the bits above [`REASON_SHIFT`], with 0 meaning it hasn't been chosen yet.
+static ACTIVE: AtomicU8 = AtomicU8::new(0);
+/// Result of [`detect`], with 0 meaning it hasn't run yet.
+static DETECTED: AtomicU8 = AtomicU8::new(0);
+
+const REASON_SHIFT: u32 = 4;
+const BACKEND_MASK: u8 = (1 << REASON_SHIFT) - 1;
+
+#[inline]
+const fn pack(backend: Backend, reason: BackendReason) -> u8 {
+    backend as u8 | (reason as u8) << REASON_SHIFT
+}
+
+#[inline]
+fn unpack(value: u8) -> Option<(Backend, BackendReason)> {
+    let backend = Backend::from_u8(value & BACKEND_MASK)?;
+    let reason = BackendReason::from_u8(value >> REASON_SHIFT)?;
+    Some((backend, reason))
+}
+
+/// Returns the backend currently used for bulk output.
+#[inline]
+pub fn active() -> Backend {
+    active_with_reason().0
+}
+
+/// Returns the backend currently used for bulk output, along with why it's being used.
+#[inline]
+pub fn active_with_reason() -> (Backend, BackendReason) {
+    match unpack(ACTIVE.load(Ordering::Relaxed)) {
+        Some(active) => active,
+        None => choose(),
+    }
+}
+
+/// Replaces the active backend, or goes back to choosing one automatically if `None`.
+#[inline]
+pub fn set_active(backend: Option<Backend>) {
+    ACTIVE.store(
+        backend.map_or(0, |backend| pack(backend, BackendReason::Forced)),
+        Ordering::Relaxed,
+    );
+}
+
+#[cold]
+fn choose() -> (Backend, BackendReason) {
+    #[cfg(feature = "std")]
+    let chosen = std::env::var("CHACHACHA_BACKEND")
+        .ok()
+        .and_then(|name| name.parse::<Backend>().ok())
+        .filter(|from_env| from_env.is_supported())
+        .map_or_else(automatic, |from_env| (from_env, BackendReason::Environment));
+    #[cfg(not(feature = "std"))]
+    let chosen = automatic();
+    // Don't clobber a backend that was forced in the meantime.
+    match ACTIVE.compare_exchange(
+        0,
+        pack(chosen.0, chosen.1),
+        Ordering::Relaxed,
+        Ordering::Relaxed,
+    ) {
+        Ok(_) => chosen,
+        Err(current) => unpack(current).unwrap_or(chosen),
+    }
+}
+
+/// Returns the backend to use when nothing else was asked for.
+#[inline]
+fn automatic() -> (Backend, BackendReason) {
+    if cfg!(feature = "autotune") {
+        (tune(), BackendReason::Tuned)
+    } else {
+        (detected(), BackendReason::Detected)
+    }
+}
+
+/// Number of batches [`tune`] times each backend on.
+#[cfg(feature = "autotune")]
+const TUNE_BATCHES: usize = 8;
+/// Number of times [`tune`] times each backend.
+#[cfg(feature = "autotune")]
+const TUNE_RUNS: usize = 3;
+
+/// Returns the fastest backend supported by the running CPU, by timing how long each one takes
+/// to produce [`TUNE_BATCHES`] batches of output (keeping the best of [`TUNE_RUNS`] attempts).
+#[cfg(feature = "autotune")]
+#[cold]
+pub fn tune() -> Backend {
+    use std::time::{Duration, Instant};
+
+    let state = ChaChaNaked {
+        row_a: ROW_A,
+        row_b: Row { u32x4: [1; 4] },
+        row_c: Row { u32x4: [2; 4] },
+        row_d: Row { u32x4: [0; 4] },
+    };
+    let mut bufs = [[0; BUF_LEN_U8]; TUNE_BATCHES];
+    let mut best = (detected(), Duration::MAX);
+    // Widest first, so it wins any ties.
+    for backend in Backend::ALL.into_iter().rev() {
+        if !backend.is_supported() {
+            continue;
+        }
+        // The first run only warms up the caches (and the wider execution units on some CPUs).
+        for run in 0..=TUNE_RUNS {
+            let start = Instant::now();
+            // Safety: only supported backends make it this far.
+            unsafe { apply_with::<R20, Djb, false>(backend, &state, &mut bufs) };
+            let elapsed = start.elapsed();
+            if run != 0 && elapsed < best.1 {
+                best = (backend, elapsed);
+            }
+            core::hint::black_box(&mut bufs);
+        }
+    }
+    best.0
+}
+
+/// Without the `autotune` feature there's nothing to time with, so this is [`detected`].
+#[cfg(not(feature = "autotune"))]
+#[inline]
+pub fn tune() -> Backend {
+    detected()
+}
+
+/// Returns the widest backend supported by the running CPU.
+#[inline]
+pub fn detected() -> Backend {
+    match Backend::from_u8(DETECTED.load(Ordering::Relaxed)) {
+        Some(backend) => backend,
+        None => {
+            let backend = detect();
+            DETECTED.store(backend as u8, Ordering::Relaxed);
+            backend
+        }
+    }
+}
+
+/// Returns `true` if `backend` can be used on the running CPU.
+#[inline]
+pub fn is_supported(backend: Backend) -> bool {
+    match (backend, detected()) {
+        (Backend::Soft, _) => true,
+        (Backend::Neon, detected) | (detected, Backend::Neon) => backend == detected,
+        // The x86 backends are declared in order, and every CPU supporting
+        // one of them also supports all of the ones declared before it.
+        (_, detected) => backend as u8 <= detected as u8,
+    }
+}
+
+#[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
+#[cold]
+fn detect() -> Backend {
+    // SGX enclaves aren't allowed to execute `cpuid`.
+    if cfg!(target_env = "sgx") {
+        return Backend::Sse2;
+    }
+    // `cpuid` is present on every CPU with SSE2, which is already required for x86.
+    #[allow(unused_unsafe)]
+    let (max_leaf, leaf_1) = unsafe { (__cpuid(0).eax, __cpuid(1)) };
+    let has_ssse3 = leaf_1.ecx & (1 << 9) != 0;
+    let has_osxsave = leaf_1.ecx & (1 << 27) != 0;
+    let has_avx = leaf_1.ecx & (1 << 28) != 0;
+    let fallback = if has_ssse3 {
+        Backend::Ssse3
+    } else {
+        Backend::Sse2
+    };
+    if !has_osxsave || !has_avx {
+        return fallback;
+    }
+    // Safety: OSXSAVE being set means `xgetbv` is available. The CPU supporting the wider
+    // registers means nothing unless the OS actually saves them on context switches.
+    let xcr0 = unsafe { _xgetbv(0) };
+    // SSE and AVX state.
+    let os_avx = xcr0 & 0b110 == 0b110;
+    // Plus the opmask registers and both halves of the upper zmm registers.
+    let os_avx512 = xcr0 & 0b1110_0110 == 0b1110_0110;
+    if !os_avx {
+        return fallback;
+    }
+    if max_leaf < 7 {
+        return Backend::Avx;
+    }
+    #[allow(unused_unsafe)]
+    let leaf_7 = unsafe { __cpuid_count(7, 0) };
+    if os_avx512 && leaf_7.ebx & (1 << 16) != 0 {
+        Backend::Avx512
+    } else if leaf_7.ebx & (1 << 5) != 0 {
+        Backend::Avx2
+    } else {
+        Backend::Avx
+    }
+}
+
+#[cfg(not(any(target_arch = "x86_64", target_arch = "x86")))]
+#[cold]
+fn detect() -> Backend {
+    if cfg!(all(
+        target_feature = "neon",
+        any(not(target_arch = "arm"), feature = "unstable-arm-neon")
+    )) {
+        Backend::Neon
+    } else {
+        Backend::Soft
+    }
+}
+
+/// Runtime dispatched backend, see the module documentation.
+#[derive(Clone)]
+#[repr(transparent)]
+pub struct Matrix(Base);
+
+impl Add for Matrix {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, rhs: Self) -> Self::Output {
+        Self(self.0 + rhs.0)
+    }
+}
+
+impl Machine for Matrix {
+    #[inline]
+    fn new_djb(state: &ChaChaNaked) -> Self {
+        Self(Base::new_djb(state))
+    }
+
+    #[inline]
+    fn new_ietf(state: &ChaChaNaked) -> Self {
+        Self(Base::new_ietf(state))
+    }
+
+    #[inline]
+    fn increment_djb(&mut self) {
+        self.0.increment_djb();
+    }
+
+    #[inline]
+    fn increment_ietf(&mut self) {
+        self.0.increment_ietf();
+    }
+
+    #[inline]
+    fn double_round(&mut self) {
+        self.0.double_round();
+    }
+
+    #[inline]
+    fn apply<R: DoubleRounds, V: Variant, const XOR: bool>(
+        state: &ChaChaNaked,
+        bufs: &mut [[u8; BUF_LEN_U8]],
+    ) {
+        // Safety: the active backend is always one the CPU supports.
+        unsafe { apply_with::<R, V, XOR>(active(), state, bufs) }
+    }
+
+    #[inline]
+    fn fetch_result(self, buf: &mut [u8; BUF_LEN_U8]) {
+        self.0.fetch_result(buf);
+    }
+
+    #[inline]
+    fn xor_result(self, buf: &mut [u8; BUF_LEN_U8]) {
+        self.0.xor_result(buf);
+    }
+
+    #[inline]
+    fn xor_result_b2b(self, src: &[u8; BUF_LEN_U8], dst: &mut [u8; BUF_LEN_U8]) {
+        self.0.xor_result_b2b(src, dst);
+    }
+}
+
+/// Produces output for `bufs` with `backend`.
+///
+/// # Safety
+///
+/// `backend` must be supported by the running CPU.
+#[inline]
+pub(crate) unsafe fn apply_with<R: DoubleRounds, V: Variant, const XOR: bool>(
+    backend: Backend,
+    state: &ChaChaNaked,
+    bufs: &mut [[u8; BUF_LEN_U8]],
+) {
+    match backend {
+        #[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
+        Backend::Avx512 => unsafe { apply_avx512::<R, V, XOR>(state, bufs) },
+        #[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
+        Backend::Avx2 => unsafe { apply_avx2::<R, V, XOR>(state, bufs) },
+        #[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
+        Backend::Avx => unsafe { apply_avx::<R, V, XOR>(state, bufs) },
+        #[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
+        Backend::Ssse3 => unsafe { apply_ssse3::<R, V, XOR>(state, bufs) },
+        #[cfg(target_feature = "sse2")]
+        Backend::Sse2 => sse2::Matrix::apply::<R, V, XOR>(state, bufs),
+        #[cfg(all(
+            target_feature = "neon",
+            any(not(target_arch = "arm"), feature = "unstable-arm-neon")
+        ))]
+        Backend::Neon => super::neon::Matrix::apply::<R, V, XOR>(state, bufs),
+        _ => soft::Matrix::apply::<R, V, XOR>(state, bufs),
+    }
+}
+
+// Everything the generic `apply` calls is `#[inline(always)]` in these backends, so it all gets
+// compiled with these features enabled, including the intrinsics. Anything left as a call would
+// be compiled without them, turning every intrinsic inside it into a call of its own.
+
+#[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
+#[target_feature(enable = "ssse3")]
+fn apply_ssse3<R: DoubleRounds, V: Variant, const XOR: bool>(
+    state: &ChaChaNaked,
+    bufs: &mut [[u8; BUF_LEN_U8]],
+) {
+    ssse3::Matrix::apply::<R, V, XOR>(state, bufs);
+}
+
+// There's no separate AVX backend, since AVX only widened the floating point operations.
+// What it does add is the VEX encoding of the existing 128-bit integer instructions, which
+// takes three operands instead of overwriting one of its inputs, and so saves most of the
+// register copies the SSSE3 backend needs. Compiling it with AVX enabled is all it takes.
+#[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
+#[target_feature(enable = "avx")]
+fn apply_avx<R: DoubleRounds, V: Variant, const XOR: bool>(
+    state: &ChaChaNaked,
+    bufs: &mut [[u8; BUF_LEN_U8]],
+) {
+    ssse3::Matrix::apply::<R, V, XOR>(state, bufs);
+}
+
+#[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
+#[target_feature(enable = "avx2")]
+fn apply_avx2<R: DoubleRounds, V: Variant, const XOR: bool>(
+    state: &ChaChaNaked,
+    bufs: &mut [[u8; BUF_LEN_U8]],
+) {
+    avx2::Matrix::apply::<R, V, XOR>(state, bufs);
+}
+
+#[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
+#[target_feature(enable = "avx512f")]
+fn apply_avx512<R: DoubleRounds, V: Variant, const XOR: bool>(
+    state: &ChaChaNaked,
+    bufs: &mut [[u8; BUF_LEN_U8]],
+) {
+    avx512::Matrix::apply::<R, V, XOR>(state, bufs);
+}
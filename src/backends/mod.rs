@@ -2,7 +2,8 @@
 Module containing all the non-portable shit. Only explicitly re-exports the widest
 implementation available as the definitive `Matrix` for the entire submodule, but still enables
 whatever other modules are available on the target system. This is done for testing purposes,
-and none of it is accessible by the end-user of this crate.
+and so that users can force a specific implementation through the backend-specific type aliases
+(`ChaCha20DjbSse2`, etc.), which is the only part of this module exposed by the crate.
 
 A ChaCha instance holds 16 32-bit integers (their signedness is irrelevant),
 in the form of a 4-by-4 matrix. The first 4 integers are constant values from the string "`expand 32-byte k`",
@@ -28,6 +29,10 @@ Counter  Counter  Nonce  Nonce
 An alternative layout, suggested by the IETF, uses only a single 32-bit integer for the counter
 and three of them for nonces. Both implementations are provided by this crate.
 
+The original specification also allows for a 128-bit key, which is repeated to fill both key rows
+and swaps the constants for "`expand 16-byte k`". The constant row is therefore passed along with
+the rest of the state rather than hardcoded into the backends.
+
 The soft implementation is the [reference implementation], but batched to (in theory) increase performance and maintain
 API compatability with the other impls. The result isn't as fast as the manually vectorized variants, but is
 better than running a pure reference implementation four times sequentially.
@@ -37,46 +42,56 @@ in the gaps. [This commit] is used as a reference for ordering in the diagonaliz
 seem to make any difference on modern machines, but this should hopefully prevent issues with older CPUs.
 
 TLDR is we process four ChaCha instances at once, working on them in terms of their rows instead of individual elements.
-SSE2/Neon are only wide enough for individual instances to be processed, but AVX2 allows for processesing two instances at once
-and AVX512 allows processesing all four at once.
+SSE2/SSSE3/Neon are only wide enough for individual instances to be processed, but AVX2 allows for processesing two instances at once
+and AVX512 allows processesing all four at once. SSSE3 is SSE2 with the rotations by 16 and 8 done as byte shuffles,
+and the AVX tier of `dispatch` is just the SSSE3 backend compiled with AVX (and so with VEX encoded instructions).
+
+`Matrix` is always the `dispatch` backend, which uses whichever of the others is active (normally the widest
+one the running CPU supports) for bulk output. On x86, the SSSE3, AVX2, and AVX512 backends are compiled into every
+build, regardless of the target features enabled at compile time, so they can be chosen at runtime.
 
 [reference implementation]: https://en.wikipedia.org/wiki/Salsa20#ChaCha_variant
 [this paper]: https://eprint.iacr.org/2013/759
 [this commit]: https://github.com/cryptocorrosion/cryptocorrosion/commit/8608f02b1fd8847cdaeb09c965f7ea26faa2039c
 */
 
+// Only the fallback for targets without a vectorized implementation, but it's always
+// compiled so the tests can check it against the reference implementation, and so
+// it can be explicitly chosen with the `ChaCha*Soft` aliases.
 pub mod soft;
 
+// Always the default `Matrix`, choosing among the others at runtime.
+pub mod dispatch;
+pub use dispatch::Matrix;
+
 cfg_if::cfg_if! {
     if #[cfg(any(target_arch = "x86_64", target_arch = "x86"))] {
-        #[cfg(target_feature = "avx512f")]
+        // The wider backends are always compiled, so `dispatch` can use them whenever the
+        // running CPU supports them, even if the binary wasn't compiled for that CPU.
         pub mod avx512;
-        #[cfg(target_feature = "avx2")]
         pub mod avx2;
+        pub mod ssse3;
         #[cfg(target_feature = "sse2")]
         pub mod sse2;
-
-        cfg_if::cfg_if! {
-            if #[cfg(target_feature = "avx512f")] {
-                pub use avx512::Matrix;
-            } else if #[cfg(target_feature = "avx2")] {
-                pub use avx2::Matrix;
-            } else if #[cfg(target_feature = "sse2")] {
-                pub use sse2::Matrix;
-            } else {
-                compile_error!("targeting x86 without sse2 is unsupported");
-            }
-        }
+        #[cfg(not(target_feature = "sse2"))]
+        compile_error!("targeting x86 without sse2 is unsupported");
     } else if #[cfg(any(target_arch = "aarch64", target_arch = "arm64ec"))] {
         cfg_if::cfg_if! {
             if #[cfg(target_feature = "neon")] {
                 pub mod neon;
-                pub use neon::Matrix;
             } else {
                 compile_error!("neon is a default feature of arm64");
             }
         }
-    } else {
-        pub use soft::Matrix;
+    } else if #[cfg(target_arch = "arm")] {
+        // The Neon intrinsics of 32-bit Arm are still unstable, so using them needs a nightly
+        // compiler and the `unstable-arm-neon` feature. Otherwise (or on cores without Neon,
+        // like the Cortex-M series) this falls back to `soft`, which `active_backend` reports.
+        //
+        // Cortex-M55/M85 do have 128-bit vectors through Helium (MVE), but there's no way to
+        // use them from Rust yet: `core::arch` has no MVE intrinsics, and `asm!` only accepts
+        // q registers as operands with Neon enabled. Until either changes, `soft` it is.
+        #[cfg(all(target_feature = "neon", feature = "unstable-arm-neon"))]
+        pub mod neon;
     }
 }
@@ -1,8 +1,13 @@
 use crate::util::*;
+#[cfg(any(target_arch = "aarch64", target_arch = "arm64ec"))]
 use core::arch::aarch64::*;
+#[cfg(target_arch = "arm")]
+use core::arch::arm::*;
 use core::mem::transmute;
 use core::ops::Add;
 
+/// Neon backend (for both AArch64 and 32-bit Arm), processing a single ChaCha instance
+/// per 128-bit register.
 #[derive(Clone)]
 #[repr(C)]
 pub struct Matrix {
@@ -33,26 +38,38 @@ impl Add for Matrix {
     }
 }
 
+// Shifting left and then inserting the bits shifted out back in with `sri` is one
+// instruction shorter than shifting both ways and oring the results together.
+//
+// The SHA3 extension doesn't help here, despite looking like it should: its rotations (`xar`)
+// only work on 64-bit lanes (the 32-bit form is SVE2-only), and none of the xors in a quarter
+// round have a third input for `eor3` to fold in.
 macro_rules! rotate_left_epi32 {
     ($value:expr, $LEFT_SHIFT:expr) => {{
         const RIGHT_SHIFT: i32 = 32 - $LEFT_SHIFT;
         let left_shift = vshlq_n_u32($value, $LEFT_SHIFT);
-        let right_shift = vshrq_n_u32($value, RIGHT_SHIFT);
-        vorrq_u32(left_shift, right_shift)
+        vsriq_n_u32(left_shift, $value, RIGHT_SHIFT)
     }};
 }
 
+/// Byte indices (within each 32-bit lane) to gather for the rotation by 8.
+#[cfg(not(target_arch = "arm"))]
+const ROTATE_8: [u8; 16] = [3, 0, 1, 2, 7, 4, 5, 6, 11, 8, 9, 10, 15, 12, 13, 14];
+
 impl Matrix {
     #[inline]
     fn quarter_round(&mut self) {
         unsafe {
+            #[cfg(not(target_arch = "arm"))]
+            let rotate_8 = vld1q_u8(ROTATE_8.as_ptr());
             for [a, b, c, d] in self.state.iter_mut().map(|v| {
                 let u32x4_4: &mut [uint32x4_t; ROWS] = transmute(v);
                 u32x4_4
             }) {
                 *a = vaddq_u32(*a, *b);
                 *d = veorq_u32(*d, *a);
-                *d = rotate_left_epi32!(*d, 16);
+                // Swapping the halves of each lane is a rotation by 16.
+                *d = vreinterpretq_u32_u16(vrev32q_u16(vreinterpretq_u16_u32(*d)));
 
                 *c = vaddq_u32(*c, *d);
                 *b = veorq_u32(*b, *c);
@@ -60,7 +77,15 @@ impl Matrix {
 
                 *a = vaddq_u32(*a, *b);
                 *d = veorq_u32(*d, *a);
-                *d = rotate_left_epi32!(*d, 8);
+                // 32-bit Arm doesn't have `tbl` on whole q registers.
+                #[cfg(not(target_arch = "arm"))]
+                {
+                    *d = vreinterpretq_u32_u8(vqtbl1q_u8(vreinterpretq_u8_u32(*d), rotate_8));
+                }
+                #[cfg(target_arch = "arm")]
+                {
+                    *d = rotate_left_epi32!(*d, 8);
+                }
 
                 *c = vaddq_u32(*c, *d);
                 *b = veorq_u32(*b, *c);
@@ -104,7 +129,7 @@ impl Machine for Matrix {
         unsafe {
             let mut result = Matrix {
                 state: [[
-                    transmute(ROW_A),
+                    transmute(state.row_a),
                     transmute(state.row_b),
                     transmute(state.row_c),
                     transmute(state.row_d),
@@ -131,7 +156,7 @@ impl Machine for Matrix {
         unsafe {
             let mut result = Matrix {
                 state: [[
-                    transmute(ROW_A),
+                    transmute(state.row_a),
                     transmute(state.row_b),
                     transmute(state.row_c),
                     transmute(state.row_d),
@@ -2,6 +2,7 @@ use crate::util::*;
 use core::mem::transmute;
 use core::ops::Add;
 
+/// Portable backend, which doesn't rely on any SIMD instructions.
 #[derive(Clone)]
 #[repr(C)]
 pub struct Matrix {
@@ -62,7 +63,7 @@ impl Machine for Matrix {
         unsafe {
             let mut result = Matrix {
                 state: [InternalMatrix {
-                    rows: [ROW_A, state.row_b, state.row_c, state.row_d],
+                    rows: [state.row_a, state.row_b, state.row_c, state.row_d],
                 }; DEPTH],
             };
             result.state[1].rows[3].u64x2[0] = result.state[1].rows[3].u64x2[0].wrapping_add(1);
@@ -77,7 +78,7 @@ impl Machine for Matrix {
         unsafe {
             let mut result = Matrix {
                 state: [InternalMatrix {
-                    rows: [ROW_A, state.row_b, state.row_c, state.row_d],
+                    rows: [state.row_a, state.row_b, state.row_c, state.row_d],
                 }; DEPTH],
             };
             result.state[1].rows[3].u32x4[0] = result.state[1].rows[3].u32x4[0].wrapping_add(1);
@@ -6,6 +6,7 @@ use core::arch::x86_64::*;
 use core::mem::transmute;
 use core::ops::Add;
 
+/// SSE2 backend, processing a single ChaCha instance per 128-bit register.
 #[derive(Clone)]
 #[repr(C)]
 pub struct Matrix {
@@ -90,7 +91,7 @@ impl Machine for Matrix {
         unsafe {
             let mut result = Matrix {
                 state: [[
-                    transmute(ROW_A),
+                    transmute(state.row_a),
                     transmute(state.row_b),
                     transmute(state.row_c),
                     transmute(state.row_d),
@@ -108,7 +109,7 @@ impl Machine for Matrix {
         unsafe {
             let mut result = Matrix {
                 state: [[
-                    transmute(ROW_A),
+                    transmute(state.row_a),
                     transmute(state.row_b),
                     transmute(state.row_c),
                     transmute(state.row_d),
@@ -0,0 +1,137 @@
+/*!
+Module containing [`ChaChaBuilder`], which constructs a ChaCha instance one named parameter at a
+time instead of through the positional arguments of [`ChaChaCore::new`] or a flat seed.
+
+The round count and variant are type parameters of the builder, chosen by [`ChaChaBuilder::rounds`]
+and [`ChaChaBuilder::variant`] (usually inferred from the type being built), so the lengths of the
+nonce and counter can be checked against the variant that actually ends up being used.
+*/
+
+use crate::rounds::*;
+use crate::variations::*;
+use crate::{ChaCha, ChaChaCore, Error};
+use core::marker::PhantomData;
+
+/// Builder for ChaCha instances with `R` rounds of the `V` variant.
+///
+/// Only the key is required; the nonce and counter both default to 0.
+///
+/// ```
+/// use chachacha::{ChaCha20Ietf, ChaChaBuilder};
+///
+/// let mut chacha: ChaCha20Ietf = ChaChaBuilder::new()
+///     .key([69; 32])
+///     .nonce(&[1; 12])
+///     .counter(7)
+///     .rounds()
+///     .variant()
+///     .build()
+///     .unwrap();
+/// assert_eq!(chacha.get_counter(), 7);
+/// assert_eq!(
+///     chacha.get_block(),
+///     ChaCha20Ietf::from_bytes([69; 32], 7, [1; 12]).get_block()
+/// );
+/// ```
+pub struct ChaChaBuilder<R = R20, V = Djb> {
+    key: Option<[u8; 32]>,
+    nonce: [u8; 24],
+    nonce_len: Option<usize>,
+    counter: u64,
+    _phantom: PhantomData<(R, V)>,
+}
+
+impl ChaChaBuilder {
+    /// Creates a new `ChaChaBuilder` instance, defaulting to 20 rounds of the [`Djb`] variant.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            key: None,
+            nonce: [0; 24],
+            nonce_len: None,
+            counter: 0,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl Default for ChaChaBuilder {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R, V> ChaChaBuilder<R, V>
+where
+    R: DoubleRounds,
+    V: Variant,
+{
+    /// Sets the 256-bit key.
+    #[inline]
+    pub fn key(mut self, key: [u8; 32]) -> Self {
+        self.key = Some(key);
+        self
+    }
+
+    /// Sets the nonce, which must be 8 bytes for [`Djb`], 12 bytes for [`Ietf`],
+    /// and 24 bytes for [`XDjb`] by the time [`Self::build`] is called.
+    /// Nonces longer than 24 bytes are always invalid.
+    #[inline]
+    pub fn nonce(mut self, nonce: &[u8]) -> Self {
+        let len = nonce.len().min(self.nonce.len());
+        self.nonce = [0; 24];
+        self.nonce[..len].copy_from_slice(&nonce[..len]);
+        self.nonce_len = Some(nonce.len());
+        self
+    }
+
+    /// Sets the block counter, which must fit in 32 bits for [`Ietf`].
+    #[inline]
+    pub fn counter(mut self, counter: u64) -> Self {
+        self.counter = counter;
+        self
+    }
+
+    /// Changes the amount of rounds to `R2`.
+    #[inline]
+    pub fn rounds<R2: DoubleRounds>(self) -> ChaChaBuilder<R2, V> {
+        self.retype()
+    }
+
+    /// Changes the variant to `V2`.
+    #[inline]
+    pub fn variant<V2: Variant>(self) -> ChaChaBuilder<R, V2> {
+        self.retype()
+    }
+
+    /// Creates the ChaCha instance.
+    ///
+    /// Returns [`Error::MissingKey`] if no key was set, [`Error::InvalidLength`] if the nonce
+    /// has the wrong length for `V`, and [`Error::CounterExhausted`] if the counter doesn't fit.
+    #[inline]
+    pub fn build(mut self) -> Result<ChaCha<R, V>, Error> {
+        let key = self.key.take().ok_or(Error::MissingKey)?;
+        let nonce_len = self.nonce_len.unwrap_or(V::NONCE_LEN);
+        if nonce_len > self.nonce.len() {
+            return Err(Error::InvalidLength);
+        }
+        if matches!(V::VAR, Variants::Ietf) && self.counter > u32::MAX as u64 {
+            return Err(Error::CounterExhausted);
+        }
+        let mut result = ChaChaCore::new_from_slices(&key, &self.nonce[..nonce_len])?;
+        result.set_counter(self.counter);
+        Ok(result)
+    }
+
+    #[inline]
+    fn retype<R2, V2>(self) -> ChaChaBuilder<R2, V2> {
+        ChaChaBuilder {
+            key: self.key,
+            nonce: self.nonce,
+            nonce_len: self.nonce_len,
+            counter: self.counter,
+            _phantom: PhantomData,
+        }
+    }
+}
@@ -8,18 +8,65 @@ to abstract the ChaCha algorithm to the most powerful vectorization model availa
 // tell we're filling it before it's eventually used.
 #![allow(clippy::uninit_assumed_init, invalid_value)]
 
+use crate::advance::CounterAdvance;
+use crate::hchacha::hchacha;
+use crate::key::{KeyMaterial, Nonce64, Nonce96};
+use crate::overflow::CounterOverflow;
 use crate::rounds::*;
 use crate::util::*;
 use crate::variations::*;
+#[cfg(feature = "alloc")]
+use alloc::{string::String, vec, vec::Vec};
+use core::fmt;
 use core::marker::PhantomData;
 use core::mem::{MaybeUninit, transmute};
+use core::ops::Range;
 use core::ptr::copy_nonoverlapping;
 
+/// ChaCha instance using the `M` backend, with `R` rounds of the `V` variant.
+///
+/// Usually used through one of the type aliases at the crate root (such as `ChaCha20Djb`).
+///
+/// # Size and word-oriented output
+///
+/// Every instance carries its own 256-byte output buffer for the word-oriented methods
+/// (`next_u32`, `next_f64`, etc.), making it 352 bytes in total on 64-bit targets. That's
+/// copied by every clone (including [`Self::fork`], [`Self::substream`], etc.), so instances
+/// which only ever use the slice methods are better kept behind a reference than cloned.
+///
+/// The buffer is refilled a whole batch (`DEPTH` blocks) at a time, advancing the counter past
+/// that batch, and the slice/block methods never touch it. So when the two kinds of methods are
+/// mixed, the slice methods continue from the end of the buffered batch (not from the next
+/// unused word), while the word-oriented methods keep handing out what's left in the buffer.
+/// Anything which moves the counter ([`Self::set_counter`], [`Self::seek`], etc.) discards it.
+///
+/// ```
+/// use chachacha::ChaCha20Djb;
+///
+/// let mut chacha = ChaCha20Djb::new([1; 8], 0, [0; 3]);
+/// chacha.next_u32();
+/// assert_eq!(chacha.get_counter(), 4);
+/// chacha.fill(&mut [0; 8]);
+/// assert_eq!(chacha.get_counter(), 5);
+/// ```
 #[repr(C)]
 pub struct ChaChaCore<M, R, V> {
+    /// Almost always [`ROW_A`], unless created with a 128-bit key.
+    row_a: Row,
     row_b: Row,
     row_c: Row,
     row_d: Row,
+    /// Output buffer used by the word-oriented methods (`next_u32`, `next_f64`, etc.).
+    /// Completely independent of the slice/block methods, which never touch it.
+    buf: [u8; BUF_LEN_U8],
+    index: usize,
+    /// Bytes at the start of the block at the current counter which have already been consumed,
+    /// so the next slice method skips them. Always 0 unless set by [`Self::seek`]
+    /// or left behind by [`CounterAdvance::Exact`].
+    offset: usize,
+    /// Only ever consulted by [`Djb`], see [`CounterOverflow`].
+    overflow: CounterOverflow,
+    advance: CounterAdvance,
     _phantom: PhantomData<(M, R, V)>,
 }
 
@@ -47,21 +94,381 @@ impl<M, R, V> From<u64> for ChaChaCore<M, R, V> {
 impl<M, R, V> From<[u8; SEED_LEN_U8]> for ChaChaCore<M, R, V> {
     #[inline]
     fn from(value: [u8; SEED_LEN_U8]) -> Self {
-        unsafe { transmute(value) }
+        let [row_b, row_c, row_d] = unsafe { transmute(value) };
+        Self::from_rows(row_b, row_c, row_d)
     }
 }
 
 impl<M, R, V> From<[u32; SEED_LEN_U32]> for ChaChaCore<M, R, V> {
     #[inline]
     fn from(value: [u32; SEED_LEN_U32]) -> Self {
-        unsafe { transmute(value) }
+        let [row_b, row_c, row_d] = unsafe { transmute(value) };
+        Self::from_rows(row_b, row_c, row_d)
     }
 }
 
 impl<M, R, V> From<[u64; SEED_LEN_U64]> for ChaChaCore<M, R, V> {
     #[inline]
     fn from(value: [u64; SEED_LEN_U64]) -> Self {
-        unsafe { transmute(value) }
+        let [row_b, row_c, row_d] = unsafe { transmute(value) };
+        Self::from_rows(row_b, row_c, row_d)
+    }
+}
+
+impl<M, R, V> Clone for ChaChaCore<M, R, V> {
+    /// Copies the entire state, including any buffered output, so the
+    /// clone produces exactly the same output as `self` from here on.
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            row_a: self.row_a,
+            row_b: self.row_b,
+            row_c: self.row_c,
+            row_d: self.row_d,
+            buf: self.buf,
+            index: self.index,
+            offset: self.offset,
+            overflow: self.overflow,
+            advance: self.advance,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<M, R, V> zeroize::Zeroize for ChaChaCore<M, R, V> {
+    /// Wipes the entire state (including any buffered output), leaving
+    /// an instance that only produces the keystream of an all zero key.
+    fn zeroize(&mut self) {
+        for row in [&mut self.row_b, &mut self.row_c, &mut self.row_d] {
+            unsafe { row.u8x16.zeroize() };
+        }
+        self.row_a = ROW_A;
+        self.buf.zeroize();
+        self.index = BUF_LEN_U8;
+        self.offset = 0;
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<M, R, V> Drop for ChaChaCore<M, R, V> {
+    #[inline]
+    fn drop(&mut self) {
+        zeroize::Zeroize::zeroize(self);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<M, R, V> zeroize::ZeroizeOnDrop for ChaChaCore<M, R, V> {}
+
+impl<M, R, V> fmt::Debug for ChaChaCore<M, R, V>
+where
+    M: Machine,
+    R: DoubleRounds,
+    V: Variant,
+{
+    /// Formats everything but the key, which is redacted so instances can be logged safely.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let words = unsafe { self.row_d.u32x4 };
+        let nonce = match V::VAR {
+            Variants::Djb => &words[2..],
+            Variants::Ietf => &words[1..],
+        };
+        f.debug_struct("ChaChaCore")
+            .field("rounds", &(R::COUNT * 2))
+            .field("variant", &V::VAR)
+            .field("key", &format_args!("***"))
+            .field("counter", &self.get_counter())
+            .field("nonce", &nonce)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<M, R, V> TryFrom<&[u8]> for ChaChaCore<M, R, V> {
+    type Error = crate::Error;
+
+    /// Creates a new `ChaChaCore` instance from a seed given as a slice,
+    /// returning [`Error::InvalidLength`](crate::Error::InvalidLength)
+    /// unless it's exactly [`SEED_LEN_U8`] bytes long.
+    #[inline]
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        let seed: [u8; SEED_LEN_U8] = value.try_into().map_err(|_| crate::Error::InvalidLength)?;
+        Ok(seed.into())
+    }
+}
+
+impl<M, R, V> ChaChaCore<M, R, V> {
+    #[inline]
+    fn from_rows(row_b: Row, row_c: Row, row_d: Row) -> Self {
+        Self {
+            row_a: ROW_A,
+            row_b,
+            row_c,
+            row_d,
+            buf: [0; BUF_LEN_U8],
+            index: BUF_LEN_U8,
+            offset: 0,
+            overflow: CounterOverflow::Wrap,
+            advance: CounterAdvance::Block,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<M, R> ChaChaCore<M, R, XDjb>
+where
+    M: Machine,
+    R: DoubleRounds,
+{
+    /// Creates a new XChaCha (or XSalsa) instance using a 192-bit `nonce`.
+    ///
+    /// The first four values in `nonce` are used with `key` to derive a subkey via HChaCha
+    /// (or HSalsa), and the last two become the nonce of the underlying [`Djb`] instance.
+    /// The resulting output is identical to libsodium's `crypto_stream_xchacha20`
+    /// (or `crypto_stream_xsalsa20`) for 20 rounds.
+    #[inline]
+    pub fn new_extended(key: [u32; 8], counter: u64, nonce: [u32; 6]) -> Self {
+        let [n0, n1, n2, n3, n4, n5] = nonce;
+        let mut subkey = M::subkey::<R>(&key, &[n0, n1, n2, n3]);
+        let result = Self::new(subkey, counter, [n4, n5, 0]);
+        subkey.fill(0);
+        result
+    }
+}
+
+impl<M, R> ChaChaCore<M, R, Djb>
+where
+    M: Machine,
+    R: DoubleRounds,
+{
+    /// Creates a new `ChaChaCore` instance with a counter and nonce of exactly the
+    /// sizes used by the variant, so nothing is silently discarded like in [`Self::new`].
+    #[inline]
+    pub fn new_exact(key: [u32; 8], counter: u64, nonce: u64) -> Self {
+        let [n0, n1]: [u32; 2] = unsafe { transmute(nonce) };
+        Self::new(key, counter, [n0, n1, 0])
+    }
+
+    /// Returns a copy of `self` using `nonce` instead, with the counter starting at 0.
+    #[inline]
+    pub fn clone_with_nonce(&self, nonce: u64) -> Self {
+        let mut result = self.duplicate();
+        result.row_d = Row { u64x2: [0, nonce] };
+        result
+    }
+
+    /// Returns what `self` does once its counter runs out.
+    #[inline]
+    pub fn counter_overflow(&self) -> CounterOverflow {
+        self.overflow
+    }
+
+    /// Sets what `self` does once its counter runs out, which is [`CounterOverflow::Wrap`]
+    /// by default. The policy is carried over to clones of `self`.
+    ///
+    /// ```should_panic
+    /// use chachacha::{ChaCha20Djb, CounterOverflow};
+    ///
+    /// let mut chacha = ChaCha20Djb::new([69; 8], u64::MAX - 1, [0; 3]);
+    /// chacha.set_counter_overflow(CounterOverflow::Saturate);
+    /// assert!(chacha.try_fill(&mut [0; 65]).is_err());
+    /// chacha.fill(&mut [0; 64]);
+    /// // Panics rather than using the final block and wrapping around.
+    /// chacha.fill(&mut [0; 1]);
+    /// ```
+    #[inline]
+    pub fn set_counter_overflow(&mut self, policy: CounterOverflow) {
+        self.overflow = policy;
+    }
+
+    /// Returns how many more bytes can be produced by [`Self::try_fill`]/[`Self::try_xor`]
+    /// before the 64-bit counter would wrap around and start repeating the keystream.
+    ///
+    /// Like with [`Ietf`], the final block (at a counter of `u64::MAX`) is never handed out.
+    #[inline]
+    pub fn remaining_bytes(&self) -> u128 {
+        let blocks = u64::MAX - self.get_counter();
+        (blocks as u128 * MATRIX_SIZE_U8 as u128).saturating_sub(self.offset as u128)
+    }
+
+    /// Fills `dst` with bytes from the output of `self`, exactly like [`Self::fill`].
+    ///
+    /// Returns [`Error::CounterExhausted`](crate::Error::CounterExhausted) (leaving `dst`
+    /// and `self` untouched) if `dst` is longer than [`Self::remaining_bytes`],
+    /// regardless of the [`CounterOverflow`] policy.
+    #[inline]
+    pub fn try_fill(&mut self, dst: &mut [u8]) -> Result<(), crate::Error> {
+        self.check_remaining(dst.len())?;
+        self.fill(dst);
+        Ok(())
+    }
+
+    /// Xors `dst` with bytes from the output of `self`, exactly like [`Self::xor`].
+    ///
+    /// Returns [`Error::CounterExhausted`](crate::Error::CounterExhausted) (leaving `dst`
+    /// and `self` untouched) if `dst` is longer than [`Self::remaining_bytes`],
+    /// regardless of the [`CounterOverflow`] policy.
+    #[inline]
+    pub fn try_xor(&mut self, dst: &mut [u8]) -> Result<(), crate::Error> {
+        self.check_remaining(dst.len())?;
+        self.xor(dst);
+        Ok(())
+    }
+
+    #[inline]
+    fn check_remaining(&self, len: usize) -> Result<(), crate::Error> {
+        if len as u128 > self.remaining_bytes() {
+            return Err(crate::Error::CounterExhausted);
+        }
+        Ok(())
+    }
+
+    /// Creates a new `ChaChaCore` instance using the exact parameters from
+    /// DJB's specification: a 256-bit key, a 64-bit counter, and a 64-bit nonce.
+    #[inline]
+    pub fn from_bytes(key: [u8; 32], counter: u64, nonce: [u8; 8]) -> Self {
+        let [n0, n1] = le_words(&nonce);
+        Self::new(le_words(&key), counter, [n0, n1, 0])
+    }
+
+    /// Like [`from_bytes`](Self::from_bytes), but with the key and nonce
+    /// given as distinct types so they can't be mixed up.
+    #[inline]
+    pub fn from_parts(key: &impl KeyMaterial, counter: u64, nonce: &Nonce64) -> Self {
+        let [n0, n1] = le_words(nonce.as_bytes());
+        Self::new(le_words(key.key_bytes()), counter, [n0, n1, 0])
+    }
+}
+
+impl<M, R> ChaChaCore<M, R, Ietf>
+where
+    M: Machine,
+    R: DoubleRounds,
+{
+    /// Creates a new `ChaChaCore` instance with a counter and nonce of exactly the
+    /// sizes used by the variant, so nothing is silently truncated like in [`Self::new`].
+    #[inline]
+    pub fn new_exact(key: [u32; 8], counter: u32, nonce: [u32; 3]) -> Self {
+        Self::new(key, counter as u64, nonce)
+    }
+
+    /// Returns a copy of `self` using `nonce` instead, with the counter starting at 0.
+    #[inline]
+    pub fn clone_with_nonce(&self, nonce: [u32; 3]) -> Self {
+        let [n0, n1, n2] = nonce;
+        let mut result = self.duplicate();
+        result.row_d = Row {
+            u32x4: [0, n0, n1, n2],
+        };
+        result
+    }
+
+    /// Returns how many more bytes can be produced by [`Self::try_fill`]/[`Self::try_xor`]
+    /// before the 32-bit counter would wrap around and start repeating the keystream.
+    ///
+    /// The final block (at a counter of `u32::MAX`) is never handed out by the fallible
+    /// methods, since using it would wrap the counter to 0, where it'd be impossible
+    /// to tell apart from an instance which had never been used.
+    #[inline]
+    pub fn remaining_bytes(&self) -> u64 {
+        let blocks = (u32::MAX as u64) - self.get_counter();
+        // Saturating, since the offset can be anywhere in the final block after a seek.
+        (blocks * MATRIX_SIZE_U8 as u64).saturating_sub(self.offset as u64)
+    }
+
+    /// Fills `dst` with bytes from the output of `self`, exactly like [`Self::fill`].
+    ///
+    /// Returns [`Error::CounterExhausted`](crate::Error::CounterExhausted) (leaving `dst`
+    /// and `self` untouched) if `dst` is longer than [`Self::remaining_bytes`].
+    ///
+    /// ```
+    /// use chachacha::ChaCha20Ietf;
+    ///
+    /// let mut chacha = ChaCha20Ietf::new([69; 8], u32::MAX as u64 - 1, [0; 3]);
+    /// assert_eq!(chacha.remaining_bytes(), 64);
+    /// assert!(chacha.try_fill(&mut [0; 65]).is_err());
+    /// assert!(chacha.try_fill(&mut [0; 64]).is_ok());
+    /// assert!(chacha.try_fill(&mut [0; 1]).is_err());
+    /// ```
+    #[inline]
+    pub fn try_fill(&mut self, dst: &mut [u8]) -> Result<(), crate::Error> {
+        self.check_remaining(dst.len())?;
+        self.fill(dst);
+        Ok(())
+    }
+
+    /// Xors `dst` with bytes from the output of `self`, exactly like [`Self::xor`].
+    ///
+    /// Returns [`Error::CounterExhausted`](crate::Error::CounterExhausted) (leaving `dst`
+    /// and `self` untouched) if `dst` is longer than [`Self::remaining_bytes`].
+    #[inline]
+    pub fn try_xor(&mut self, dst: &mut [u8]) -> Result<(), crate::Error> {
+        self.check_remaining(dst.len())?;
+        self.xor(dst);
+        Ok(())
+    }
+
+    #[inline]
+    fn check_remaining(&self, len: usize) -> Result<(), crate::Error> {
+        if len as u64 > self.remaining_bytes() {
+            return Err(crate::Error::CounterExhausted);
+        }
+        Ok(())
+    }
+
+    /// Creates a new `ChaChaCore` instance using the exact parameters from
+    /// RFC 8439: a 256-bit key, a 32-bit counter, and a 96-bit nonce.
+    ///
+    /// ```
+    /// use chachacha::ChaCha20Ietf;
+    ///
+    /// // RFC 8439 section 2.4.2
+    /// let key = core::array::from_fn(|i| i as u8);
+    /// let nonce = [0, 0, 0, 0, 0, 0, 0, 0x4a, 0, 0, 0, 0];
+    /// let mut chacha = ChaCha20Ietf::from_bytes(key, 1, nonce);
+    /// let mut buf = *b"Ladies and Gentlemen";
+    /// chacha.xor(&mut buf);
+    /// assert_eq!(buf[..4], [0x6e, 0x2e, 0x35, 0x9a]);
+    /// ```
+    #[inline]
+    pub fn from_bytes(key: [u8; 32], counter: u32, nonce: [u8; 12]) -> Self {
+        Self::new(le_words(&key), counter as u64, le_words(&nonce))
+    }
+
+    /// Like [`from_bytes`](Self::from_bytes), but with the key and nonce
+    /// given as distinct types so they can't be mixed up.
+    #[inline]
+    pub fn from_parts(key: &impl KeyMaterial, counter: u32, nonce: &Nonce96) -> Self {
+        Self::new(
+            le_words(key.key_bytes()),
+            counter as u64,
+            le_words(nonce.as_bytes()),
+        )
+    }
+}
+
+impl<M, R> ChaChaCore<M, R, XDjb>
+where
+    M: Machine,
+    R: DoubleRounds,
+{
+    /// Creates a new XChaCha (or XSalsa) instance using a 256-bit key, a 64-bit counter,
+    /// and a 192-bit nonce, exactly like [`new_extended`](Self::new_extended).
+    #[inline]
+    pub fn from_bytes(key: [u8; 32], counter: u64, nonce: [u8; 24]) -> Self {
+        Self::new_extended(le_words(&key), counter, le_words(&nonce))
+    }
+}
+
+#[cfg(feature = "getrandom")]
+impl<M, R, V> ChaChaCore<M, R, V> {
+    /// Creates a new `ChaChaCore` instance whose entire seed is pulled
+    /// from the operating system's entropy source.
+    #[inline]
+    pub(crate) fn try_from_entropy() -> Result<Self, crate::Error> {
+        let mut seed = [0; SEED_LEN_U8];
+        getrandom::fill(&mut seed)?;
+        Ok(seed.into())
     }
 }
 
@@ -80,6 +487,9 @@ where
     /// [`Djb`] will use all of `counter` and only the first two values in `nonce`.
     ///
     /// [`Ietf`] will truncate `counter` to a `u32` and use all values in `nonce`.
+    ///
+    /// [`XDjb`] is treated exactly like [`Djb`], skipping the HChaCha step entirely,
+    /// so you almost certainly want [`Self::new_extended`] instead.
     pub fn new(key: [u32; 8], counter: u64, nonce: [u32; 3]) -> Self {
         let row_b = Row {
             u32x4: [key[0], key[1], key[2], key[3]],
@@ -101,14 +511,70 @@ where
                 }
             }
         };
-        Self {
-            row_b,
-            row_c,
-            row_d,
-            _phantom: PhantomData,
+        Self::from_rows(row_b, row_c, row_d)
+    }
+
+    /// Creates a new `ChaChaCore` instance from a byte slice `key` and `nonce`,
+    /// with the counter starting at 0.
+    ///
+    /// `nonce` must be 8 bytes for [`Djb`], 12 bytes for [`Ietf`], and 24 bytes for [`XDjb`]
+    /// (which derives a subkey exactly like [`new_extended`](ChaChaCore::new_extended)),
+    /// and `key` must always be 32 bytes. Returns [`Error::InvalidLength`](crate::Error::InvalidLength)
+    /// if either length is wrong.
+    ///
+    /// ```
+    /// use chachacha::{ChaCha20Djb, ChaCha20Ietf, XChaCha20Djb};
+    ///
+    /// assert!(ChaCha20Djb::new_from_slices(&[0; 32], &[0; 8]).is_ok());
+    /// assert!(ChaCha20Ietf::new_from_slices(&[0; 32], &[0; 12]).is_ok());
+    /// assert!(XChaCha20Djb::new_from_slices(&[0; 32], &[0; 24]).is_ok());
+    /// assert!(ChaCha20Ietf::new_from_slices(&[0; 32], &[0; 8]).is_err());
+    /// ```
+    pub fn new_from_slices(key: &[u8], nonce: &[u8]) -> Result<Self, crate::Error> {
+        if key.len() != 32 || nonce.len() != V::NONCE_LEN {
+            return Err(crate::Error::InvalidLength);
         }
+        let mut key = le_words(key);
+        let mut padded = [0; 24];
+        padded[..nonce.len()].copy_from_slice(nonce);
+        let [n0, n1, n2, n3, n4, n5] = le_words(&padded);
+        let result = if V::NONCE_LEN == 24 {
+            let mut subkey = M::subkey::<R>(&key, &[n0, n1, n2, n3]);
+            let result = Self::new(subkey, 0, [n4, n5, 0]);
+            subkey.fill(0);
+            result
+        } else {
+            Self::new(key, 0, [n0, n1, n2])
+        };
+        key.fill(0);
+        Ok(result)
+    }
+
+    /// Creates a new `ChaChaCore` instance using `key`, with both the counter and nonce set to 0.
+    ///
+    /// Only suitable when `key` will never be used for any other stream.
+    /// Equivalent to [`new_from_slices`](Self::new_from_slices) with an all zero nonce,
+    /// so [`XDjb`] still derives a subkey.
+    #[inline]
+    pub fn from_key(key: [u8; 32]) -> Self {
+        Self::new_from_slices(&key, &[0; 24][..V::NONCE_LEN]).unwrap()
+    }
+
+    /// Creates a new `ChaChaCore` instance using a 128-bit `key`, as supported by the
+    /// original ChaCha (and Salsa) specification.
+    ///
+    /// `key` is used for both halves of the usual 256-bit key, and the constants become
+    /// "`expand 16-byte k`". `counter` and `nonce` are handled exactly like in [`Self::new`].
+    ///
+    /// Only use this when required for compatibility, as 128-bit keys are far weaker.
+    pub fn new_128(key: [u32; 4], counter: u64, nonce: [u32; 3]) -> Self {
+        let [k0, k1, k2, k3] = key;
+        let mut result = Self::new([k0, k1, k2, k3, k0, k1, k2, k3], counter, nonce);
+        result.row_a = ROW_A_128;
+        result
     }
 
+    /// Returns the block counter of `self`.
     #[inline]
     pub fn get_counter(&self) -> u64 {
         unsafe {
@@ -119,8 +585,13 @@ where
         }
     }
 
+    /// Sets the block counter of `self`, truncating it to a `u32` for [`Ietf`].
+    ///
+    /// Any output buffered by the word-oriented methods is discarded.
     #[inline]
     pub fn set_counter(&mut self, new_counter: u64) {
+        self.index = BUF_LEN_U8;
+        self.offset = 0;
         unsafe {
             match V::VAR {
                 Variants::Djb => self.row_d.u64x2[0] = new_counter,
@@ -129,6 +600,644 @@ where
         }
     }
 
+    /// Returns a copy of `self` with the counter set to `counter`, so it can be handed
+    /// to another thread to produce a different section of the same keystream.
+    ///
+    /// ```
+    /// use chachacha::ChaCha20Djb;
+    ///
+    /// let mut chacha = ChaCha20Djb::new([69; 8], 0, [0; 3]);
+    /// let mut worker = chacha.clone_with_counter(1 << 20);
+    /// chacha.set_counter(1 << 20);
+    /// assert_eq!(worker.get_block(), chacha.get_block());
+    /// ```
+    #[inline]
+    pub fn clone_with_counter(&self, counter: u64) -> Self {
+        let mut result = self.duplicate();
+        result.set_counter(counter);
+        result
+    }
+
+    /// Returns where the counter moves to after a slice method produces a partial block.
+    #[inline]
+    pub fn counter_advance(&self) -> CounterAdvance {
+        self.advance
+    }
+
+    /// Sets where the counter moves to after a slice method (`fill`, `xor`, etc.) produces
+    /// a partial block, which is [`CounterAdvance::Block`] by default. The policy is carried
+    /// over to clones of `self`.
+    ///
+    /// ```
+    /// use chachacha::{ChaCha20Ietf, CounterAdvance};
+    ///
+    /// let mut expected = [0; 100];
+    /// ChaCha20Ietf::new([69; 8], 0, [0; 3]).fill(&mut expected);
+    ///
+    /// let mut chacha = ChaCha20Ietf::new([69; 8], 0, [0; 3]);
+    /// chacha.set_counter_advance(CounterAdvance::Exact);
+    /// let (mut a, mut b) = ([0; 30], [0; 70]);
+    /// chacha.fill(&mut a);
+    /// chacha.fill(&mut b);
+    /// assert_eq!([a.as_slice(), &b].concat(), expected);
+    /// assert_eq!(chacha.position(), 100);
+    /// ```
+    #[inline]
+    pub fn set_counter_advance(&mut self, policy: CounterAdvance) {
+        self.advance = policy;
+    }
+
+    /// Returns the position (in bytes) in the keystream that the next slice method
+    /// (`fill`, `xor`, etc.) will start at.
+    ///
+    /// This is the counter multiplied by the block size, plus any offset into
+    /// the current block set by [`Self::seek`] (or left by [`CounterAdvance::Exact`]).
+    #[inline]
+    pub fn position(&self) -> u128 {
+        self.get_counter() as u128 * MATRIX_SIZE_U8 as u128 + self.offset as u128
+    }
+
+    /// Moves to `position` (in bytes) in the keystream, so the next slice method
+    /// (`fill`, `xor`, etc.) starts exactly there, even in the middle of a block.
+    ///
+    /// [`Djb`] streams are 2<sup>70</sup> bytes long, and [`Ietf`] streams are 2<sup>38</sup>
+    /// bytes long, with larger positions wrapping around exactly as the counter does.
+    ///
+    /// Any output buffered by the word-oriented methods is discarded. The block methods
+    /// (`fill_block`, etc.) always start at the beginning of the current block.
+    #[inline]
+    pub fn seek(&mut self, position: u128) {
+        self.set_counter((position / MATRIX_SIZE_U8 as u128) as u64);
+        self.offset = (position % MATRIX_SIZE_U8 as u128) as usize;
+    }
+
+    /// Advances the keystream by `n` bytes without generating any output,
+    /// exactly as if `n` bytes had been passed to [`Self::fill`] in one call.
+    ///
+    /// Wraps around exactly like [`Self::seek`], and has the same effect on buffered output.
+    #[inline]
+    pub fn skip_bytes(&mut self, n: u64) {
+        self.seek(self.position() + n as u128);
+    }
+
+    /// Advances the keystream by `n` whole blocks without generating any output,
+    /// keeping any offset into the current block set by [`Self::seek`].
+    ///
+    /// Wraps around exactly like [`Self::seek`], and has the same effect on buffered output.
+    #[inline]
+    pub fn skip_blocks(&mut self, n: u64) {
+        self.seek(self.position() + n as u128 * MATRIX_SIZE_U8 as u128);
+    }
+
+    /// Returns the stream identifier (the nonce) of `self`.
+    ///
+    /// [`Djb`] has a 64-bit nonce, so only the lower 64 bits can be non-zero.
+    ///
+    /// [`Ietf`] has a 96-bit nonce, so only the lower 96 bits can be non-zero.
+    #[inline]
+    pub fn get_stream(&self) -> u128 {
+        unsafe {
+            match V::VAR {
+                Variants::Djb => self.row_d.u64x2[1] as u128,
+                Variants::Ietf => {
+                    let [_, a, b, c] = self.row_d.u32x4;
+                    (a as u128) | ((b as u128) << 32) | ((c as u128) << 64)
+                }
+            }
+        }
+    }
+
+    /// Sets the stream identifier (the nonce) of `self`, leaving the key and counter untouched.
+    ///
+    /// Every stream is an independent sequence of output, so this allows for deriving
+    /// numbered streams from a single key.
+    ///
+    /// [`Djb`] will truncate `new_stream` to a `u64`.
+    ///
+    /// [`Ietf`] will truncate `new_stream` to its lower 96 bits.
+    ///
+    /// Any output buffered by the word-oriented methods is discarded.
+    #[inline]
+    pub fn set_stream(&mut self, new_stream: u128) {
+        self.index = BUF_LEN_U8;
+        unsafe {
+            match V::VAR {
+                Variants::Djb => self.row_d.u64x2[1] = new_stream as u64,
+                Variants::Ietf => {
+                    self.row_d.u32x4[1] = new_stream as u32;
+                    self.row_d.u32x4[2] = (new_stream >> 32) as u32;
+                    self.row_d.u32x4[3] = (new_stream >> 64) as u32;
+                }
+            }
+        }
+    }
+
+    /// Serializes the position of `self` in the keystream (along with everything
+    /// needed to continue it) into [`STATE_LEN_U8`] bytes, which can be restored by
+    /// [`Self::from_state_bytes`] on any platform.
+    ///
+    /// The layout is:
+    ///
+    /// - Bytes `0..16`: the constant row, as four little-endian `u32` values.
+    /// - Bytes `16..48`: the key, as eight little-endian `u32` values.
+    /// - Bytes `48..64`: the counter followed by the nonce, as two little-endian `u64` values
+    ///   for [`Djb`], or four little-endian `u32` values for [`Ietf`].
+    /// - Byte `64`: the offset into the current block set by [`Self::seek`] (always below 64).
+    ///
+    /// Any output buffered by the word-oriented methods isn't included, so it's discarded
+    /// by the restored instance, exactly like after [`Self::set_counter`].
+    ///
+    /// ```
+    /// use chachacha::ChaCha20Ietf;
+    ///
+    /// let mut chacha = ChaCha20Ietf::new([69; 8], 0, [1, 2, 3]);
+    /// chacha.seek(1000);
+    /// let state = chacha.to_state_bytes();
+    /// let mut restored = ChaCha20Ietf::from_state_bytes(&state).unwrap();
+    /// assert_eq!(restored.position(), 1000);
+    /// ```
+    #[inline]
+    pub fn to_state_bytes(&self) -> [u8; STATE_LEN_U8] {
+        let mut result = [0; STATE_LEN_U8];
+        let (words, rest) = result.split_at_mut(MATRIX_SIZE_U8 - size_of::<Row>());
+        let rows = [self.row_a, self.row_b, self.row_c];
+        for (dst, src) in words
+            .chunks_exact_mut(size_of::<u32>())
+            .zip(rows.iter().flat_map(|row| unsafe { row.u32x4 }))
+        {
+            dst.copy_from_slice(&src.to_le_bytes());
+        }
+        unsafe {
+            match V::VAR {
+                Variants::Djb => {
+                    for (dst, src) in rest
+                        .chunks_exact_mut(size_of::<u64>())
+                        .zip(self.row_d.u64x2)
+                    {
+                        dst.copy_from_slice(&src.to_le_bytes());
+                    }
+                }
+                Variants::Ietf => {
+                    for (dst, src) in rest
+                        .chunks_exact_mut(size_of::<u32>())
+                        .zip(self.row_d.u32x4)
+                    {
+                        dst.copy_from_slice(&src.to_le_bytes());
+                    }
+                }
+            }
+        }
+        result[MATRIX_SIZE_U8] = self.offset as u8;
+        result
+    }
+
+    /// Restores an instance serialized by [`Self::to_state_bytes`].
+    ///
+    /// Returns [`Error::InvalidState`](crate::Error::InvalidState) if the offset is out of range.
+    #[inline]
+    pub fn from_state_bytes(state: &[u8; STATE_LEN_U8]) -> Result<Self, crate::Error> {
+        let offset = state[MATRIX_SIZE_U8] as usize;
+        if offset >= MATRIX_SIZE_U8 {
+            return Err(crate::Error::InvalidState);
+        }
+        let [a0, a1, a2, a3, b0, b1, b2, b3, c0, c1, c2, c3] = le_words(state);
+        let row_d = match V::VAR {
+            Variants::Djb => {
+                let word =
+                    |i: usize| u64::from_le_bytes(state[48 + i * 8..][..8].try_into().unwrap());
+                Row {
+                    u64x2: [word(0), word(1)],
+                }
+            }
+            Variants::Ietf => Row {
+                u32x4: le_words(&state[48..]),
+            },
+        };
+        let mut result = Self::from_rows(
+            Row {
+                u32x4: [b0, b1, b2, b3],
+            },
+            Row {
+                u32x4: [c0, c1, c2, c3],
+            },
+            row_d,
+        );
+        result.row_a = Row {
+            u32x4: [a0, a1, a2, a3],
+        };
+        result.offset = offset;
+        Ok(result)
+    }
+
+    /// Writes the output of [`Self::to_state_bytes`] to `writer`, so the
+    /// keystream can be resumed later by [`Self::load_state`].
+    #[cfg(feature = "std")]
+    #[inline]
+    pub fn save_state(&self, writer: &mut impl std::io::Write) -> std::io::Result<()> {
+        let mut state = self.to_state_bytes();
+        let result = writer.write_all(&state);
+        state.fill(0);
+        result
+    }
+
+    /// Reads a state written by [`Self::save_state`] from `reader`, and restores it.
+    ///
+    /// Malformed state is reported as an [`InvalidData`](std::io::ErrorKind::InvalidData) error.
+    ///
+    /// ```
+    /// use chachacha::ChaCha20Djb;
+    ///
+    /// let mut chacha = ChaCha20Djb::new([69; 8], 420, [0; 3]);
+    /// let mut file = Vec::new();
+    /// chacha.save_state(&mut file).unwrap();
+    /// let mut restored = ChaCha20Djb::load_state(&mut file.as_slice()).unwrap();
+    /// assert_eq!(restored.get_block(), chacha.get_block());
+    /// ```
+    #[cfg(feature = "std")]
+    #[inline]
+    pub fn load_state(reader: &mut impl std::io::Read) -> std::io::Result<Self> {
+        let mut state = [0; STATE_LEN_U8];
+        reader.read_exact(&mut state)?;
+        let result = Self::from_state_bytes(&state).map_err(Into::into);
+        state.fill(0);
+        result
+    }
+
+    /// Reads `reader` until it's exhausted, xoring everything with the output of `self`
+    /// and writing the result to `writer`, returning the amount of bytes copied.
+    ///
+    /// Like [`std::io::copy`], but the keystream is continuous across reads of any size,
+    /// so the output is identical to xoring everything in a single call.
+    ///
+    /// ```
+    /// use chachacha::ChaCha20Ietf;
+    ///
+    /// let plaintext = [69; 5000];
+    /// let mut ciphertext = Vec::new();
+    /// let mut chacha = ChaCha20Ietf::new([1; 8], 1, [2; 3]);
+    /// let copied = chacha.encrypt_copy(&plaintext[..], &mut ciphertext).unwrap();
+    /// assert_eq!(copied, 5000);
+    ///
+    /// let mut expected = plaintext;
+    /// ChaCha20Ietf::new([1; 8], 1, [2; 3]).xor(&mut expected);
+    /// assert_eq!(ciphertext, expected);
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn encrypt_copy(
+        &mut self,
+        mut reader: impl std::io::Read,
+        mut writer: impl std::io::Write,
+    ) -> std::io::Result<u64> {
+        let mut chunk = [0; BUF_LEN_U8 * 16];
+        let mut copied = 0;
+        loop {
+            let len = match reader.read(&mut chunk) {
+                Ok(0) => return Ok(copied),
+                Ok(len) => len,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            let start = self.position();
+            self.xor(&mut chunk[..len]);
+            // Short reads would otherwise discard the rest of their final block.
+            self.seek(start + len as u128);
+            writer.write_all(&chunk[..len])?;
+            copied += len as u64;
+        }
+    }
+
+    /// Creates a new instance with the same key as `self`, a counter of 0, and a
+    /// stream identifier deterministically derived from `label`.
+    ///
+    /// The same key and label will always produce the same stream, which makes this
+    /// useful for reproducible per-name output (procedural generation, test fixtures, etc.).
+    /// The label is compressed with HChaCha keyed by `self`, so stream identifiers
+    /// can't be predicted without knowing the key.
+    pub fn substream(&self, label: &[u8]) -> Self {
+        let key: [u32; 8] = unsafe { transmute([self.row_b.u32x4, self.row_c.u32x4]) };
+        let mut state = key;
+        let mut absorb = |block: &[u8]| {
+            let mut input = [0; 4];
+            for (dst, src) in input.iter_mut().zip(block.chunks(size_of::<u32>())) {
+                let mut bytes = [0; size_of::<u32>()];
+                bytes[..src.len()].copy_from_slice(src);
+                *dst = u32::from_le_bytes(bytes);
+            }
+            state = hchacha::<R>(&state, &input);
+        };
+        label.chunks(size_of::<Row>()).for_each(&mut absorb);
+        // Finish with the length of the label, so labels which only differ
+        // by trailing zeros don't end up with the same stream.
+        absorb(&(label.len() as u64).to_le_bytes());
+        let [a, b, c, d, ..] = state;
+        let stream = (a as u128) | ((b as u128) << 32) | ((c as u128) << 64) | ((d as u128) << 96);
+        let mut result = Self::new(key, 0, [0; 3]);
+        result.set_stream(stream);
+        result
+    }
+
+    /// Derives a new, statistically independent instance from the output of `self`.
+    ///
+    /// The key and nonce of the child are pulled from the next block of output
+    /// (which advances `self` as if `get_block` had been called), and its counter
+    /// starts at 0. Since the child's key is never exposed anywhere else,
+    /// its output can't be predicted from the output of `self` or vice versa.
+    #[inline]
+    pub fn fork(&mut self) -> Self {
+        let mut block = [0; BUF_LEN_U8];
+        self.fill_block(&mut block);
+        let mut words = [0; SEED_LEN_U32];
+        for (dst, src) in words.iter_mut().zip(block.chunks_exact(size_of::<u32>())) {
+            *dst = u32::from_le_bytes(src.try_into().unwrap());
+        }
+        let key = words[..8].try_into().unwrap();
+        let nonce = words[8..11].try_into().unwrap();
+        let result = Self::new(key, 0, nonce);
+        block.fill(0);
+        words.fill(0);
+        result
+    }
+
+    /// Returns a uniformly distributed `f32` in the range [0, 1).
+    #[inline]
+    pub fn next_f32(&mut self) -> f32 {
+        const SCALE: f32 = 1.0 / (1u32 << f32::MANTISSA_DIGITS) as f32;
+        (self.next_u32() >> (u32::BITS - f32::MANTISSA_DIGITS)) as f32 * SCALE
+    }
+
+    /// Returns a uniformly distributed `f64` in the range [0, 1).
+    #[inline]
+    pub fn next_f64(&mut self) -> f64 {
+        const SCALE: f64 = 1.0 / (1u64 << f64::MANTISSA_DIGITS) as f64;
+        (self.next_u64() >> (u64::BITS - f64::MANTISSA_DIGITS)) as f64 * SCALE
+    }
+
+    /// Returns a uniformly distributed `u32`.
+    ///
+    /// Values are drawn from an internal buffer of keystream, which is only refilled
+    /// (advancing the counter by a full batch) once it runs out.
+    #[inline]
+    pub fn next_u32(&mut self) -> u32 {
+        u32::from_le_bytes(self.next_bytes())
+    }
+
+    /// Returns a uniformly distributed `u64`.
+    ///
+    /// Shares the same internal buffer as [`next_u32`](Self::next_u32).
+    #[inline]
+    pub fn next_u64(&mut self) -> u64 {
+        u64::from_le_bytes(self.next_bytes())
+    }
+
+    /// Returns a uniformly distributed `u128`.
+    #[inline]
+    pub fn next_u128(&mut self) -> u128 {
+        u128::from_le_bytes(self.next_bytes())
+    }
+
+    /// Fills `dst` with `u32` values from the output of `self`.
+    ///
+    /// Every value is read from the keystream in little-endian order, so the output
+    /// is the same on every platform. Has the same counter semantics as
+    /// [`fill`](Self::fill) when given a slice of the equivalent length in bytes.
+    #[inline]
+    pub fn fill_u32(&mut self, dst: &mut [u32]) {
+        // Every bit pattern is a valid `u32`, so there's no
+        // harm in filling them as raw bytes.
+        let bytes =
+            unsafe { core::slice::from_raw_parts_mut(dst.as_mut_ptr().cast(), size_of_val(dst)) };
+        self.fill(bytes);
+        #[cfg(target_endian = "big")]
+        dst.iter_mut()
+            .for_each(|value| *value = u32::from_le(*value));
+    }
+
+    /// Fills `dst` with `u64` values from the output of `self`.
+    ///
+    /// Every value is read from the keystream in little-endian order, so the output
+    /// is the same on every platform. Has the same counter semantics as
+    /// [`fill`](Self::fill) when given a slice of the equivalent length in bytes.
+    #[inline]
+    pub fn fill_u64(&mut self, dst: &mut [u64]) {
+        // Every bit pattern is a valid `u64`, so there's no
+        // harm in filling them as raw bytes.
+        let bytes =
+            unsafe { core::slice::from_raw_parts_mut(dst.as_mut_ptr().cast(), size_of_val(dst)) };
+        self.fill(bytes);
+        #[cfg(target_endian = "big")]
+        dst.iter_mut()
+            .for_each(|value| *value = u64::from_le(*value));
+    }
+
+    /// Xors `dst` with `u64` values from the output of `self`.
+    ///
+    /// Every value is read from the keystream in little-endian order, so the output
+    /// is the same on every platform. Has the same counter semantics as
+    /// [`xor`](Self::xor) when given a slice of the equivalent length in bytes.
+    #[inline]
+    pub fn xor_u64(&mut self, dst: &mut [u64]) {
+        #[cfg(target_endian = "big")]
+        dst.iter_mut().for_each(|value| *value = value.to_le());
+        let bytes =
+            unsafe { core::slice::from_raw_parts_mut(dst.as_mut_ptr().cast(), size_of_val(dst)) };
+        self.xor(bytes);
+        #[cfg(target_endian = "big")]
+        dst.iter_mut()
+            .for_each(|value| *value = u64::from_le(*value));
+    }
+
+    /// Fills `dst` with `u128` values from the output of `self`.
+    ///
+    /// Every value is read from the keystream in little-endian order, so the output
+    /// is the same on every platform. Has the same counter semantics as
+    /// [`fill`](Self::fill) when given a slice of the equivalent length in bytes.
+    #[inline]
+    pub fn fill_u128(&mut self, dst: &mut [u128]) {
+        // Every bit pattern is a valid `u128`, so there's no
+        // harm in filling them as raw bytes.
+        let bytes =
+            unsafe { core::slice::from_raw_parts_mut(dst.as_mut_ptr().cast(), size_of_val(dst)) };
+        self.fill(bytes);
+        #[cfg(target_endian = "big")]
+        dst.iter_mut()
+            .for_each(|value| *value = u128::from_le(*value));
+    }
+
+    /// Fills `dst` with values from the output of `self`, for any type
+    /// which is valid for every bit pattern (structs, `u16`, `f32`, etc.).
+    ///
+    /// The values are the raw keystream bytes in memory order, so multi-byte fields
+    /// depend on the endianness of the platform. Has the same counter semantics as
+    /// [`fill`](Self::fill) when given a slice of the equivalent length in bytes.
+    #[cfg(feature = "bytemuck")]
+    #[inline]
+    pub fn fill_pod<T: bytemuck::Pod>(&mut self, dst: &mut [T]) {
+        self.fill(bytemuck::cast_slice_mut(dst));
+    }
+
+    /// Xors the bytes of `dst` with the output of `self`, for any type
+    /// which is valid for every bit pattern (structs, `u16`, `f32`, etc.).
+    ///
+    /// Has the same counter semantics as [`xor`](Self::xor) when
+    /// given a slice of the equivalent length in bytes.
+    #[cfg(feature = "bytemuck")]
+    #[inline]
+    pub fn xor_pod<T: bytemuck::Pod>(&mut self, dst: &mut [T]) {
+        self.xor(bytemuck::cast_slice_mut(dst));
+    }
+
+    /// Returns a uniformly distributed `u64` in the range [0, `bound`),
+    /// or 0 when `bound` is 0.
+    ///
+    /// Uses Lemire's nearly divisionless method, so there's no modulo bias and
+    /// the (rare) rejections only cost another draw from the internal buffer.
+    #[inline]
+    pub fn next_bounded(&mut self, bound: u64) -> u64 {
+        let mut product = self.next_u64() as u128 * bound as u128;
+        if (product as u64) < bound {
+            let threshold = bound.wrapping_neg() % bound;
+            while (product as u64) < threshold {
+                product = self.next_u64() as u128 * bound as u128;
+            }
+        }
+        (product >> u64::BITS) as u64
+    }
+
+    /// Returns a uniformly distributed `u64` in `range`.
+    ///
+    /// # Panics
+    ///
+    /// If `range` is empty.
+    #[inline]
+    pub fn next_range(&mut self, range: Range<u64>) -> u64 {
+        assert!(!range.is_empty(), "cannot sample from an empty range");
+        range.start + self.next_bounded(range.end - range.start)
+    }
+
+    /// Fills `dst` with uniformly distributed `u64` values in `range`.
+    ///
+    /// # Panics
+    ///
+    /// If `range` is empty.
+    #[inline]
+    pub fn fill_range(&mut self, dst: &mut [u64], range: Range<u64>) {
+        assert!(!range.is_empty(), "cannot sample from an empty range");
+        let span = range.end - range.start;
+        dst.iter_mut()
+            .for_each(|v| *v = range.start + self.next_bounded(span));
+    }
+
+    /// Shuffles `slice` in place using the Fisher-Yates algorithm,
+    /// so every permutation is equally likely.
+    #[inline]
+    pub fn shuffle<T>(&mut self, slice: &mut [T]) {
+        for i in (1..slice.len()).rev() {
+            let j = self.next_bounded(i as u64 + 1) as usize;
+            slice.swap(i, j);
+        }
+    }
+
+    /// Fills `dst` with uniformly distributed ASCII alphanumeric characters
+    /// (`A-Z`, `a-z`, and `0-9`).
+    #[inline]
+    pub fn fill_ascii_alphanumeric(&mut self, dst: &mut [u8]) {
+        const CHARSET: &[u8; 62] =
+            b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+        dst.iter_mut().for_each(|v| {
+            // Rejection sampling on 6 bits at a time avoids any bias.
+            *v = loop {
+                let [byte] = self.next_bytes();
+                if let Some(&c) = CHARSET.get((byte >> 2) as usize) {
+                    break c;
+                }
+            };
+        });
+    }
+
+    /// Returns a `String` of `len` uniformly distributed ASCII alphanumeric characters,
+    /// suitable for session tokens, API keys, etc.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    pub fn token(&mut self, len: usize) -> String {
+        let mut result = vec![0; len];
+        self.fill_ascii_alphanumeric(&mut result);
+        // Every byte is ASCII, which is always valid UTF-8.
+        unsafe { String::from_utf8_unchecked(result) }
+    }
+
+    /// Returns a `Vec` holding the next `len` bytes of output from `self`.
+    ///
+    /// Has the same counter semantics as [`fill`](Self::fill).
+    #[cfg(feature = "alloc")]
+    #[inline]
+    pub fn keystream_vec(&mut self, len: usize) -> Vec<u8> {
+        self.collect_vec(len, |chacha, _, chunk| chacha.fill(chunk))
+    }
+
+    /// Returns a `Vec` holding `data` xored with the output of `self`,
+    /// leaving `data` untouched.
+    ///
+    /// Has the same counter semantics as [`xor`](Self::xor).
+    ///
+    /// ```
+    /// use chachacha::ChaCha20Ietf;
+    ///
+    /// let mut chacha = ChaCha20Ietf::new([69; 8], 1, [0; 3]);
+    /// let ciphertext = chacha.encrypt_to_vec(b"hello world");
+    /// chacha.set_counter(1);
+    /// assert_eq!(chacha.encrypt_to_vec(&ciphertext), b"hello world");
+    /// ```
+    #[cfg(feature = "alloc")]
+    #[inline]
+    pub fn encrypt_to_vec(&mut self, data: &[u8]) -> Vec<u8> {
+        self.collect_vec(data.len(), |chacha, start, chunk| {
+            chacha.xor_b2b(&data[start..start + chunk.len()], chunk)
+        })
+    }
+
+    /// Returns a `Vec` of `len` bytes produced piece by piece by `write`, which is passed
+    /// `self`, the position of the piece within the result, and a stack buffer to fill.
+    ///
+    /// Every piece after the first starts on a block boundary, and all but the last are whole
+    /// batches, so the counter ends up exactly where a single call for all `len` bytes would leave it.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    fn collect_vec(
+        &mut self,
+        len: usize,
+        mut write: impl FnMut(&mut Self, usize, &mut [u8]),
+    ) -> Vec<u8> {
+        let mut result = Vec::with_capacity(len);
+        let mut chunk = [0; BUF_LEN_U8 * 16];
+        let (head, rest) = result.spare_capacity_mut()[..len].split_at_mut(self.partial_len(len));
+        let mut start = 0;
+        for dst in core::iter::once(head).chain(rest.chunks_mut(chunk.len())) {
+            let chunk = &mut chunk[..dst.len()];
+            write(self, start, chunk);
+            // `MaybeUninit<u8>` has the same layout as `u8`.
+            unsafe { copy_nonoverlapping(chunk.as_ptr(), dst.as_mut_ptr().cast(), chunk.len()) };
+            start += chunk.len();
+        }
+        // The first `len` bytes were all just initialized.
+        unsafe { result.set_len(len) };
+        result
+    }
+
+    /// Pulls `N` bytes from the internal buffer, refilling it first if
+    /// there aren't enough left (any leftover bytes are discarded).
+    #[inline]
+    pub(crate) fn next_bytes<const N: usize>(&mut self) -> [u8; N] {
+        if self.index + N > BUF_LEN_U8 {
+            let mut buf = unsafe { MaybeUninit::uninit().assume_init() };
+            self.fill_block(&mut buf);
+            self.buf = buf;
+            self.index = 0;
+        }
+        let result = self.buf[self.index..self.index + N].try_into().unwrap();
+        self.index += N;
+        result
+    }
+
     /// Xors `dst` with bytes from the output of `self`.
     #[inline(never)]
     pub fn xor(&mut self, dst: &mut [u8]) {
@@ -141,40 +1250,248 @@ where
         self.slice::<false>(dst);
     }
 
+    /// Fills every buffer in `dsts` with bytes from the output of `self`, in order.
+    ///
+    /// The buffers are treated as one contiguous destination, so the output is identical to
+    /// calling [`Self::fill`] on their concatenation. Unlike separate calls to [`Self::fill`],
+    /// small buffers share blocks instead of each one discarding the rest of its final block,
+    /// and the state is only broadcast to the backend once.
+    ///
+    /// ```
+    /// use chachacha::ChaCha20Djb;
+    ///
+    /// let (mut a, mut b, mut c) = ([0; 12], [0; 300], [0; 40]);
+    /// let mut chacha = ChaCha20Djb::new([7; 8], 0, [0; 3]);
+    /// chacha.fill_many(&mut [&mut a, &mut b, &mut c]);
+    ///
+    /// let mut expected = [0; 352];
+    /// ChaCha20Djb::new([7; 8], 0, [0; 3]).fill(&mut expected);
+    /// assert_eq!([&a[..], &b, &c].concat(), expected);
+    /// assert_eq!(chacha.get_counter(), 6);
+    /// ```
+    #[inline(never)]
+    pub fn fill_many(&mut self, dsts: &mut [&mut [u8]]) {
+        let total: usize = dsts.iter().map(|dst| dst.len()).sum();
+        let skip = self.offset;
+        let blocks = self.slice_blocks(total as u64);
+        self.check_overflow(blocks);
+        self.offset = 0;
+        let mut machine = M::new::<V>(self.get_naked());
+        let mut batch: [u8; BUF_LEN_U8] = unsafe { MaybeUninit::uninit().assume_init() };
+        let mut pos = BUF_LEN_U8;
+        if skip != 0 {
+            self.chacha::<false, false>(&mut machine, &mut batch);
+            machine.increment::<V>();
+            pos = skip;
+        }
+        for dst in dsts.iter_mut() {
+            let mut dst: &mut [u8] = dst;
+            while !dst.is_empty() {
+                if pos == BUF_LEN_U8 {
+                    if dst.len() >= BUF_LEN_U8 {
+                        // Whole batches skip the intermediate buffer entirely.
+                        let (chunk, rest) = core::mem::take(&mut dst).split_at_mut(BUF_LEN_U8);
+                        self.chacha::<false, false>(&mut machine, chunk.try_into().unwrap());
+                        machine.increment::<V>();
+                        dst = rest;
+                        continue;
+                    }
+                    self.chacha::<false, false>(&mut machine, &mut batch);
+                    machine.increment::<V>();
+                    pos = 0;
+                }
+                let len = dst.len().min(BUF_LEN_U8 - pos);
+                let (head, rest) = core::mem::take(&mut dst).split_at_mut(len);
+                head.copy_from_slice(&batch[pos..pos + len]);
+                pos += len;
+                dst = rest;
+            }
+        }
+        if self.advance == CounterAdvance::Batch {
+            // Batches here don't line up with the ones `fill` would use when `skip` isn't 0.
+            self.add_blocks(blocks);
+        } else {
+            self.increment_partial(skip + total);
+        }
+    }
+
+    /// Xors `buf` with the keystream starting `byte_offset` bytes into the stream
+    /// (measured from block 0), without modifying `self` at all.
+    ///
+    /// This allows random reads and writes of data encrypted with the same key and nonce.
+    /// Offsets beyond the end of the stream wrap around, exactly as the counter does.
+    #[inline(never)]
+    pub fn apply_keystream_at(&self, byte_offset: u64, buf: &mut [u8]) {
+        let mut temp = self.duplicate();
+        temp.seek(byte_offset as u128);
+        temp.xor(buf);
+    }
+
+    /// Xors `src` with bytes from the output of `self`, storing the result in `dst`.
+    ///
+    /// Equivalent to copying `src` into `dst` and calling [`Self::xor`], but in a single pass.
+    ///
+    /// # Panics
+    ///
+    /// If `src` and `dst` have different lengths.
+    #[inline(never)]
+    pub fn xor_b2b(&mut self, src: &[u8], dst: &mut [u8]) {
+        assert_eq!(
+            src.len(),
+            dst.len(),
+            "source and destination lengths differ"
+        );
+        self.check_overflow(self.slice_blocks(dst.len() as u64));
+        let (head, dst) = dst.split_at_mut(self.partial_len(dst.len()));
+        let (head_src, src) = src.split_at(head.len());
+        if !head.is_empty() {
+            head.copy_from_slice(head_src);
+            self.xor_partial(head);
+        }
+        let mut machine = M::new::<V>(self.get_naked());
+        let (src_chunks, src_rem) = src.as_chunks::<BUF_LEN_U8>();
+        let (dst_chunks, dst_rem) = dst.as_chunks_mut::<BUF_LEN_U8>();
+        src_chunks
+            .iter()
+            .zip(dst_chunks)
+            .for_each(|(src, dst)| self.chacha_b2b::<true>(&mut machine, src, dst));
+        if !src_rem.is_empty() {
+            let mut buf = [0; BUF_LEN_U8];
+            buf[..src_rem.len()].copy_from_slice(src_rem);
+            self.chacha::<false, true>(&mut machine, &mut buf);
+            dst_rem.copy_from_slice(&buf[..src_rem.len()]);
+            self.increment_partial(src_rem.len());
+        }
+    }
+
     #[inline]
     fn slice<const XOR: bool>(&mut self, dst: &mut [u8]) {
-        let mut machine = M::new::<V>(self.get_naked());
-        dst.chunks_exact_mut(BUF_LEN_U8).for_each(|chunk| {
-            // FUCKING JUST GIVE US ARRAY WINDOWS OR SOMETHING DAMNIT.
-            let buf: &mut [u8; BUF_LEN_U8] = chunk.try_into().unwrap();
-            self.chacha::<true, XOR>(&mut machine, buf)
-        });
-        let rem = dst.chunks_exact_mut(BUF_LEN_U8).into_remainder();
+        self.check_overflow(self.slice_blocks(dst.len() as u64));
+        let (head, dst) = dst.split_at_mut(self.partial_len(dst.len()));
+        if !head.is_empty() {
+            if !XOR {
+                head.fill(0);
+            }
+            self.xor_partial(head);
+        }
+        let (chunks, rem) = dst.as_chunks_mut::<BUF_LEN_U8>();
+        if !chunks.is_empty() {
+            M::apply::<R, V, XOR>(self.get_naked(), chunks);
+            self.add_blocks((chunks.len() * DEPTH) as u64);
+        }
         if !rem.is_empty() {
+            let mut machine = M::new::<V>(self.get_naked());
             let mut buf: [u8; BUF_LEN_U8] = unsafe { MaybeUninit::uninit().assume_init() };
+            if XOR {
+                // The keystream is xored into `buf`, so it needs to start out holding `rem`.
+                buf[..rem.len()].copy_from_slice(rem);
+            }
             self.chacha::<false, XOR>(&mut machine, &mut buf);
             unsafe {
                 copy_nonoverlapping(buf.as_ptr(), rem.as_mut_ptr(), rem.len());
             }
-            // Normally, `ChaChaCore` is incremented by `DEPTH` after each call to ChaChaCore::chacha, but
-            // this approach fails to maintain parity with reference ChaCha implementations when `dst` has
-            // a length which isn't a perfect multiple of `BUF_LEN_U8`.
-            // Because we are processesing four ChaCha instances at once, we meed to make sure the counter
-            // is set to the value just beyond the instance whose data we (even just partially) consumed.
-            // For values of rem.len(), these are the mappings we need:
-            // (0,64] --> 1 (only data from the first ChaCha instance was used)
-            // (64,128] --> 2 (data from the first two ChaCha instances was used)
-            // (128,192] --> 3 (data from the first three ChaCha instances was used)
-            // (192,256] --> 4 (data from all ChaCha instances was used)
-            let increment = rem.len().div_ceil(MATRIX_SIZE_U8);
-            unsafe {
-                match V::VAR {
-                    Variants::Djb => {
-                        self.row_d.u64x2[0] = self.row_d.u64x2[0].wrapping_add(increment as u64);
-                    }
-                    Variants::Ietf => {
-                        self.row_d.u32x4[0] = self.row_d.u32x4[0].wrapping_add(increment as u32);
-                    }
+            self.increment_partial(rem.len());
+        }
+    }
+
+    /// Returns how much of a `len` byte slice falls into the remainder of
+    /// the current block, when part of it has already been consumed.
+    #[inline]
+    fn partial_len(&self, len: usize) -> usize {
+        if self.offset == 0 {
+            0
+        } else {
+            len.min(MATRIX_SIZE_U8 - self.offset)
+        }
+    }
+
+    /// Xors `data` with the keystream starting `self.offset` bytes into
+    /// the current block, then moves on to the next block (unless
+    /// [`CounterAdvance::Exact`] leaves part of it for the next call).
+    #[cold]
+    fn xor_partial(&mut self, data: &mut [u8]) {
+        let skip = core::mem::take(&mut self.offset);
+        let mut block = self.duplicate().get_block64();
+        data.iter_mut()
+            .zip(&block[skip..])
+            .for_each(|(value, key)| *value ^= key);
+        block.fill(0);
+        let end = skip + data.len();
+        if self.advance == CounterAdvance::Exact && end < MATRIX_SIZE_U8 {
+            self.offset = end;
+        } else {
+            self.add_blocks(1);
+        }
+    }
+
+    /// Returns how many blocks past the current counter a slice method producing `len` bytes
+    /// reaches, which is more than it actually uses with [`CounterAdvance::Batch`].
+    #[inline(always)]
+    fn slice_blocks(&self, len: u64) -> u64 {
+        if self.advance == CounterAdvance::Batch {
+            let head = match self.offset {
+                0 => 0,
+                offset => len.min((MATRIX_SIZE_U8 - offset) as u64),
+            };
+            (head != 0) as u64 + (len - head).div_ceil(BUF_LEN_U8 as u64) * DEPTH as u64
+        } else {
+            (self.offset as u64 + len).div_ceil(MATRIX_SIZE_U8 as u64)
+        }
+    }
+
+    /// Moves the counter exactly as a slice method producing `len` bytes would,
+    /// for output that was generated elsewhere (such as by [`XorIter`](crate::XorIter)).
+    #[inline]
+    pub(crate) fn advance_slice(&mut self, len: u64) {
+        if len == 0 {
+            return;
+        }
+        if self.advance == CounterAdvance::Exact {
+            let end = self.offset as u64 + len;
+            self.add_blocks(end / MATRIX_SIZE_U8 as u64);
+            self.offset = (end % MATRIX_SIZE_U8 as u64) as usize;
+        } else {
+            self.add_blocks(self.slice_blocks(len));
+            self.offset = 0;
+        }
+    }
+
+    /// Advances the counter past the final `len` bytes produced (where `len` is usually less
+    /// than `BUF_LEN_U8`), according to the [`CounterAdvance`] policy.
+    #[inline]
+    fn increment_partial(&mut self, len: usize) {
+        match self.advance {
+            CounterAdvance::Block => {
+                // Normally, `ChaChaCore` is incremented by `DEPTH` after each call to
+                // ChaChaCore::chacha, but this approach fails to maintain parity with reference
+                // ChaCha implementations when `dst` has a length which isn't a perfect multiple
+                // of `BUF_LEN_U8`. Because we are processesing four ChaCha instances at once, we
+                // meed to make sure the counter is set to the value just beyond the instance
+                // whose data we (even just partially) consumed.
+                // For values of `len`, these are the mappings we need:
+                // (0,64] --> 1 (only data from the first ChaCha instance was used)
+                // (64,128] --> 2 (data from the first two ChaCha instances was used)
+                // (128,192] --> 3 (data from the first three ChaCha instances was used)
+                // (192,256] --> 4 (data from all ChaCha instances was used)
+                self.add_blocks(len.div_ceil(MATRIX_SIZE_U8) as u64);
+            }
+            CounterAdvance::Batch => self.add_blocks((len.div_ceil(BUF_LEN_U8) * DEPTH) as u64),
+            CounterAdvance::Exact => {
+                self.add_blocks((len / MATRIX_SIZE_U8) as u64);
+                self.offset = len % MATRIX_SIZE_U8;
+            }
+        }
+    }
+
+    #[inline]
+    fn add_blocks(&mut self, increment: u64) {
+        unsafe {
+            match V::VAR {
+                Variants::Djb => {
+                    self.row_d.u64x2[0] = self.row_d.u64x2[0].wrapping_add(increment);
+                }
+                Variants::Ietf => {
+                    self.row_d.u32x4[0] = self.row_d.u32x4[0].wrapping_add(increment as u32);
                 }
             }
         }
@@ -219,8 +1536,79 @@ where
         self.chacha_once::<true>(buf);
     }
 
+    /// Replaces the key of `self` with the first 32 bytes of its next block of output,
+    /// then resets the counter to 0. The nonce is left as is.
+    ///
+    /// Compromising the state after a rekey reveals nothing about the output produced
+    /// before it, which makes this a cheap ratchet for long-lived streams. Both sides of
+    /// a stream need to rekey at the same point to stay in sync.
+    ///
+    /// ```
+    /// use chachacha::ChaCha20Djb;
+    ///
+    /// let mut a = ChaCha20Djb::new([69; 8], 9, [1; 3]);
+    /// let mut b = a.clone();
+    /// let before = a.peek_block();
+    /// a.rekey();
+    /// b.rekey();
+    /// assert_eq!(a.get_counter(), 0);
+    /// assert_ne!(a.peek_block(), before);
+    /// assert_eq!(a.get_block(), b.get_block());
+    /// ```
+    #[inline]
+    pub fn rekey(&mut self) {
+        let mut block = self.get_block64();
+        let mut key: [u32; 8] = le_words(&block);
+        self.row_b = Row {
+            u32x4: [key[0], key[1], key[2], key[3]],
+        };
+        self.row_c = Row {
+            u32x4: [key[4], key[5], key[6], key[7]],
+        };
+        key.fill(0);
+        block.fill(0);
+        // Buffered output came from the old key, so it can't be left lying around.
+        self.buf.fill(0);
+        self.set_counter(0);
+    }
+
+    /// Returns the output the next call to [`Self::get_block`] would produce,
+    /// without modifying `self` at all.
+    #[inline]
+    pub fn peek_block(&self) -> [u8; BUF_LEN_U8] {
+        self.duplicate().get_block()
+    }
+
+    /// Computes a single RFC-sized ChaCha block and returns it,
+    /// advancing the counter by exactly 1 instead of `DEPTH`.
+    #[inline]
+    pub fn get_block64(&mut self) -> [u8; MATRIX_SIZE_U8] {
+        let mut result = [0; MATRIX_SIZE_U8];
+        self.fill_block64(&mut result);
+        result
+    }
+
+    /// Computes a single RFC-sized ChaCha block and uses it to fill `buf`,
+    /// advancing the counter by exactly 1 instead of `DEPTH`.
+    ///
+    /// The block is computed with plain scalar code rather than as part of a batch on the
+    /// backend, so none of the work is thrown away.
+    #[inline]
+    pub fn fill_block64(&mut self, buf: &mut [u8; MATRIX_SIZE_U8]) {
+        self.check_overflow(1);
+        self.offset = 0;
+        let mut block = M::single_block::<R>(self.get_naked());
+        for (dst, word) in buf.chunks_exact_mut(size_of::<u32>()).zip(block) {
+            dst.copy_from_slice(&word.to_le_bytes());
+        }
+        block.fill(0);
+        self.add_blocks(1);
+    }
+
     #[inline(never)]
     fn chacha_once<const XOR: bool>(&mut self, buf: &mut [u8; BUF_LEN_U8]) {
+        self.check_overflow(DEPTH as u64);
+        self.offset = 0;
         let mut machine = M::new::<V>(self.get_naked());
         self.chacha::<false, XOR>(&mut machine, buf);
         self.increment();
@@ -248,6 +1636,46 @@ where
         }
     }
 
+    #[inline]
+    fn chacha_b2b<const INCREMENT: bool>(
+        &mut self,
+        machine: &mut M,
+        src: &[u8; BUF_LEN_U8],
+        dst: &mut [u8; BUF_LEN_U8],
+    ) {
+        let mut cur = machine.clone();
+        for _ in 0..R::COUNT {
+            cur.double_round();
+        }
+        (cur + machine.clone()).xor_result_b2b(src, dst);
+        if INCREMENT {
+            machine.increment::<V>();
+            self.increment();
+        }
+    }
+
+    /// Applies the [`CounterOverflow`] policy of a [`Djb`] instance,
+    /// if producing `blocks` blocks would use the final one.
+    #[inline(always)]
+    fn check_overflow(&self, blocks: u64) {
+        if let Variants::Djb = V::VAR
+            && self.overflow != CounterOverflow::Wrap
+            && blocks > u64::MAX - unsafe { self.row_d.u64x2[0] }
+        {
+            self.overflowed();
+        }
+    }
+
+    #[cold]
+    #[track_caller]
+    fn overflowed(&self) {
+        match self.overflow {
+            CounterOverflow::Wrap => {}
+            CounterOverflow::Saturate => panic!("counter exhausted"),
+            CounterOverflow::DebugPanic => debug_assert!(false, "counter exhausted"),
+        }
+    }
+
     #[inline]
     fn increment(&mut self) {
         unsafe {
@@ -262,12 +1690,24 @@ where
         }
     }
 
+    /// Returns a fresh instance with the same state as `self`, minus the word buffer.
+    #[inline]
+    fn duplicate(&self) -> Self {
+        let mut result = Self::from_rows(self.row_b, self.row_c, self.row_d);
+        result.row_a = self.row_a;
+        result.overflow = self.overflow;
+        result.advance = self.advance;
+        result
+    }
+
     #[inline]
-    fn get_naked(&self) -> &ChaChaNaked {
+    pub(crate) fn get_naked(&self) -> &ChaChaNaked {
+        // The rows are the first fields of both (`repr(C)`) types,
+        // so `ChaChaNaked` is a valid prefix of `Self`.
         const {
             assert!(align_of::<Self>() == align_of::<ChaChaNaked>());
-            assert!(size_of::<Self>() == size_of::<ChaChaNaked>());
+            assert!(size_of::<Self>() >= size_of::<ChaChaNaked>());
         }
-        unsafe { transmute(self) }
+        unsafe { &*(self as *const Self).cast() }
     }
 }
@@ -0,0 +1,570 @@
+/*!
+Module containing [`ChaChaPoly1305`], the AEAD construction specified in [RFC 8439], which
+combines the [`Ietf`] variant of ChaCha with the [`Poly1305`] authenticator.
+
+The first 32 bytes of keystream (block 0) are used as the one-time Poly1305 key, and the
+message is encrypted starting at block 1. The tag then authenticates the additional data and
+the ciphertext (each padded to 16 bytes) followed by their lengths. Reduced round versions are
+provided as well, built the exact same way but with fewer ChaCha rounds.
+
+Encryption and decryption operate in place, with the tag either kept separate from the ciphertext
+or appended to it in space provided by the caller, so no allocation is ever needed. The `heapless`
+feature adds methods which append the tag to a [`heapless::Vec`] instead. With
+the `aead` feature enabled, the traits of the RustCrypto [`aead`] crate are implemented as well,
+so these types can be used anywhere that abstraction is expected.
+
+Messages too large to hold in memory can be processed piece by piece with
+[`ChaChaPoly1305Encryptor`] and [`ChaChaPoly1305Decryptor`].
+
+The 32-bit counter limits a single message to [`AEAD_MAX_MESSAGE_LEN`] bytes (2<sup>38</sup> - 64),
+since going any further would wrap the counter back around to block 0 and reuse the keystream
+the Poly1305 key came from. Every method rejects longer messages with [`Error::InvalidLength`].
+
+[RFC 8439]: https://datatracker.ietf.org/doc/html/rfc8439
+*/
+
+use crate::key::{Key, SecretKey};
+use crate::poly1305::*;
+use crate::rounds::*;
+use crate::util::{BUF_LEN_U8, MATRIX_SIZE_U8, le_words};
+use crate::variations::*;
+use crate::{ChaCha, Error, HCHACHA_INPUT_LEN, hchacha20};
+use core::marker::PhantomData;
+
+/// Size (in 8-bit integers) of a ChaCha-Poly1305 key.
+pub const AEAD_KEY_LEN: usize = 32;
+/// Size (in 8-bit integers) of a ChaCha-Poly1305 nonce.
+pub const AEAD_NONCE_LEN: usize = 12;
+/// Maximum size (in 8-bit integers) of a single ChaCha-Poly1305 message: blocks 1 through
+/// 2<sup>32</sup> - 1, exactly as in RFC 8439 (section 2.8).
+pub const AEAD_MAX_MESSAGE_LEN: u64 = u32::MAX as u64 * MATRIX_SIZE_U8 as u64;
+
+/// ChaCha-Poly1305 AEAD with `R` rounds of ChaCha.
+///
+/// **A nonce must never be used to encrypt more than one message under the same key.**
+///
+/// ```
+/// use chachacha::ChaCha20Poly1305;
+///
+/// let aead = ChaCha20Poly1305::new(&[69; 32]);
+/// let nonce = [0; 12];
+/// let mut buf = *b"hello world";
+/// let tag = aead.encrypt_detached(&nonce, b"header", &mut buf).unwrap();
+/// assert!(aead.decrypt_detached(&nonce, b"header", &mut buf, &tag).is_ok());
+/// assert_eq!(&buf, b"hello world");
+/// ```
+#[derive(Clone)]
+pub struct ChaChaPoly1305<R> {
+    key: [u32; 8],
+    _phantom: PhantomData<R>,
+}
+
+/// ChaCha20-Poly1305 AEAD, exactly as specified in RFC 8439.
+pub type ChaCha20Poly1305 = ChaChaPoly1305<R20>;
+/// ChaCha-Poly1305 AEAD with 12 rounds of ChaCha.
+pub type ChaCha12Poly1305 = ChaChaPoly1305<R12>;
+/// ChaCha-Poly1305 AEAD with 8 rounds of ChaCha.
+pub type ChaCha8Poly1305 = ChaChaPoly1305<R8>;
+
+impl<R: DoubleRounds> TryFrom<&[u8]> for ChaChaPoly1305<R> {
+    type Error = Error;
+
+    /// Creates a new `ChaChaPoly1305` instance from a key given as a slice,
+    /// returning [`Error::InvalidLength`] unless it's exactly [`AEAD_KEY_LEN`] bytes long.
+    #[inline]
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        let key = value.try_into().map_err(|_| Error::InvalidLength)?;
+        Ok(Self::new(key))
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<R> zeroize::Zeroize for ChaChaPoly1305<R> {
+    #[inline]
+    fn zeroize(&mut self) {
+        self.key.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<R> Drop for ChaChaPoly1305<R> {
+    #[inline]
+    fn drop(&mut self) {
+        zeroize::Zeroize::zeroize(self);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<R> zeroize::ZeroizeOnDrop for ChaChaPoly1305<R> {}
+
+impl<R: DoubleRounds> From<&Key> for ChaChaPoly1305<R> {
+    #[inline]
+    fn from(value: &Key) -> Self {
+        Self::new(value.as_bytes())
+    }
+}
+
+impl<R: DoubleRounds> From<&SecretKey> for ChaChaPoly1305<R> {
+    #[inline]
+    fn from(value: &SecretKey) -> Self {
+        Self::new(value.as_bytes())
+    }
+}
+
+impl<R: DoubleRounds> ChaChaPoly1305<R> {
+    /// Creates a new `ChaChaPoly1305` instance using `key`.
+    #[inline]
+    pub fn new(key: &[u8; AEAD_KEY_LEN]) -> Self {
+        Self {
+            key: le_words(key),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Encrypts `buf` in place, returning the tag authenticating it and `aad`.
+    ///
+    /// Returns [`Error::InvalidLength`] if `buf` is longer than [`AEAD_MAX_MESSAGE_LEN`],
+    /// in which case it's left untouched.
+    #[inline]
+    pub fn encrypt_detached(
+        &self,
+        nonce: &[u8; AEAD_NONCE_LEN],
+        aad: &[u8],
+        buf: &mut [u8],
+    ) -> Result<[u8; POLY1305_TAG_LEN], Error> {
+        check_message_len(buf.len() as u64)?;
+        let (mut chacha, mut poly) = self.init(nonce);
+        chacha.xor(buf);
+        poly.update_padded(aad);
+        poly.update_padded(buf);
+        Ok(finish(poly, aad.len() as u64, buf.len() as u64).finalize())
+    }
+
+    /// Authenticates `buf` and `aad` against `tag`, and if successful decrypts `buf` in place.
+    ///
+    /// Returns [`Error::InvalidLength`] if `buf` is longer than [`AEAD_MAX_MESSAGE_LEN`], and
+    /// [`Error::TagMismatch`] if authentication fails. In both cases `buf` is left untouched.
+    #[inline]
+    pub fn decrypt_detached(
+        &self,
+        nonce: &[u8; AEAD_NONCE_LEN],
+        aad: &[u8],
+        buf: &mut [u8],
+        tag: &[u8; POLY1305_TAG_LEN],
+    ) -> Result<(), Error> {
+        check_message_len(buf.len() as u64)?;
+        let (mut chacha, mut poly) = self.init(nonce);
+        poly.update_padded(aad);
+        poly.update_padded(buf);
+        if finish(poly, aad.len() as u64, buf.len() as u64).verify(tag) {
+            chacha.xor(buf);
+            Ok(())
+        } else {
+            Err(Error::TagMismatch)
+        }
+    }
+
+    /// Encrypts the message in `buf` in place and appends the tag, authenticating it and `aad`.
+    ///
+    /// The last [`POLY1305_TAG_LEN`] bytes of `buf` are the space reserved for the tag (their
+    /// contents are ignored), and everything before them is the message. Returns
+    /// [`Error::InvalidLength`] if `buf` is too short to hold a tag (or the message is longer
+    /// than [`AEAD_MAX_MESSAGE_LEN`]), in which case it's left untouched.
+    ///
+    /// ```
+    /// use chachacha::ChaCha20Poly1305;
+    ///
+    /// let aead = ChaCha20Poly1305::new(&[69; 32]);
+    /// let mut packet = [0; 11 + 16];
+    /// packet[..11].copy_from_slice(b"hello world");
+    /// aead.seal_in_place(&[0; 12], b"header", &mut packet).unwrap();
+    /// let msg = aead.open_in_place(&[0; 12], b"header", &mut packet).unwrap();
+    /// assert_eq!(msg, b"hello world");
+    /// ```
+    #[inline]
+    pub fn seal_in_place(
+        &self,
+        nonce: &[u8; AEAD_NONCE_LEN],
+        aad: &[u8],
+        buf: &mut [u8],
+    ) -> Result<(), Error> {
+        let (msg, tag) = buf
+            .split_last_chunk_mut::<POLY1305_TAG_LEN>()
+            .ok_or(Error::InvalidLength)?;
+        *tag = self.encrypt_detached(nonce, aad, msg)?;
+        Ok(())
+    }
+
+    /// Authenticates the ciphertext and appended tag in `buf` (as produced by [`Self::seal_in_place`])
+    /// along with `aad`, and if successful decrypts it in place, returning the message.
+    ///
+    /// Returns [`Error::InvalidLength`] if `buf` is too short to hold a tag (or the message is longer
+    /// than [`AEAD_MAX_MESSAGE_LEN`]), and [`Error::TagMismatch`] if authentication fails.
+    /// In both cases `buf` is left untouched.
+    #[inline]
+    pub fn open_in_place<'a>(
+        &self,
+        nonce: &[u8; AEAD_NONCE_LEN],
+        aad: &[u8],
+        buf: &'a mut [u8],
+    ) -> Result<&'a mut [u8], Error> {
+        let (msg, tag) = buf
+            .split_last_chunk_mut::<POLY1305_TAG_LEN>()
+            .ok_or(Error::InvalidLength)?;
+        self.decrypt_detached(nonce, aad, msg, tag)?;
+        Ok(msg)
+    }
+
+    /// Encrypts the message in `buf` in place and pushes the tag onto the end of it.
+    ///
+    /// Returns [`Error::InvalidLength`] if `buf` doesn't have enough spare capacity
+    /// for the tag (or is longer than [`AEAD_MAX_MESSAGE_LEN`]), in which case it's left untouched.
+    #[cfg(feature = "heapless")]
+    #[inline]
+    pub fn seal_heapless<const N: usize>(
+        &self,
+        nonce: &[u8; AEAD_NONCE_LEN],
+        aad: &[u8],
+        buf: &mut heapless::Vec<u8, N>,
+    ) -> Result<(), Error> {
+        if buf.capacity() - buf.len() < POLY1305_TAG_LEN {
+            return Err(Error::InvalidLength);
+        }
+        let tag = self.encrypt_detached(nonce, aad, buf)?;
+        buf.extend_from_slice(&tag)
+            .map_err(|_| Error::InvalidLength)
+    }
+
+    /// Authenticates the ciphertext and appended tag in `buf` (as produced by [`Self::seal_heapless`])
+    /// along with `aad`, and if successful decrypts it in place and removes the tag.
+    ///
+    /// Returns [`Error::InvalidLength`] if `buf` is too short to hold a tag, and [`Error::TagMismatch`]
+    /// if authentication fails. In both cases `buf` is left untouched.
+    #[cfg(feature = "heapless")]
+    #[inline]
+    pub fn open_heapless<const N: usize>(
+        &self,
+        nonce: &[u8; AEAD_NONCE_LEN],
+        aad: &[u8],
+        buf: &mut heapless::Vec<u8, N>,
+    ) -> Result<(), Error> {
+        let len = self.open_in_place(nonce, aad, buf)?.len();
+        buf.truncate(len);
+        Ok(())
+    }
+
+    /// Begins incrementally encrypting a single message using `nonce`.
+    ///
+    /// Useful when the message is too large to hold in memory all at once.
+    /// The resulting tag is identical to that of [`Self::encrypt_detached`].
+    #[inline]
+    pub fn encryptor(&self, nonce: &[u8; AEAD_NONCE_LEN]) -> ChaChaPoly1305Encryptor<R> {
+        ChaChaPoly1305Encryptor {
+            inner: Incremental::new(self.init(nonce)),
+        }
+    }
+
+    /// Begins incrementally decrypting a single message using `nonce`.
+    ///
+    /// Useful when the message is too large to hold in memory all at once.
+    /// The message is accepted or rejected exactly as by [`Self::decrypt_detached`].
+    #[inline]
+    pub fn decryptor(&self, nonce: &[u8; AEAD_NONCE_LEN]) -> ChaChaPoly1305Decryptor<R> {
+        ChaChaPoly1305Decryptor {
+            inner: Incremental::new(self.init(nonce)),
+        }
+    }
+
+    /// Creates the ChaCha instance (positioned at block 1) and the
+    /// Poly1305 instance (keyed with block 0) used for a single message.
+    #[inline]
+    fn init(&self, nonce: &[u8; AEAD_NONCE_LEN]) -> (ChaCha<R, Ietf>, Poly1305) {
+        let mut chacha = ChaCha::<R, Ietf>::new(self.key, 0, le_words(nonce));
+        // Only consumes the first 32 bytes of block 0, which
+        // leaves the counter at 1 for the actual message.
+        let mut poly_key = [0; POLY1305_KEY_LEN];
+        chacha.fill(&mut poly_key);
+        let poly = Poly1305::new(&poly_key);
+        poly_key.fill(0);
+        (chacha, poly)
+    }
+}
+
+/// Incremental ChaCha-Poly1305 encryption of a single message,
+/// created by [`ChaChaPoly1305::encryptor`].
+///
+/// All additional data must be fed in before any of the message.
+///
+/// ```
+/// use chachacha::ChaCha20Poly1305;
+///
+/// let aead = ChaCha20Poly1305::new(&[69; 32]);
+/// let nonce = [0; 12];
+/// let mut whole = *b"hello world";
+/// let tag = aead.encrypt_detached(&nonce, b"header", &mut whole).unwrap();
+///
+/// let mut encryptor = aead.encryptor(&nonce);
+/// encryptor.update_aad(b"head");
+/// encryptor.update_aad(b"er");
+/// let mut pieces = *b"hello world";
+/// let (hello, world) = pieces.split_at_mut(5);
+/// encryptor.update(hello).unwrap();
+/// encryptor.update(world).unwrap();
+/// assert_eq!(encryptor.finalize(), tag);
+/// assert_eq!(pieces, whole);
+/// ```
+pub struct ChaChaPoly1305Encryptor<R> {
+    pub(crate) inner: Incremental<R>,
+}
+
+impl<R: DoubleRounds> ChaChaPoly1305Encryptor<R> {
+    /// Feeds `aad` into the authenticator as additional data.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called after [`Self::update`].
+    #[inline]
+    pub fn update_aad(&mut self, aad: &[u8]) {
+        self.inner.update_aad(aad);
+    }
+
+    /// Encrypts the next piece of the message in place.
+    ///
+    /// Returns [`Error::InvalidLength`] (leaving `buf` and `self` untouched) if the message
+    /// would become longer than [`AEAD_MAX_MESSAGE_LEN`].
+    #[inline]
+    pub fn update(&mut self, buf: &mut [u8]) -> Result<(), Error> {
+        self.inner.start_message(buf.len())?;
+        self.inner.xor(buf);
+        self.inner.poly.update(buf);
+        Ok(())
+    }
+
+    /// Consumes `self`, returning the tag authenticating the
+    /// additional data and the message.
+    #[inline]
+    pub fn finalize(self) -> [u8; POLY1305_TAG_LEN] {
+        self.inner.finish().finalize()
+    }
+}
+
+/// Incremental ChaCha-Poly1305 decryption of a single message,
+/// created by [`ChaChaPoly1305::decryptor`].
+///
+/// All additional data must be fed in before any of the message.
+///
+/// **The output of [`Self::update`] is unauthenticated until [`Self::finalize`]
+/// succeeds, and must not be acted upon before then.**
+pub struct ChaChaPoly1305Decryptor<R> {
+    pub(crate) inner: Incremental<R>,
+}
+
+impl<R: DoubleRounds> ChaChaPoly1305Decryptor<R> {
+    /// Feeds `aad` into the authenticator as additional data.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called after [`Self::update`].
+    #[inline]
+    pub fn update_aad(&mut self, aad: &[u8]) {
+        self.inner.update_aad(aad);
+    }
+
+    /// Decrypts the next piece of the message in place.
+    ///
+    /// Returns [`Error::InvalidLength`] (leaving `buf` and `self` untouched) if the message
+    /// would become longer than [`AEAD_MAX_MESSAGE_LEN`].
+    #[inline]
+    pub fn update(&mut self, buf: &mut [u8]) -> Result<(), Error> {
+        self.inner.start_message(buf.len())?;
+        self.inner.poly.update(buf);
+        self.inner.xor(buf);
+        Ok(())
+    }
+
+    /// Consumes `self`, checking the additional data and message against `tag`.
+    ///
+    /// Returns [`Error::TagMismatch`] if authentication fails, in which
+    /// case everything produced by [`Self::update`] must be discarded.
+    #[inline]
+    pub fn finalize(self, tag: &[u8; POLY1305_TAG_LEN]) -> Result<(), Error> {
+        match self.inner.finish().verify(tag) {
+            true => Ok(()),
+            false => Err(Error::TagMismatch),
+        }
+    }
+}
+
+/// State shared by [`ChaChaPoly1305Encryptor`] and [`ChaChaPoly1305Decryptor`].
+pub(crate) struct Incremental<R> {
+    chacha: ChaCha<R, Ietf>,
+    poly: Poly1305,
+    /// Unused keystream, starting at `index`.
+    keystream: [u8; BUF_LEN_U8],
+    index: usize,
+    aad_len: u64,
+    /// `None` until the message starts (at which point the additional data gets padded).
+    pub(crate) msg_len: Option<u64>,
+}
+
+impl<R: DoubleRounds> Incremental<R> {
+    #[inline]
+    fn new((chacha, poly): (ChaCha<R, Ietf>, Poly1305)) -> Self {
+        Self {
+            chacha,
+            poly,
+            keystream: [0; BUF_LEN_U8],
+            index: BUF_LEN_U8,
+            aad_len: 0,
+            msg_len: None,
+        }
+    }
+
+    #[inline]
+    fn update_aad(&mut self, aad: &[u8]) {
+        assert!(
+            self.msg_len.is_none(),
+            "additional data must come before the message"
+        );
+        self.poly.update(aad);
+        self.aad_len += aad.len() as u64;
+    }
+
+    /// Starts the message (if it hasn't been already), and accounts for the next `len` bytes
+    /// of it, failing if that would take it past [`AEAD_MAX_MESSAGE_LEN`].
+    #[inline]
+    fn start_message(&mut self, len: usize) -> Result<(), Error> {
+        let msg_len = self
+            .msg_len
+            .unwrap_or(0)
+            .checked_add(len as u64)
+            .ok_or(Error::InvalidLength)?;
+        check_message_len(msg_len)?;
+        if self.msg_len.is_none() {
+            self.poly.update_padded(&[]);
+        }
+        self.msg_len = Some(msg_len);
+        Ok(())
+    }
+
+    /// Xors `buf` with the keystream, continuing exactly where the last call left off.
+    #[inline]
+    fn xor(&mut self, mut buf: &mut [u8]) {
+        // Leftovers from the last call.
+        let len = buf.len().min(BUF_LEN_U8 - self.index);
+        let (head, rest) = buf.split_at_mut(len);
+        head.iter_mut()
+            .zip(&self.keystream[self.index..])
+            .for_each(|(dst, src)| *dst ^= src);
+        self.index += len;
+        buf = rest;
+        // Full blocks can be handled directly by ChaCha.
+        let (blocks, rem) = buf.as_chunks_mut::<BUF_LEN_U8>();
+        blocks
+            .iter_mut()
+            .for_each(|block| self.chacha.xor_block(block));
+        if !rem.is_empty() {
+            self.chacha.fill_block(&mut self.keystream);
+            rem.iter_mut()
+                .zip(&self.keystream)
+                .for_each(|(dst, src)| *dst ^= src);
+            self.index = rem.len();
+        }
+    }
+
+    #[inline]
+    fn finish(mut self) -> Poly1305 {
+        // Can't fail, since it doesn't add to the message.
+        let _ = self.start_message(0);
+        self.poly.update_padded(&[]);
+        finish(self.poly, self.aad_len, self.msg_len.unwrap())
+    }
+}
+
+/// Returns [`Error::InvalidLength`] if a message of `len` bytes would wrap the counter.
+#[inline]
+pub(crate) fn check_message_len(len: u64) -> Result<(), Error> {
+    match len <= AEAD_MAX_MESSAGE_LEN {
+        true => Ok(()),
+        false => Err(Error::InvalidLength),
+    }
+}
+
+/// Feeds the lengths of the additional data and ciphertext into `poly`.
+#[inline]
+pub(crate) fn finish(mut poly: Poly1305, aad_len: u64, buf_len: u64) -> Poly1305 {
+    let mut lengths = [0; 16];
+    lengths[..8].copy_from_slice(&aad_len.to_le_bytes());
+    lengths[8..].copy_from_slice(&buf_len.to_le_bytes());
+    poly.update(&lengths);
+    poly
+}
+
+/// Size (in 8-bit integers) of an XChaCha20-Poly1305 nonce.
+pub(crate) const XAEAD_NONCE_LEN: usize = 24;
+
+/// Derives the XChaCha20-Poly1305 subkey for `nonce` (as specified in draft-irtf-cfrg-xchacha),
+/// returning the resulting ChaCha20-Poly1305 instance and the nonce to use with it.
+#[inline]
+pub(crate) fn xchacha20_poly1305(
+    key: &[u8; AEAD_KEY_LEN],
+    nonce: &[u8; XAEAD_NONCE_LEN],
+) -> (ChaCha20Poly1305, [u8; AEAD_NONCE_LEN]) {
+    let (prefix, suffix) = nonce.split_first_chunk::<HCHACHA_INPUT_LEN>().unwrap();
+    let mut subkey = hchacha20(key, prefix);
+    let aead = ChaCha20Poly1305::new(&subkey);
+    subkey.fill(0);
+    let mut inner_nonce = [0; AEAD_NONCE_LEN];
+    inner_nonce[4..].copy_from_slice(suffix);
+    (aead, inner_nonce)
+}
+
+#[cfg(feature = "aead")]
+mod aead_impls {
+    use super::*;
+    use aead::consts::{U0, U12, U16, U32};
+    use aead::{AeadCore, AeadInPlace, Key, KeyInit, KeySizeUser, Nonce, Tag};
+
+    impl<R> KeySizeUser for ChaChaPoly1305<R> {
+        type KeySize = U32;
+    }
+
+    impl<R: DoubleRounds> KeyInit for ChaChaPoly1305<R> {
+        #[inline]
+        fn new(key: &Key<Self>) -> Self {
+            Self::new(key.as_ref())
+        }
+    }
+
+    impl<R> AeadCore for ChaChaPoly1305<R> {
+        type NonceSize = U12;
+        type TagSize = U16;
+        type CiphertextOverhead = U0;
+    }
+
+    impl<R: DoubleRounds> AeadInPlace for ChaChaPoly1305<R> {
+        #[inline]
+        fn encrypt_in_place_detached(
+            &self,
+            nonce: &Nonce<Self>,
+            associated_data: &[u8],
+            buffer: &mut [u8],
+        ) -> aead::Result<Tag<Self>> {
+            self.encrypt_detached(nonce.as_ref(), associated_data, buffer)
+                .map(Into::into)
+                .map_err(|_| aead::Error)
+        }
+
+        #[inline]
+        fn decrypt_in_place_detached(
+            &self,
+            nonce: &Nonce<Self>,
+            associated_data: &[u8],
+            buffer: &mut [u8],
+            tag: &Tag<Self>,
+        ) -> aead::Result<()> {
+            self.decrypt_detached(nonce.as_ref(), associated_data, buffer, tag.as_ref())
+                .map_err(|_| aead::Error)
+        }
+    }
+}
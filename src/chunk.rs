@@ -0,0 +1,100 @@
+/*!
+Module containing [`ChunkCipher`], which encrypts the chunks of backup and deduplicating storage
+systems with nonces derived from the chunk ids, so no nonce ever needs to be stored.
+
+Chunks are encrypted with XChaCha20-Poly1305, using a nonce derived from the 32-byte chunk id
+with HChaCha20 (cascaded over the two halves of the id, then truncated to 24 bytes). Separate
+subkeys are derived from the master key for nonce derivation and for encryption, and the chunk id
+is authenticated as additional data, so a chunk can't be passed off as any other chunk.
+
+Encryption is deterministic, which is what allows identical chunks to be deduplicated after
+encryption. The price is one rule that must always be followed:
+
+**A chunk id must never be used for two different plaintexts.**
+
+The id should be a collision-resistant hash of the plaintext (typically a keyed hash, so the ids
+themselves don't reveal whether someone else stores a known chunk), in which case the rule holds
+automatically. The API enforces everything else: callers can never supply a nonce, nonces are
+only ever derived from the id they're used with, and the nonce derivation key is never used to
+encrypt anything.
+*/
+
+use crate::chacha_poly1305::*;
+use crate::poly1305::POLY1305_TAG_LEN;
+use crate::{Error, HCHACHA_INPUT_LEN, derive_subkey, hchacha20};
+
+/// Size (in 8-bit integers) of a chunk id.
+pub const CHUNK_ID_LEN: usize = 32;
+
+/// Convergent encryption of content-addressed chunks.
+///
+/// ```
+/// use chachacha::ChunkCipher;
+///
+/// let cipher = ChunkCipher::new(&[69; 32]);
+/// let id = [1; 32]; // Should be a (keyed) hash of the chunk.
+/// let mut a = *b"chunk contents";
+/// let mut b = *b"chunk contents";
+/// let tag_a = cipher.seal_chunk(&id, &mut a).unwrap();
+/// let tag_b = cipher.seal_chunk(&id, &mut b).unwrap();
+/// // Identical chunks encrypt identically, so they can be deduplicated.
+/// assert_eq!((a, tag_a), (b, tag_b));
+/// assert!(cipher.open_chunk(&id, &mut a, &tag_a).is_ok());
+/// assert_eq!(&a, b"chunk contents");
+/// ```
+pub struct ChunkCipher {
+    nonce_key: [u8; 32],
+    encryption_key: [u8; AEAD_KEY_LEN],
+}
+
+impl ChunkCipher {
+    /// Creates a new `ChunkCipher` instance using `master_key`.
+    #[inline]
+    pub fn new(master_key: &[u8; 32]) -> Self {
+        Self {
+            nonce_key: derive_subkey(master_key, 0, b"chunknon"),
+            encryption_key: derive_subkey(master_key, 0, b"chunkenc"),
+        }
+    }
+
+    /// Returns the nonce used for the chunk with id `chunk_id`.
+    ///
+    /// This never needs to be stored, but can be useful for interoperating with other
+    /// XChaCha20-Poly1305 implementations.
+    #[inline]
+    pub fn chunk_nonce(&self, chunk_id: &[u8; CHUNK_ID_LEN]) -> [u8; XAEAD_NONCE_LEN] {
+        let (low, high) = chunk_id.split_at(HCHACHA_INPUT_LEN);
+        let mut key = hchacha20(&self.nonce_key, low.try_into().unwrap());
+        let derived = hchacha20(&key, high.try_into().unwrap());
+        key.fill(0);
+        *derived.first_chunk().unwrap()
+    }
+
+    /// Encrypts the chunk with id `chunk_id` in place, returning the tag authenticating it.
+    ///
+    /// Returns [`Error::InvalidLength`] if `buf` is longer than [`AEAD_MAX_MESSAGE_LEN`],
+    /// in which case it's left untouched.
+    #[inline]
+    pub fn seal_chunk(
+        &self,
+        chunk_id: &[u8; CHUNK_ID_LEN],
+        buf: &mut [u8],
+    ) -> Result<[u8; POLY1305_TAG_LEN], Error> {
+        let (aead, nonce) = xchacha20_poly1305(&self.encryption_key, &self.chunk_nonce(chunk_id));
+        aead.encrypt_detached(&nonce, chunk_id, buf)
+    }
+
+    /// Authenticates the chunk with id `chunk_id` against `tag`, and if successful decrypts it in place.
+    ///
+    /// Returns [`Error::TagMismatch`] if authentication fails, in which case `buf` is left untouched.
+    #[inline]
+    pub fn open_chunk(
+        &self,
+        chunk_id: &[u8; CHUNK_ID_LEN],
+        buf: &mut [u8],
+        tag: &[u8; POLY1305_TAG_LEN],
+    ) -> Result<(), Error> {
+        let (aead, nonce) = xchacha20_poly1305(&self.encryption_key, &self.chunk_nonce(chunk_id));
+        aead.decrypt_detached(&nonce, chunk_id, buf, tag)
+    }
+}
@@ -0,0 +1,121 @@
+/*!
+Module containing the [`KeystreamCursor`] wrapper, which turns the output of a ChaCha instance
+into one continuous keystream regardless of how it's consumed.
+
+The slice methods of the ChaCha types always finish on a block boundary, so a call whose length
+isn't a multiple of 64 discards the rest of its final block, and the next call starts with a fresh
+one. That keeps them fast and stateless, but means encrypting a message in two pieces doesn't give
+the same result as encrypting it all at once. The cursor instead keeps the unconsumed remainder of
+the final block around, and hands it out before generating anything new.
+*/
+
+use crate::ChaChaCore;
+use crate::rounds::*;
+use crate::util::*;
+use crate::variations::*;
+
+/// Wrapper around a ChaCha instance whose `fill`/`xor` calls
+/// continue exactly where the previous one left off.
+///
+/// ```
+/// use chachacha::{ChaCha20Ietf, KeystreamCursor};
+///
+/// let mut all_at_once = [0; 100];
+/// ChaCha20Ietf::new([69; 8], 1, [0; 3]).xor(&mut all_at_once);
+///
+/// let mut cursor = KeystreamCursor::new(ChaCha20Ietf::new([69; 8], 1, [0; 3]));
+/// let mut pieces = [0; 100];
+/// let (a, b) = pieces.split_at_mut(7);
+/// cursor.xor(a);
+/// cursor.xor(b);
+/// assert_eq!(all_at_once, pieces);
+/// ```
+pub struct KeystreamCursor<C> {
+    core: C,
+    block: [u8; MATRIX_SIZE_U8],
+    index: usize,
+}
+
+impl<M, R, V> KeystreamCursor<ChaChaCore<M, R, V>>
+where
+    M: Machine,
+    R: DoubleRounds,
+    V: Variant,
+{
+    /// Creates a new `KeystreamCursor` which continues from the current position of `core`.
+    #[inline]
+    pub fn new(mut core: ChaChaCore<M, R, V>) -> Self {
+        let mut block = [0; MATRIX_SIZE_U8];
+        let offset = (core.position() % MATRIX_SIZE_U8 as u128) as usize;
+        if offset != 0 {
+            // `core` was sought into the middle of a block, so buffer the rest of it.
+            core.seek(core.position() - offset as u128);
+            core.fill(&mut block);
+        }
+        Self {
+            core,
+            block,
+            index: if offset != 0 { offset } else { MATRIX_SIZE_U8 },
+        }
+    }
+
+    /// Returns the position (in bytes) in the keystream that the next call will start at.
+    #[inline]
+    pub fn position(&self) -> u128 {
+        self.core.position() - (MATRIX_SIZE_U8 - self.index) as u128
+    }
+
+    /// Consumes `self`, returning the underlying ChaCha instance positioned
+    /// exactly where the next call would have started.
+    #[inline]
+    pub fn into_inner(mut self) -> ChaChaCore<M, R, V> {
+        let position = self.position();
+        self.core.seek(position);
+        self.block.fill(0);
+        self.core
+    }
+
+    /// Fills `dst` with the next bytes of the keystream.
+    #[inline]
+    pub fn fill(&mut self, dst: &mut [u8]) {
+        self.slice::<false>(dst);
+    }
+
+    /// Xors `dst` with the next bytes of the keystream.
+    #[inline]
+    pub fn xor(&mut self, dst: &mut [u8]) {
+        self.slice::<true>(dst);
+    }
+
+    #[inline]
+    fn slice<const XOR: bool>(&mut self, dst: &mut [u8]) {
+        // Start with whatever's left of the buffered block.
+        let len = dst.len().min(MATRIX_SIZE_U8 - self.index);
+        let (head, dst) = dst.split_at_mut(len);
+        self.take::<XOR>(head);
+        // Whole blocks can go straight through `core`.
+        let (body, tail) = dst.split_at_mut(dst.len() - dst.len() % MATRIX_SIZE_U8);
+        if XOR {
+            self.core.xor(body);
+        } else {
+            self.core.fill(body);
+        }
+        // Anything left over starts a new buffered block.
+        if !tail.is_empty() {
+            self.core.fill(&mut self.block);
+            self.index = 0;
+            self.take::<XOR>(tail);
+        }
+    }
+
+    #[inline]
+    fn take<const XOR: bool>(&mut self, dst: &mut [u8]) {
+        let src = &self.block[self.index..self.index + dst.len()];
+        if XOR {
+            dst.iter_mut().zip(src).for_each(|(d, s)| *d ^= s);
+        } else {
+            dst.copy_from_slice(src);
+        }
+        self.index += dst.len();
+    }
+}
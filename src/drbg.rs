@@ -0,0 +1,163 @@
+/*!
+Module containing [`ChaChaDrbg`], a deterministic random bit generator with the explicit
+instantiate/reseed/generate lifecycle of [SP 800-90A], built on ChaCha20 instead of a block cipher.
+
+The structure follows CTR_DRBG (without a derivation function), with the 256-bit ChaCha20 key
+as the entire internal state. Every operation uses the original 64-bit nonce variant of ChaCha20
+with a zero nonce, starting at block 0:
+
+- `update(provided)` replaces the key with the first 32 bytes of keystream xored with `provided`.
+- Instantiating starts from an all-zero key and updates it with `entropy ^ personalization`.
+- Reseeding updates the key with `entropy ^ additional_input`.
+- Generating first updates the key with the additional input (if any), then returns the start of
+  the keystream as output. The 32 bytes starting at the next block boundary (xored with the
+  additional input, if any) become the new key, so compromising the state later reveals nothing
+  about earlier output.
+
+Seed material is always exactly 32 bytes, so callers with longer or variable length inputs are
+expected to condition them first (with a hash, for instance). After `reseed_interval` calls to
+generate, [`ChaChaDrbg::generate`] refuses to produce more output until it's reseeded.
+
+[SP 800-90A]: https://csrc.nist.gov/pubs/sp/800/90/a/r1/final
+*/
+
+use crate::ChaCha;
+use crate::Error;
+use crate::rounds::R20;
+use crate::util::le_words;
+use crate::variations::Djb;
+
+/// Size (in 8-bit integers) of entropy input, personalization strings, and additional input.
+pub const DRBG_SEED_LEN: usize = 32;
+/// The largest reseed interval permitted by SP 800-90A.
+pub const DRBG_MAX_RESEED_INTERVAL: u64 = 1 << 48;
+
+/// Deterministic random bit generator based on ChaCha20.
+///
+/// ```
+/// use chachacha::ChaChaDrbg;
+///
+/// let entropy = [69; 32]; // Should come from a real entropy source.
+/// let mut drbg = ChaChaDrbg::instantiate(&entropy, Some(b"my application, instance #000001"), 1000);
+/// let mut buf = [0; 100];
+/// drbg.generate(&mut buf, None).unwrap();
+/// drbg.generate(&mut buf, Some(&[1; 32])).unwrap();
+/// drbg.reseed(&[42; 32], None);
+/// ```
+pub struct ChaChaDrbg {
+    key: [u32; 8],
+    reseed_counter: u64,
+    reseed_interval: u64,
+}
+
+impl ChaChaDrbg {
+    /// Creates a new `ChaChaDrbg` instance from `entropy` and an optional `personalization`
+    /// string, which must be reseeded after every `reseed_interval` calls to [`Self::generate`].
+    ///
+    /// `reseed_interval` is clamped to between 1 and [`DRBG_MAX_RESEED_INTERVAL`].
+    #[inline]
+    pub fn instantiate(
+        entropy: &[u8; DRBG_SEED_LEN],
+        personalization: Option<&[u8; DRBG_SEED_LEN]>,
+        reseed_interval: u64,
+    ) -> Self {
+        let mut result = Self {
+            key: [0; 8],
+            reseed_counter: 1,
+            reseed_interval: reseed_interval.clamp(1, DRBG_MAX_RESEED_INTERVAL),
+        };
+        result.update(&combine(entropy, personalization));
+        result
+    }
+
+    /// Mixes fresh `entropy` and optional `additional_input` into the state,
+    /// resetting the amount of calls to [`Self::generate`] until the next reseed.
+    #[inline]
+    pub fn reseed(
+        &mut self,
+        entropy: &[u8; DRBG_SEED_LEN],
+        additional_input: Option<&[u8; DRBG_SEED_LEN]>,
+    ) {
+        self.update(&combine(entropy, additional_input));
+        self.reseed_counter = 1;
+    }
+
+    /// Returns `true` if [`Self::generate`] will fail until [`Self::reseed`] is called.
+    #[inline]
+    pub fn needs_reseed(&self) -> bool {
+        self.reseed_counter > self.reseed_interval
+    }
+
+    /// Fills `dst` with output, mixing in the optional `additional_input`.
+    ///
+    /// Returns [`Error::ReseedRequired`] if the reseed interval has been reached,
+    /// in which case `dst` is left untouched.
+    #[inline]
+    pub fn generate(
+        &mut self,
+        dst: &mut [u8],
+        additional_input: Option<&[u8; DRBG_SEED_LEN]>,
+    ) -> Result<(), Error> {
+        if self.needs_reseed() {
+            return Err(Error::ReseedRequired);
+        }
+        if let Some(additional_input) = additional_input {
+            self.update(additional_input);
+        }
+        let mut chacha = self.chacha();
+        chacha.fill(dst);
+        let mut key = [0; DRBG_SEED_LEN];
+        chacha.fill(&mut key);
+        self.set_key(&key, additional_input);
+        key.fill(0);
+        self.reseed_counter += 1;
+        Ok(())
+    }
+
+    /// Reseeds from the operating system's entropy source before filling `dst` with output,
+    /// so the output is unpredictable even to someone who knows the current state.
+    #[cfg(feature = "getrandom")]
+    #[inline]
+    pub fn generate_with_prediction_resistance(
+        &mut self,
+        dst: &mut [u8],
+        additional_input: Option<&[u8; DRBG_SEED_LEN]>,
+    ) -> Result<(), Error> {
+        let mut entropy = [0; DRBG_SEED_LEN];
+        getrandom::fill(&mut entropy)?;
+        self.reseed(&entropy, additional_input);
+        entropy.fill(0);
+        // Can't fail, since the reseed counter was just reset.
+        let _ = self.generate(dst, None);
+        Ok(())
+    }
+
+    #[inline]
+    fn chacha(&self) -> ChaCha<R20, Djb> {
+        ChaCha::new(self.key, 0, [0; 3])
+    }
+
+    #[inline]
+    fn update(&mut self, provided: &[u8; DRBG_SEED_LEN]) {
+        let mut key = [0; DRBG_SEED_LEN];
+        self.chacha().fill(&mut key);
+        self.set_key(&key, Some(provided));
+        key.fill(0);
+    }
+
+    #[inline]
+    fn set_key(&mut self, key: &[u8; DRBG_SEED_LEN], provided: Option<&[u8; DRBG_SEED_LEN]>) {
+        let mut key = combine(key, provided);
+        self.key = le_words(&key);
+        key.fill(0);
+    }
+}
+
+#[inline]
+fn combine(a: &[u8; DRBG_SEED_LEN], b: Option<&[u8; DRBG_SEED_LEN]>) -> [u8; DRBG_SEED_LEN] {
+    let mut result = *a;
+    if let Some(b) = b {
+        result.iter_mut().zip(b).for_each(|(r, b)| *r ^= b);
+    }
+    result
+}
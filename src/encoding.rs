@@ -0,0 +1,191 @@
+/*!
+Module containing the constant-time hex and base64 codecs behind the `from_hex`/`from_base64`
+constructors of [`Key`](crate::Key) and the nonces, along with [`Encoded`], the output of their
+`encode_*` methods.
+
+Keys usually arrive as text (configuration files, environment variables, etc.), and the obvious
+way to decode them uses table lookups and early exits that depend on the secret characters. Every
+character here is instead decoded with the same sequence of arithmetic operations, and validity
+is accumulated into a mask which is only checked once everything has been processed. Only the
+length of the input (which is public anyway) affects the control flow.
+
+Hex accepts either case. Base64 uses the standard alphabet with padding (RFC 4648 section 4),
+and rejects encodings whose unused trailing bits aren't zero, so every value has exactly one
+valid encoding.
+*/
+
+use crate::Error;
+use core::fmt;
+
+/// ASCII text produced by one of the `encode_*` methods.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Encoded<const N: usize>([u8; N]);
+
+impl<const N: usize> Encoded<N> {
+    /// Returns the encoded text.
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        // Only ever filled with ASCII by the encoders below.
+        core::str::from_utf8(&self.0).unwrap()
+    }
+}
+
+impl<const N: usize> AsRef<str> for Encoded<N> {
+    #[inline]
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<const N: usize> fmt::Display for Encoded<N> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl<const N: usize> fmt::Debug for Encoded<N> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+/// Returns -1 if `lo <= c <= hi`, and 0 otherwise.
+#[inline]
+fn in_range(c: i16, lo: i16, hi: i16) -> i16 {
+    ((lo - 1 - c) & (c - hi - 1)) >> 8
+}
+
+/// Returns -1 if `v >= threshold`, and 0 otherwise.
+#[inline]
+fn at_least(v: i16, threshold: i16) -> i16 {
+    (threshold - 1 - v) >> 8
+}
+
+/// Decodes a hex digit, returning its value and a mask which is -1 if it was valid.
+#[inline]
+fn hex_digit(c: u8) -> (i16, i16) {
+    let c = c as i16;
+    let lower = c | 0x20;
+    let digit = in_range(c, 0x30, 0x39);
+    let letter = in_range(lower, 0x61, 0x66);
+    (
+        (digit & (c - 0x30)) | (letter & (lower - 0x57)),
+        digit | letter,
+    )
+}
+
+/// Decodes a base64 character, returning its value and a mask which is -1 if it was valid.
+#[inline]
+fn base64_digit(c: u8) -> (i16, i16) {
+    let c = c as i16;
+    let upper = in_range(c, 0x41, 0x5a);
+    let lower = in_range(c, 0x61, 0x7a);
+    let digit = in_range(c, 0x30, 0x39);
+    let plus = in_range(c, 0x2b, 0x2b);
+    let slash = in_range(c, 0x2f, 0x2f);
+    let value =
+        (upper & (c - 65)) | (lower & (c - 71)) | (digit & (c + 4)) | (plus & 62) | (slash & 63);
+    (value, upper | lower | digit | plus | slash)
+}
+
+/// Encodes a 4-bit value as a lowercase hex digit.
+#[inline]
+fn hex_char(v: u8) -> u8 {
+    let v = v as i16;
+    (v + 0x30 + (at_least(v, 10) & 0x27)) as u8
+}
+
+/// Encodes a 6-bit value as a base64 character.
+#[inline]
+fn base64_char(v: u8) -> u8 {
+    let v = v as i16;
+    let c = v + 65 + (at_least(v, 26) & 6) + (at_least(v, 52) & -75);
+    (c + (at_least(v, 62) & -15) + (at_least(v, 63) & 3)) as u8
+}
+
+/// Decodes the hex in `src` into `dst`, which must be exactly half its length.
+pub fn decode_hex(src: &[u8], dst: &mut [u8]) -> Result<(), Error> {
+    if src.len() != dst.len() * 2 {
+        return Err(Error::InvalidLength);
+    }
+    let mut valid = -1;
+    for (pair, byte) in src.chunks_exact(2).zip(dst.iter_mut()) {
+        let (hi, hi_valid) = hex_digit(pair[0]);
+        let (lo, lo_valid) = hex_digit(pair[1]);
+        *byte = ((hi << 4) | lo) as u8;
+        valid &= hi_valid & lo_valid;
+    }
+    finish(valid, dst)
+}
+
+/// Decodes the padded base64 in `src` into `dst`, which determines the required length of `src`.
+pub fn decode_base64(src: &[u8], dst: &mut [u8]) -> Result<(), Error> {
+    if src.len() != dst.len().div_ceil(3) * 4 {
+        return Err(Error::InvalidLength);
+    }
+    let (data, padding) = src.split_at((dst.len() * 8).div_ceil(6));
+    let mut valid = -1;
+    let mut acc = 0u32;
+    let mut bits = 0;
+    let mut out = dst.iter_mut();
+    for &c in data {
+        let (v, v_valid) = base64_digit(c);
+        valid &= v_valid;
+        acc = (acc << 6) | v as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            *out.next().unwrap() = (acc >> bits) as u8;
+        }
+    }
+    // Any leftover bits must be zero, so that the encoding is canonical.
+    valid &= in_range((acc & ((1 << bits) - 1)) as i16, 0, 0);
+    for &c in padding {
+        valid &= in_range(c as i16, 0x3d, 0x3d);
+    }
+    finish(valid, dst)
+}
+
+/// Encodes `src` as lowercase hex. `N` must be exactly twice the length of `src`.
+pub fn encode_hex<const N: usize>(src: &[u8]) -> Encoded<N> {
+    debug_assert_eq!(N, src.len() * 2);
+    let mut result = [0; N];
+    for (pair, byte) in result.chunks_exact_mut(2).zip(src) {
+        pair[0] = hex_char(byte >> 4);
+        pair[1] = hex_char(byte & 0xf);
+    }
+    Encoded(result)
+}
+
+/// Encodes `src` as padded base64. `N` must be exactly the length of the encoding.
+pub fn encode_base64<const N: usize>(src: &[u8]) -> Encoded<N> {
+    debug_assert_eq!(N, src.len().div_ceil(3) * 4);
+    let mut result = [b'='; N];
+    let mut acc = 0u32;
+    let mut bits = 0;
+    let mut out = result.iter_mut();
+    for &byte in src {
+        acc = (acc << 8) | byte as u32;
+        bits += 8;
+        while bits >= 6 {
+            bits -= 6;
+            *out.next().unwrap() = base64_char((acc >> bits) as u8 & 0x3f);
+        }
+    }
+    if bits != 0 {
+        *out.next().unwrap() = base64_char((acc << (6 - bits)) as u8 & 0x3f);
+    }
+    Encoded(result)
+}
+
+#[inline]
+fn finish(valid: i16, dst: &mut [u8]) -> Result<(), Error> {
+    if core::hint::black_box(valid) == -1 {
+        Ok(())
+    } else {
+        dst.fill(0);
+        Err(Error::InvalidEncoding)
+    }
+}
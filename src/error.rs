@@ -0,0 +1,83 @@
+/*!
+Module containing [`Error`], the error type returned by the fallible APIs of this crate.
+*/
+
+use core::fmt;
+
+/// Errors that can occur when using this crate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Error {
+    /// The authentication tag didn't match the ciphertext and additional data.
+    TagMismatch,
+    /// A buffer was too short (or too long) to be valid.
+    InvalidLength,
+    /// The counter (or sequence number) used as a nonce has run out,
+    /// so nothing more can be encrypted or decrypted with the current key.
+    CounterExhausted,
+    /// The random generator has produced as much output as it's allowed to, and must be reseeded.
+    ReseedRequired,
+    /// A required parameter (such as the key) was never provided.
+    MissingKey,
+    /// Serialized state was malformed, so it can't have come from this crate.
+    InvalidState,
+    /// The requested amount of rounds isn't supported.
+    UnsupportedRounds,
+    /// Text which was supposed to be hex or base64 contained something else.
+    InvalidEncoding,
+    /// The operating system's entropy source couldn't provide any randomness.
+    EntropyUnavailable,
+    /// The requested backend doesn't exist, or can't be used on the running CPU.
+    UnsupportedBackend,
+}
+
+impl fmt::Display for Error {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TagMismatch => f.write_str("authentication tag mismatch"),
+            Self::InvalidLength => f.write_str("invalid buffer length"),
+            Self::CounterExhausted => f.write_str("nonce counter exhausted"),
+            Self::ReseedRequired => f.write_str("reseed required"),
+            Self::MissingKey => f.write_str("missing key"),
+            Self::InvalidState => f.write_str("invalid serialized state"),
+            Self::UnsupportedRounds => f.write_str("unsupported round count"),
+            Self::InvalidEncoding => f.write_str("invalid encoding"),
+            Self::EntropyUnavailable => f.write_str("entropy source unavailable"),
+            Self::UnsupportedBackend => f.write_str("unsupported backend"),
+        }
+    }
+}
+
+impl core::error::Error for Error {}
+
+#[cfg(feature = "getrandom")]
+impl From<getrandom::Error> for Error {
+    #[inline]
+    fn from(_: getrandom::Error) -> Self {
+        Self::EntropyUnavailable
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<Error> for std::io::Error {
+    /// Wraps `value` in an [`io::Error`](std::io::Error) of the closest matching kind,
+    /// so it can be propagated out of I/O code with `?`.
+    #[inline]
+    fn from(value: Error) -> Self {
+        use std::io::ErrorKind;
+        let kind = match value {
+            Error::InvalidLength
+            | Error::MissingKey
+            | Error::UnsupportedRounds
+            | Error::UnsupportedBackend => ErrorKind::InvalidInput,
+            Error::TagMismatch | Error::InvalidState | Error::InvalidEncoding => {
+                ErrorKind::InvalidData
+            }
+            Error::CounterExhausted | Error::ReseedRequired | Error::EntropyUnavailable => {
+                ErrorKind::Other
+            }
+        };
+        Self::new(kind, value)
+    }
+}
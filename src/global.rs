@@ -0,0 +1,56 @@
+/*!
+Module containing a global ChaCha instance for `no_std` targets, protected by the
+[`critical-section`] crate so it can be used from both interrupt handlers and the main loop.
+
+There's no OS to pull entropy from on these targets, so the instance has to be explicitly
+seeded via [`global_init`] (usually from a hardware RNG peripheral) before it's used.
+Output is generated one `BUF_LEN_U8` block per critical section, so large requests don't
+keep interrupts disabled for longer than a single ChaCha computation.
+
+[`critical-section`]: https://crates.io/crates/critical-section
+*/
+
+use crate::ChaCha8Djb;
+use crate::util::*;
+use core::cell::RefCell;
+use critical_section::Mutex;
+
+static GLOBAL: Mutex<RefCell<Option<ChaCha8Djb>>> = Mutex::new(RefCell::new(None));
+
+/// Seeds the global ChaCha instance, replacing it if it was already seeded.
+///
+/// ```
+/// chachacha::global_init([69; chachacha::SEED_LEN_U8]);
+/// let mut buf = [0; 32];
+/// chachacha::global_fill(&mut buf);
+/// ```
+#[inline]
+pub fn global_init(seed: [u8; SEED_LEN_U8]) {
+    critical_section::with(|cs| {
+        GLOBAL.borrow_ref_mut(cs).replace(seed.into());
+    });
+}
+
+/// Returns `true` if the global ChaCha instance has been seeded.
+#[inline]
+pub fn global_is_init() -> bool {
+    critical_section::with(|cs| GLOBAL.borrow_ref(cs).is_some())
+}
+
+/// Fills `dst` with bytes from the output of the global ChaCha instance.
+///
+/// # Panics
+///
+/// If the global instance hasn't been seeded with [`global_init`].
+#[inline]
+pub fn global_fill(dst: &mut [u8]) {
+    dst.chunks_mut(BUF_LEN_U8).for_each(|chunk| {
+        critical_section::with(|cs| {
+            GLOBAL
+                .borrow_ref_mut(cs)
+                .as_mut()
+                .expect("global ChaCha instance used before `global_init`")
+                .fill(chunk);
+        });
+    });
+}
@@ -0,0 +1,123 @@
+/*!
+Module containing HChaCha, the keyed permutation used to derive subkeys from a ChaCha key
+and a 128-bit input (most notably as the first step of XChaCha).
+
+Only a single block is ever computed at a time, so this is a plain scalar implementation
+instead of going through the batched backends.
+*/
+
+use crate::rounds::*;
+use crate::util::*;
+
+/// Size (in 8-bit integers) of an HChaCha key.
+pub const HCHACHA_KEY_LEN: usize = 32;
+/// Size (in 8-bit integers) of an HChaCha input (the nonce, in the case of XChaCha).
+pub const HCHACHA_INPUT_LEN: usize = 16;
+/// Size (in 8-bit integers) of an HChaCha output.
+pub const HCHACHA_OUTPUT_LEN: usize = 32;
+
+/// Computes HChaCha20 over `key` and `input`, returning the derived 256-bit subkey.
+///
+/// This is exactly the function specified in [draft-irtf-cfrg-xchacha].
+///
+/// ```
+/// use chachacha::hchacha20;
+///
+/// let subkey = hchacha20(&[69; 32], &[42; 16]);
+/// assert_ne!(subkey, hchacha20(&[69; 32], &[43; 16]));
+/// ```
+///
+/// [draft-irtf-cfrg-xchacha]: https://datatracker.ietf.org/doc/html/draft-irtf-cfrg-xchacha-03#section-2.2
+#[inline]
+pub fn hchacha20(
+    key: &[u8; HCHACHA_KEY_LEN],
+    input: &[u8; HCHACHA_INPUT_LEN],
+) -> [u8; HCHACHA_OUTPUT_LEN] {
+    hchacha_bytes::<R20>(key, input)
+}
+
+/// Computes HChaCha with 12 rounds over `key` and `input`, returning the derived 256-bit subkey.
+#[inline]
+pub fn hchacha12(
+    key: &[u8; HCHACHA_KEY_LEN],
+    input: &[u8; HCHACHA_INPUT_LEN],
+) -> [u8; HCHACHA_OUTPUT_LEN] {
+    hchacha_bytes::<R12>(key, input)
+}
+
+/// Computes HChaCha with 8 rounds over `key` and `input`, returning the derived 256-bit subkey.
+#[inline]
+pub fn hchacha8(
+    key: &[u8; HCHACHA_KEY_LEN],
+    input: &[u8; HCHACHA_INPUT_LEN],
+) -> [u8; HCHACHA_OUTPUT_LEN] {
+    hchacha_bytes::<R8>(key, input)
+}
+
+#[inline]
+fn hchacha_bytes<R: DoubleRounds>(
+    key: &[u8; HCHACHA_KEY_LEN],
+    input: &[u8; HCHACHA_INPUT_LEN],
+) -> [u8; HCHACHA_OUTPUT_LEN] {
+    let words = hchacha::<R>(&le_words(key), &le_words(input));
+    let mut output = [0; HCHACHA_OUTPUT_LEN];
+    for (dst, src) in output.chunks_exact_mut(size_of::<u32>()).zip(words) {
+        dst.copy_from_slice(&src.to_le_bytes());
+    }
+    output
+}
+
+/// Computes HChaCha with `R` rounds over `key` and `input`, returning the
+/// first and last rows of the permuted matrix (without the feed-forward addition).
+#[inline]
+pub(crate) fn hchacha<R: DoubleRounds>(key: &[u32; 8], input: &[u32; 4]) -> [u32; 8] {
+    let mut matrix = [0; MATRIX_SIZE_U32];
+    matrix[..4].copy_from_slice(unsafe { &ROW_A.u32x4 });
+    matrix[4..12].copy_from_slice(key);
+    matrix[12..].copy_from_slice(input);
+    permute::<R>(&mut matrix);
+    let mut result = [0; 8];
+    result[..4].copy_from_slice(&matrix[..4]);
+    result[4..].copy_from_slice(&matrix[12..]);
+    matrix.fill(0);
+    result
+}
+
+/// Applies `R` double rounds to `matrix` in place, with no feed-forward addition.
+///
+/// Shared with [`Machine::single_block`](crate::util::Machine::single_block), which only
+/// ever needs a single block as well.
+#[inline]
+pub(crate) fn permute<R: DoubleRounds>(matrix: &mut [u32; MATRIX_SIZE_U32]) {
+    for _ in 0..R::COUNT {
+        // Column rounds
+        quarter_round(matrix, 0, 4, 8, 12);
+        quarter_round(matrix, 1, 5, 9, 13);
+        quarter_round(matrix, 2, 6, 10, 14);
+        quarter_round(matrix, 3, 7, 11, 15);
+        // Diagonal rounds
+        quarter_round(matrix, 0, 5, 10, 15);
+        quarter_round(matrix, 1, 6, 11, 12);
+        quarter_round(matrix, 2, 7, 8, 13);
+        quarter_round(matrix, 3, 4, 9, 14);
+    }
+}
+
+#[inline(always)]
+fn quarter_round(matrix: &mut [u32; MATRIX_SIZE_U32], a: usize, b: usize, c: usize, d: usize) {
+    matrix[a] = matrix[a].wrapping_add(matrix[b]);
+    matrix[d] ^= matrix[a];
+    matrix[d] = matrix[d].rotate_left(16);
+
+    matrix[c] = matrix[c].wrapping_add(matrix[d]);
+    matrix[b] ^= matrix[c];
+    matrix[b] = matrix[b].rotate_left(12);
+
+    matrix[a] = matrix[a].wrapping_add(matrix[b]);
+    matrix[d] ^= matrix[a];
+    matrix[d] = matrix[d].rotate_left(8);
+
+    matrix[c] = matrix[c].wrapping_add(matrix[d]);
+    matrix[b] ^= matrix[c];
+    matrix[b] = matrix[b].rotate_left(7);
+}
@@ -0,0 +1,348 @@
+/*!
+Module containing infinite iterators over the keystream of a ChaCha instance.
+
+[`ChaChaCore::iter_u8`] and [`ChaChaCore::iter_u64`] pull from the same internal buffer as the
+word-oriented methods (`next_u64`, etc.), so they can be freely interleaved with those without
+any output being skipped or repeated.
+
+[`ChaChaCore::iter_blocks`] and [`ChaChaCore::iter_batches`] instead work like the block methods
+(`fill_block`, etc.), yielding the keystream starting at the current counter, and advancing the
+counter past every block they yield.
+
+[`ChaChaCore::xor_iter`] works like the slice methods (`xor`, etc.) instead, lazily xoring the
+bytes of another iterator with the keystream. Once it's dropped, the counter ends up exactly
+where [`ChaChaCore::xor`] would have left it after xoring all the bytes it yielded.
+*/
+
+use crate::ChaChaCore;
+use crate::rounds::*;
+use crate::util::*;
+use crate::variations::*;
+use core::iter::FusedIterator;
+
+/// Infinite iterator over the keystream of a ChaCha instance, one byte at a time.
+pub struct IterU8<'a, M, R, V> {
+    core: &'a mut ChaChaCore<M, R, V>,
+}
+
+/// Infinite iterator over the keystream of a ChaCha instance, as little-endian `u64` values.
+pub struct IterU64<'a, M, R, V> {
+    core: &'a mut ChaChaCore<M, R, V>,
+}
+
+/// Infinite iterator over the 64-byte blocks of the keystream of a ChaCha instance.
+///
+/// Blocks are generated a full batch at a time, and when the iterator is dropped the
+/// counter is moved back to the first block that wasn't yielded.
+pub struct Blocks<'a, M, R, V>
+where
+    M: Machine,
+    R: DoubleRounds,
+    V: Variant,
+{
+    core: &'a mut ChaChaCore<M, R, V>,
+    batch: [u8; BUF_LEN_U8],
+    index: usize,
+}
+
+/// Iterator which xors the bytes of `I` with the keystream of a ChaCha instance.
+///
+/// Created by [`ChaChaCore::xor_iter`].
+pub struct XorIter<'a, M, R, V, I>
+where
+    M: Machine,
+    R: DoubleRounds,
+    V: Variant,
+{
+    core: &'a mut ChaChaCore<M, R, V>,
+    keystream: ChaChaCore<M, R, V>,
+    iter: I,
+    batch: [u8; BUF_LEN_U8],
+    index: usize,
+    /// Where the first batch starts being used, until it's been generated.
+    skip: usize,
+    consumed: u64,
+}
+
+/// Infinite iterator over the native 256-byte batches of the keystream of a ChaCha instance.
+pub struct Batches<'a, M, R, V> {
+    core: &'a mut ChaChaCore<M, R, V>,
+}
+
+impl<M, R, V> ChaChaCore<M, R, V>
+where
+    M: Machine,
+    R: DoubleRounds,
+    V: Variant,
+{
+    /// Returns an infinite iterator over the bytes of the keystream.
+    ///
+    /// ```
+    /// use chachacha::ChaCha8Djb;
+    ///
+    /// let mut rng = ChaCha8Djb::from(0u8);
+    /// let odd = rng.iter_u8().filter(|x| x % 2 == 1).take(10).count();
+    /// assert_eq!(odd, 10);
+    /// ```
+    #[inline]
+    pub fn iter_u8(&mut self) -> IterU8<'_, M, R, V> {
+        IterU8 { core: self }
+    }
+
+    /// Returns an infinite iterator over the keystream as `u64` values.
+    #[inline]
+    pub fn iter_u64(&mut self) -> IterU64<'_, M, R, V> {
+        IterU64 { core: self }
+    }
+
+    /// Returns an infinite iterator over the 64-byte blocks of the keystream, as defined by RFC 8439.
+    ///
+    /// ```
+    /// use chachacha::ChaCha20Ietf;
+    ///
+    /// let mut chacha = ChaCha20Ietf::new([69; 8], 0, [0; 3]);
+    /// let blocks: Vec<[u8; 64]> = chacha.iter_blocks().take(3).collect();
+    /// assert_eq!(chacha.get_counter(), 3);
+    /// ```
+    #[inline]
+    pub fn iter_blocks(&mut self) -> Blocks<'_, M, R, V> {
+        Blocks {
+            core: self,
+            batch: [0; BUF_LEN_U8],
+            index: BUF_LEN_U8,
+        }
+    }
+
+    /// Returns an infinite iterator over the native 256-byte batches of the keystream,
+    /// each of which is what [`Self::get_block`] would have returned.
+    #[inline]
+    pub fn iter_batches(&mut self) -> Batches<'_, M, R, V> {
+        Batches { core: self }
+    }
+
+    /// Returns an iterator which lazily xors the bytes of `iter` with the keystream,
+    /// producing the same output as [`Self::xor`] would on all of them at once.
+    ///
+    /// The keystream is generated a batch at a time as it's needed, so nothing is ever
+    /// buffered beyond the current batch. The counter is updated once the returned
+    /// iterator is dropped, based on how many bytes it actually yielded.
+    ///
+    /// ```
+    /// use chachacha::ChaCha20Djb;
+    ///
+    /// let mut chacha = ChaCha20Djb::new([69; 8], 0, [0; 3]);
+    /// let encrypted: Vec<u8> = chacha.xor_iter((0..100).map(|i| i as u8)).collect();
+    /// assert_eq!(chacha.get_counter(), 2);
+    ///
+    /// let mut expected: Vec<u8> = (0..100).collect();
+    /// ChaCha20Djb::new([69; 8], 0, [0; 3]).xor(&mut expected);
+    /// assert_eq!(encrypted, expected);
+    /// ```
+    #[inline]
+    pub fn xor_iter<I>(&mut self, iter: I) -> XorIter<'_, M, R, V, I::IntoIter>
+    where
+        I: IntoIterator<Item = u8>,
+    {
+        XorIter {
+            keystream: self.clone_with_counter(self.get_counter()),
+            skip: (self.position() % MATRIX_SIZE_U8 as u128) as usize,
+            core: self,
+            iter: iter.into_iter(),
+            batch: [0; BUF_LEN_U8],
+            index: BUF_LEN_U8,
+            consumed: 0,
+        }
+    }
+}
+
+impl<M, R, V> Iterator for IterU8<'_, M, R, V>
+where
+    M: Machine,
+    R: DoubleRounds,
+    V: Variant,
+{
+    type Item = u8;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(u8::from_le_bytes(self.core.next_bytes()))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, None)
+    }
+}
+
+impl<M, R, V> FusedIterator for IterU8<'_, M, R, V>
+where
+    M: Machine,
+    R: DoubleRounds,
+    V: Variant,
+{
+}
+
+impl<M, R, V> Iterator for Blocks<'_, M, R, V>
+where
+    M: Machine,
+    R: DoubleRounds,
+    V: Variant,
+{
+    type Item = [u8; MATRIX_SIZE_U8];
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index == BUF_LEN_U8 {
+            self.core.fill_block(&mut self.batch);
+            self.index = 0;
+        }
+        let result = self.batch[self.index..self.index + MATRIX_SIZE_U8]
+            .try_into()
+            .unwrap();
+        self.index += MATRIX_SIZE_U8;
+        Some(result)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, None)
+    }
+}
+
+impl<M, R, V> FusedIterator for Blocks<'_, M, R, V>
+where
+    M: Machine,
+    R: DoubleRounds,
+    V: Variant,
+{
+}
+
+impl<M, R, V> Drop for Blocks<'_, M, R, V>
+where
+    M: Machine,
+    R: DoubleRounds,
+    V: Variant,
+{
+    #[inline]
+    fn drop(&mut self) {
+        let unused = ((BUF_LEN_U8 - self.index) / MATRIX_SIZE_U8) as u64;
+        if unused != 0 {
+            let counter = self.core.get_counter().wrapping_sub(unused);
+            self.core.set_counter(counter);
+        }
+        self.batch.fill(0);
+    }
+}
+
+impl<M, R, V> Iterator for Batches<'_, M, R, V>
+where
+    M: Machine,
+    R: DoubleRounds,
+    V: Variant,
+{
+    type Item = [u8; BUF_LEN_U8];
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.core.get_block())
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, None)
+    }
+}
+
+impl<M, R, V> FusedIterator for Batches<'_, M, R, V>
+where
+    M: Machine,
+    R: DoubleRounds,
+    V: Variant,
+{
+}
+
+impl<M, R, V> Iterator for IterU64<'_, M, R, V>
+where
+    M: Machine,
+    R: DoubleRounds,
+    V: Variant,
+{
+    type Item = u64;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(u64::from_le_bytes(self.core.next_bytes()))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, None)
+    }
+}
+
+impl<M, R, V> FusedIterator for IterU64<'_, M, R, V>
+where
+    M: Machine,
+    R: DoubleRounds,
+    V: Variant,
+{
+}
+
+impl<M, R, V, I> Iterator for XorIter<'_, M, R, V, I>
+where
+    M: Machine,
+    R: DoubleRounds,
+    V: Variant,
+    I: Iterator<Item = u8>,
+{
+    type Item = u8;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let value = self.iter.next()?;
+        if self.index == BUF_LEN_U8 {
+            self.keystream.fill_block(&mut self.batch);
+            self.index = core::mem::take(&mut self.skip);
+        }
+        let result = value ^ self.batch[self.index];
+        self.index += 1;
+        self.consumed += 1;
+        Some(result)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<M, R, V, I> ExactSizeIterator for XorIter<'_, M, R, V, I>
+where
+    M: Machine,
+    R: DoubleRounds,
+    V: Variant,
+    I: ExactSizeIterator<Item = u8>,
+{
+}
+
+impl<M, R, V, I> FusedIterator for XorIter<'_, M, R, V, I>
+where
+    M: Machine,
+    R: DoubleRounds,
+    V: Variant,
+    I: FusedIterator<Item = u8>,
+{
+}
+
+impl<M, R, V, I> Drop for XorIter<'_, M, R, V, I>
+where
+    M: Machine,
+    R: DoubleRounds,
+    V: Variant,
+{
+    #[inline]
+    fn drop(&mut self) {
+        self.core.advance_slice(self.consumed);
+        self.batch.fill(0);
+    }
+}
@@ -0,0 +1,58 @@
+/*!
+Module containing [`derive_subkey`], which derives many independent subkeys from a single
+master key, in the style of libsodium's `crypto_kdf`.
+
+libsodium builds `crypto_kdf` on BLAKE2b, so this is a ChaCha-specific design with the same
+interface rather than a byte-compatible port: the subkey is the first `N` bytes of XChaCha20
+keystream, keyed by the master key, using the 24-byte nonce `context || le64(subkey_id) || 0^8`.
+Since HChaCha20 compresses the first 16 bytes of that nonce into a fresh key, every
+(`context`, `subkey_id`) pair yields an unrelated ChaCha20 key, and knowing any number of
+subkeys reveals nothing about the master key or the other subkeys.
+
+The context is an arbitrary 8-byte label (such as `*b"sessions"`) which binds subkeys to their
+purpose, so different parts of an application can't end up with the same subkey by accident.
+*/
+
+use crate::XChaCha20Djb;
+use crate::util::le_words;
+
+/// Size (in 8-bit integers) of a master key.
+pub const KDF_KEY_LEN: usize = 32;
+/// Size (in 8-bit integers) of a context.
+pub const KDF_CONTEXT_LEN: usize = 8;
+/// Minimum size (in 8-bit integers) of a derived subkey.
+pub const KDF_SUBKEY_LEN_MIN: usize = 16;
+/// Maximum size (in 8-bit integers) of a derived subkey.
+pub const KDF_SUBKEY_LEN_MAX: usize = 64;
+
+/// Derives subkey number `subkey_id` for `context` from `master_key`.
+///
+/// `N` must be between [`KDF_SUBKEY_LEN_MIN`] and [`KDF_SUBKEY_LEN_MAX`] (inclusive),
+/// which is checked at compile time.
+///
+/// ```
+/// use chachacha::derive_subkey;
+///
+/// let master_key = [69; 32];
+/// let a: [u8; 32] = derive_subkey(&master_key, 0, b"sessions");
+/// let b: [u8; 32] = derive_subkey(&master_key, 1, b"sessions");
+/// let c: [u8; 32] = derive_subkey(&master_key, 0, b"cookies_");
+/// assert!(a != b && a != c && b != c);
+/// ```
+#[inline]
+pub fn derive_subkey<const N: usize>(
+    master_key: &[u8; KDF_KEY_LEN],
+    subkey_id: u64,
+    context: &[u8; KDF_CONTEXT_LEN],
+) -> [u8; N] {
+    const {
+        assert!(N >= KDF_SUBKEY_LEN_MIN && N <= KDF_SUBKEY_LEN_MAX);
+    }
+    let [c0, c1] = le_words(context);
+    let nonce = [c0, c1, subkey_id as u32, (subkey_id >> 32) as u32, 0, 0];
+    let mut key = le_words(master_key);
+    let mut subkey = [0; N];
+    XChaCha20Djb::new_extended(key, 0, nonce).fill(&mut subkey);
+    key.fill(0);
+    subkey
+}
@@ -0,0 +1,229 @@
+/*!
+Module containing the [`Key`], [`SecretKey`], [`Nonce64`], and [`Nonce96`] wrappers, which give
+the byte arrays passed to the constructors distinct types so they can't be swapped by accident.
+[`SecretKey`] is the stricter of the two key types: it can't be cloned or debug printed at all,
+so the only copy of the key is the one which gets wiped.
+
+All of them are wiped when dropped (using the [`zeroize`](https://docs.rs/zeroize) crate if the `zeroize` feature is
+enabled, so the wipe can't be optimized away), and compare in constant time.
+
+With the `encoding` feature, all of them can be parsed from hex or base64 in constant time.
+Only the nonces can be encoded again, since there's no legitimate reason to print a key.
+*/
+
+use crate::Error;
+#[cfg(feature = "encoding")]
+use crate::encoding::*;
+use crate::util::ct_eq;
+use core::fmt;
+
+macro_rules! secret_bytes {
+    ($(#[$meta:meta])* $name:ident, $len:literal) => {
+        $(#[$meta])*
+        pub struct $name([u8; $len]);
+
+        impl $name {
+            /// Size (in 8-bit integers) of the wrapped value.
+            pub const LEN: usize = $len;
+
+            /// Wraps `bytes`.
+            #[inline]
+            pub const fn new(bytes: [u8; $len]) -> Self {
+                Self(bytes)
+            }
+
+            /// Returns a reference to the wrapped bytes.
+            #[inline]
+            pub fn as_bytes(&self) -> &[u8; $len] {
+                &self.0
+            }
+
+            /// Parses `text` as hex (of either case), in constant time.
+            ///
+            /// Returns [`Error::InvalidLength`] if `text` isn't exactly twice as long as
+            /// [`Self::LEN`], and [`Error::InvalidEncoding`] if it isn't hex.
+            #[cfg(feature = "encoding")]
+            #[inline]
+            pub fn from_hex(text: &str) -> Result<Self, Error> {
+                let mut result = Self([0; $len]);
+                decode_hex(text.as_bytes(), &mut result.0)?;
+                Ok(result)
+            }
+
+            /// Parses `text` as padded base64 (using the standard alphabet), in constant time.
+            ///
+            /// Returns [`Error::InvalidLength`] if `text` isn't the length of an encoding of
+            /// [`Self::LEN`] bytes, and [`Error::InvalidEncoding`] if it isn't canonical base64.
+            #[cfg(feature = "encoding")]
+            #[inline]
+            pub fn from_base64(text: &str) -> Result<Self, Error> {
+                let mut result = Self([0; $len]);
+                decode_base64(text.as_bytes(), &mut result.0)?;
+                Ok(result)
+            }
+        }
+
+        impl From<[u8; $len]> for $name {
+            #[inline]
+            fn from(value: [u8; $len]) -> Self {
+                Self(value)
+            }
+        }
+
+        impl TryFrom<&[u8]> for $name {
+            type Error = Error;
+
+            #[inline]
+            fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+                value.try_into().map(Self).map_err(|_| Error::InvalidLength)
+            }
+        }
+
+        impl PartialEq for $name {
+            #[inline]
+            fn eq(&self, other: &Self) -> bool {
+                ct_eq(&self.0, &other.0)
+            }
+        }
+
+        impl Eq for $name {}
+
+        #[cfg(feature = "zeroize")]
+        impl zeroize::Zeroize for $name {
+            #[inline]
+            fn zeroize(&mut self) {
+                self.0.zeroize();
+            }
+        }
+
+        #[cfg(feature = "zeroize")]
+        impl zeroize::ZeroizeOnDrop for $name {}
+
+        impl Drop for $name {
+            #[inline]
+            fn drop(&mut self) {
+                #[cfg(feature = "zeroize")]
+                zeroize::Zeroize::zeroize(&mut self.0);
+                #[cfg(not(feature = "zeroize"))]
+                self.0.fill(0);
+            }
+        }
+    };
+}
+
+secret_bytes!(
+    /// 256-bit ChaCha key.
+    #[derive(Clone)]
+    Key,
+    32
+);
+secret_bytes!(
+    /// 256-bit ChaCha key which deliberately implements neither `Clone` nor `Debug`,
+    /// and is the preferred way to hand a key to any of the `from_parts` constructors.
+    ///
+    /// ```
+    /// use chachacha::{ChaCha20Djb, ChaCha20Poly1305, Nonce64, SecretKey};
+    ///
+    /// let key = SecretKey::new([69; 32]);
+    /// let mut chacha = ChaCha20Djb::from_parts(&key, 0, &Nonce64::new([0; 8]));
+    /// let aead = ChaCha20Poly1305::from(&key);
+    /// drop(key); // Wiped here, and there are no other copies.
+    /// # let _ = (chacha.get_block(), aead);
+    /// ```
+    ///
+    /// ```compile_fail
+    /// let key = chachacha::SecretKey::new([69; 32]);
+    /// println!("{:?}", key);
+    /// ```
+    SecretKey,
+    32
+);
+
+impl From<Key> for SecretKey {
+    #[inline]
+    fn from(value: Key) -> Self {
+        Self(*value.as_bytes())
+    }
+}
+
+mod private {
+    pub trait Sealed {}
+    impl Sealed for super::Key {}
+    impl Sealed for super::SecretKey {}
+}
+
+/// Types which can be used as a 256-bit ChaCha key: [`Key`] and [`SecretKey`].
+///
+/// This trait is sealed, so it can't be implemented outside of this crate.
+pub trait KeyMaterial: private::Sealed {
+    /// Returns a reference to the key.
+    fn key_bytes(&self) -> &[u8; 32];
+}
+
+impl KeyMaterial for Key {
+    #[inline]
+    fn key_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+impl KeyMaterial for SecretKey {
+    #[inline]
+    fn key_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Key {
+    /// Redacts the key, so it can't end up in logs by accident.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Key(***)")
+    }
+}
+secret_bytes!(
+    /// 64-bit nonce, as used by the [`Djb`](crate::ChaCha20Djb) variants.
+    #[derive(Clone)]
+    Nonce64,
+    8
+);
+secret_bytes!(
+    /// 96-bit nonce, as used by the [`Ietf`](crate::ChaCha20Ietf) variants and ChaCha-Poly1305.
+    #[derive(Clone)]
+    Nonce96,
+    12
+);
+
+macro_rules! encode_methods {
+    ($name:ident, $len:literal) => {
+        #[cfg(feature = "encoding")]
+        impl $name {
+            /// Encodes the nonce as lowercase hex.
+            #[inline]
+            pub fn encode_hex(&self) -> Encoded<{ $len * 2 }> {
+                encode_hex(&self.0)
+            }
+
+            /// Encodes the nonce as padded base64 (using the standard alphabet).
+            #[inline]
+            pub fn encode_base64(&self) -> Encoded<{ usize::div_ceil($len, 3) * 4 }> {
+                encode_base64(&self.0)
+            }
+        }
+    };
+}
+
+encode_methods!(Nonce64, 8);
+encode_methods!(Nonce96, 12);
+
+// Nonces aren't secret, so there's nothing to redact.
+impl fmt::Debug for Nonce64 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Nonce64").field(&self.0).finish()
+    }
+}
+
+impl fmt::Debug for Nonce96 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Nonce96").field(&self.0).finish()
+    }
+}
@@ -27,27 +27,191 @@ assert!(!all_zeros);
 #![allow(clippy::missing_transmute_annotations)]
 #![deny(missing_docs)]
 #![no_std]
+#![cfg_attr(
+    all(target_arch = "arm", feature = "unstable-arm-neon"),
+    feature(stdarch_arm_neon_intrinsics)
+)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
 
 // The reference implementation is only used for testing the vectorized implementations
 // to ensure they're correct; don't bother compiling it when not testing.
 #[cfg(test)]
 mod chacha_reference;
 
+mod advance;
+mod any_rounds;
+mod any_variant;
+#[cfg(feature = "getrandom")]
+mod arc4random;
+#[cfg(any(feature = "futures-io", feature = "tokio"))]
+mod async_io;
+#[cfg(feature = "unstable-backend")]
+pub mod backend;
 mod backends;
+mod builder;
 mod chacha;
+mod chacha_poly1305;
+mod chunk;
+mod cursor;
+mod drbg;
+#[cfg(feature = "encoding")]
+mod encoding;
+mod error;
+#[cfg(feature = "critical-section")]
+mod global;
+mod hchacha;
+mod iter;
+mod kdf;
+mod key;
+mod nonce_sequence;
+mod oneshot;
+mod openssh;
+mod overflow;
+mod poly1305;
+mod quic;
+#[cfg(feature = "alloc")]
+mod random_nonce;
+#[cfg(feature = "std")]
+mod reader;
+#[cfg(feature = "getrandom")]
+mod reseeding;
 mod rounds;
+mod salsa;
+mod secretstream;
+mod sector;
+mod seed;
+mod select;
+pub mod sodium_compat;
+#[cfg(feature = "cipher")]
+mod stream_cipher;
+#[cfg(feature = "std")]
+mod sync;
+#[cfg(feature = "std")]
+mod thread_rng;
+mod tls;
 mod util;
 mod variations;
+mod wireguard;
+#[cfg(feature = "std")]
+mod writer;
 
 use backends::Matrix;
-use chacha::ChaChaCore;
-use rounds::*;
-use variations::*;
+use salsa::Salsa;
 
-pub use util::{BUF_LEN_U8, BUF_LEN_U64, SEED_LEN_U8, SEED_LEN_U32, SEED_LEN_U64};
+pub use advance::CounterAdvance;
+pub use any_rounds::{ChaChaAny, ChaChaAnyDjb, ChaChaAnyIetf};
+pub use any_variant::{
+    ChaCha8AnyVariant, ChaCha12AnyVariant, ChaCha20AnyVariant, ChaChaAnyVariant,
+};
+#[cfg(feature = "getrandom")]
+pub use arc4random::Arc4Random;
+#[cfg(any(feature = "futures-io", feature = "tokio"))]
+pub use async_io::{AsyncDecryptingReader, AsyncEncryptingWriter};
+#[cfg(target_feature = "avx2")]
+pub use backends::avx2::Matrix as Avx2;
+#[cfg(target_feature = "avx512f")]
+pub use backends::avx512::Matrix as Avx512;
+#[cfg(all(
+    target_feature = "neon",
+    any(not(target_arch = "arm"), feature = "unstable-arm-neon")
+))]
+pub use backends::neon::Matrix as Neon;
+pub use backends::soft::Matrix as Soft;
+#[cfg(target_feature = "sse2")]
+pub use backends::sse2::Matrix as Sse2;
+#[cfg(target_feature = "ssse3")]
+pub use backends::ssse3::Matrix as Ssse3;
+pub use builder::ChaChaBuilder;
+pub use chacha::ChaChaCore;
+pub use chacha_poly1305::{
+    AEAD_KEY_LEN, AEAD_MAX_MESSAGE_LEN, AEAD_NONCE_LEN, ChaCha8Poly1305, ChaCha12Poly1305,
+    ChaCha20Poly1305, ChaChaPoly1305, ChaChaPoly1305Decryptor, ChaChaPoly1305Encryptor,
+};
+pub use chunk::{CHUNK_ID_LEN, ChunkCipher};
+pub use cursor::KeystreamCursor;
+pub use drbg::{ChaChaDrbg, DRBG_MAX_RESEED_INTERVAL, DRBG_SEED_LEN};
+#[cfg(feature = "encoding")]
+pub use encoding::Encoded;
+pub use error::Error;
+#[cfg(feature = "critical-section")]
+pub use global::{global_fill, global_init, global_is_init};
+pub use hchacha::{
+    HCHACHA_INPUT_LEN, HCHACHA_KEY_LEN, HCHACHA_OUTPUT_LEN, hchacha8, hchacha12, hchacha20,
+};
+pub use iter::{Batches, Blocks, IterU8, IterU64, XorIter};
+pub use kdf::{
+    KDF_CONTEXT_LEN, KDF_KEY_LEN, KDF_SUBKEY_LEN_MAX, KDF_SUBKEY_LEN_MIN, derive_subkey,
+};
+pub use key::{Key, KeyMaterial, Nonce64, Nonce96, SecretKey};
+pub use nonce_sequence::NonceSequence;
+pub use oneshot::{
+    OneShot, chacha8_djb_xor, chacha8_ietf_xor, chacha12_djb_xor, chacha12_ietf_xor,
+    chacha20_djb_xor, chacha20_ietf_xor,
+};
+pub use openssh::{OPENSSH_KEY_LEN, OPENSSH_LENGTH_LEN, OpenSshChaChaPoly};
+pub use overflow::CounterOverflow;
+pub use poly1305::{POLY1305_KEY_LEN, POLY1305_TAG_LEN, Poly1305};
+pub use quic::{QUIC_HP_KEY_LEN, QUIC_HP_MASK_LEN, QUIC_HP_SAMPLE_LEN, quic_hp_mask};
+#[cfg(feature = "alloc")]
+pub use random_nonce::{RANDOM_NONCE_LEN, RANDOM_NONCE_OVERHEAD, decrypt_with_random_nonce};
+#[cfg(all(feature = "alloc", feature = "getrandom"))]
+pub use random_nonce::{encrypt_with_random_nonce, try_encrypt_with_random_nonce};
+#[cfg(feature = "std")]
+pub use reader::{DecryptingReader, KeystreamReader};
+#[cfg(feature = "getrandom")]
+pub use reseeding::Reseeding;
+pub use rounds::{DoubleRounds, R4, R6, R8, R12, R20, Rounds};
+pub use secretstream::{
+    SECRETSTREAM_ABYTES, SECRETSTREAM_HEADER_LEN, SECRETSTREAM_KEY_LEN, SecretStreamPull,
+    SecretStreamPush, SecretStreamTag,
+};
+pub use sector::{SectorCipher, SectorCipher8, SectorCipher12, SectorCipher20};
+pub use seed::Seed;
+pub use select::{
+    Backend, BackendInfo, BackendReason, active_backend, clear_forced_backend, force_backend,
+};
+#[cfg(feature = "cipher")]
+pub use stream_cipher::ChaChaCipher;
+#[cfg(feature = "std")]
+pub use sync::{SyncChaCha, SyncChaChaHandle};
+#[cfg(feature = "std")]
+pub use thread_rng::{ThreadRng, fill, thread_rng};
+pub use tls::{TlsRecordCipher, tls13_nonce};
+pub use util::{BUF_LEN_U8, BUF_LEN_U64, SEED_LEN_U8, SEED_LEN_U32, SEED_LEN_U64, STATE_LEN_U8};
+pub use variations::{Djb, Ietf, Variant, Variants, XDjb};
+pub use wireguard::{
+    REJECT_AFTER_MESSAGES, REKEY_AFTER_MESSAGES, WireGuardReceiver, WireGuardSender,
+    wireguard_nonce,
+};
+#[cfg(feature = "std")]
+pub use writer::EncryptingWriter;
 
 type ChaCha<R, V> = ChaChaCore<Matrix, R, V>;
 
+/// Salsa20 with a 64-bit counter and a 64-bit nonce.
+pub type Salsa20 = ChaChaCore<Salsa, R20, Djb>;
+/// XSalsa20 with a 64-bit counter and a 192-bit nonce.
+pub type XSalsa20 = ChaChaCore<Salsa, R20, XDjb>;
+
+/// XChaCha with 8 rounds, a 64-bit counter, and a 192-bit nonce.
+pub type XChaCha8Djb = ChaCha<R8, XDjb>;
+/// XChaCha with 12 rounds, a 64-bit counter, and a 192-bit nonce.
+pub type XChaCha12Djb = ChaCha<R12, XDjb>;
+/// XChaCha with 20 rounds, a 64-bit counter, and a 192-bit nonce.
+pub type XChaCha20Djb = ChaCha<R20, XDjb>;
+
+/// ChaCha with 4 rounds, a 64-bit counter, and a 64-bit nonce.
+///
+/// Far too weak for any real use; only intended for benchmarking and cryptanalysis.
+pub type ChaCha4Djb = ChaCha<R4, Djb>;
+/// ChaCha with 6 rounds, a 64-bit counter, and a 64-bit nonce.
+///
+/// Far too weak for any real use; only intended for benchmarking and cryptanalysis.
+pub type ChaCha6Djb = ChaCha<R6, Djb>;
 /// ChaCha with 8 rounds, a 64-bit counter, and a 64-bit nonce.
 pub type ChaCha8Djb = ChaCha<R8, Djb>;
 /// ChaCha with 12 rounds, a 64-bit counter, and a 64-bit nonce.
@@ -55,6 +219,14 @@ pub type ChaCha12Djb = ChaCha<R12, Djb>;
 /// ChaCha with 20 rounds, a 64-bit counter, and a 64-bit nonce.
 pub type ChaCha20Djb = ChaCha<R20, Djb>;
 
+/// ChaCha with 4 rounds, a 32-bit counter, and a 96-bit nonce.
+///
+/// Far too weak for any real use; only intended for benchmarking and cryptanalysis.
+pub type ChaCha4Ietf = ChaCha<R4, Ietf>;
+/// ChaCha with 6 rounds, a 32-bit counter, and a 96-bit nonce.
+///
+/// Far too weak for any real use; only intended for benchmarking and cryptanalysis.
+pub type ChaCha6Ietf = ChaCha<R6, Ietf>;
 /// ChaCha with 8 rounds, a 32-bit counter, and a 96-bit nonce.
 pub type ChaCha8Ietf = ChaCha<R8, Ietf>;
 /// ChaCha with 12 rounds, a 32-bit counter, and a 96-bit nonce.
@@ -62,6 +234,87 @@ pub type ChaCha12Ietf = ChaCha<R12, Ietf>;
 /// ChaCha with 20 rounds, a 32-bit counter, and a 96-bit nonce.
 pub type ChaCha20Ietf = ChaCha<R20, Ietf>;
 
+/// Defines the standard aliases for a specific backend, for users who need to
+/// control exactly which implementation is used (reproducible benchmarks, etc.).
+macro_rules! backend_aliases {
+    ($backend:ident, $name:literal, $($alias:ident = $rounds:ident, $variant:ident;)*) => {
+        $(
+            #[doc = concat!(
+                "[`", stringify!($rounds), "`]-round [`", stringify!($variant),
+                "`] ChaCha, always using the ", $name, " backend."
+            )]
+            pub type $alias = ChaChaCore<$backend, $rounds, $variant>;
+        )*
+    };
+}
+
+#[cfg(target_feature = "avx512f")]
+backend_aliases!(Avx512, "AVX-512",
+    ChaCha8DjbAvx512 = R8, Djb;
+    ChaCha12DjbAvx512 = R12, Djb;
+    ChaCha20DjbAvx512 = R20, Djb;
+    ChaCha8IetfAvx512 = R8, Ietf;
+    ChaCha12IetfAvx512 = R12, Ietf;
+    ChaCha20IetfAvx512 = R20, Ietf;
+);
+#[cfg(target_feature = "avx2")]
+backend_aliases!(Avx2, "AVX2",
+    ChaCha8DjbAvx2 = R8, Djb;
+    ChaCha12DjbAvx2 = R12, Djb;
+    ChaCha20DjbAvx2 = R20, Djb;
+    ChaCha8IetfAvx2 = R8, Ietf;
+    ChaCha12IetfAvx2 = R12, Ietf;
+    ChaCha20IetfAvx2 = R20, Ietf;
+);
+#[cfg(target_feature = "ssse3")]
+backend_aliases!(Ssse3, "SSSE3",
+    ChaCha8DjbSsse3 = R8, Djb;
+    ChaCha12DjbSsse3 = R12, Djb;
+    ChaCha20DjbSsse3 = R20, Djb;
+    ChaCha8IetfSsse3 = R8, Ietf;
+    ChaCha12IetfSsse3 = R12, Ietf;
+    ChaCha20IetfSsse3 = R20, Ietf;
+);
+#[cfg(target_feature = "sse2")]
+backend_aliases!(Sse2, "SSE2",
+    ChaCha8DjbSse2 = R8, Djb;
+    ChaCha12DjbSse2 = R12, Djb;
+    ChaCha20DjbSse2 = R20, Djb;
+    ChaCha8IetfSse2 = R8, Ietf;
+    ChaCha12IetfSse2 = R12, Ietf;
+    ChaCha20IetfSse2 = R20, Ietf;
+);
+#[cfg(all(
+    target_feature = "neon",
+    any(not(target_arch = "arm"), feature = "unstable-arm-neon")
+))]
+backend_aliases!(Neon, "Neon",
+    ChaCha8DjbNeon = R8, Djb;
+    ChaCha12DjbNeon = R12, Djb;
+    ChaCha20DjbNeon = R20, Djb;
+    ChaCha8IetfNeon = R8, Ietf;
+    ChaCha12IetfNeon = R12, Ietf;
+    ChaCha20IetfNeon = R20, Ietf;
+);
+backend_aliases!(Soft, "portable",
+    ChaCha8DjbSoft = R8, Djb;
+    ChaCha12DjbSoft = R12, Djb;
+    ChaCha20DjbSoft = R20, Djb;
+    ChaCha8IetfSoft = R8, Ietf;
+    ChaCha12IetfSoft = R12, Ietf;
+    ChaCha20IetfSoft = R20, Ietf;
+);
+
+/// ChaCha with `DOUBLE_ROUNDS * 2` rounds, a 64-bit counter, and a 64-bit nonce.
+///
+/// Intended for research into non-standard round counts; `ChaChaDjb<10>` is
+/// identical to [`ChaCha20Djb`].
+pub type ChaChaDjb<const DOUBLE_ROUNDS: usize> = ChaCha<Rounds<DOUBLE_ROUNDS>, Djb>;
+/// ChaCha with `DOUBLE_ROUNDS * 2` rounds, a 32-bit counter, and a 96-bit nonce.
+pub type ChaChaIetf<const DOUBLE_ROUNDS: usize> = ChaCha<Rounds<DOUBLE_ROUNDS>, Ietf>;
+/// XChaCha with `DOUBLE_ROUNDS * 2` rounds, a 64-bit counter, and a 192-bit nonce.
+pub type XChaChaDjb<const DOUBLE_ROUNDS: usize> = ChaCha<Rounds<DOUBLE_ROUNDS>, XDjb>;
+
 #[cfg(test)]
 mod tests {
     use super::backends::*;
@@ -80,42 +333,133 @@ mod tests {
     /// produces a quarter of the output per block operation.
     const TEST_LEN_REF: usize = TEST_LEN * 4;
 
-    #[cfg(target_feature = "neon")]
+    #[test]
+    fn chacha_const_rounds() {
+        use super::{ChaCha20Djb, ChaChaDjb};
+
+        test_chacha::<Matrix, Rounds<1>, Djb>();
+        test_chacha::<Matrix, Rounds<3>, Ietf>();
+        test_chacha::<Matrix, Rounds<7>, Djb>();
+        assert_eq!(
+            ChaChaDjb::<10>::new([1; 8], 2, [3; 3]).get_block(),
+            ChaCha20Djb::new([1; 8], 2, [3; 3]).get_block()
+        );
+    }
+
+    #[cfg(all(
+        target_feature = "neon",
+        any(not(target_arch = "arm"), feature = "unstable-arm-neon")
+    ))]
+    #[test]
+    fn chacha_4_djb_neon() {
+        test_chacha::<neon::Matrix, R4, Djb>();
+    }
+
+    #[cfg(all(
+        target_feature = "neon",
+        any(not(target_arch = "arm"), feature = "unstable-arm-neon")
+    ))]
+    #[test]
+    fn chacha_4_ietf_neon() {
+        test_chacha::<neon::Matrix, R4, Ietf>();
+    }
+
+    #[cfg(all(
+        target_feature = "neon",
+        any(not(target_arch = "arm"), feature = "unstable-arm-neon")
+    ))]
+    #[test]
+    fn chacha_6_djb_neon() {
+        test_chacha::<neon::Matrix, R6, Djb>();
+    }
+
+    #[cfg(all(
+        target_feature = "neon",
+        any(not(target_arch = "arm"), feature = "unstable-arm-neon")
+    ))]
+    #[test]
+    fn chacha_6_ietf_neon() {
+        test_chacha::<neon::Matrix, R6, Ietf>();
+    }
+
+    #[cfg(all(
+        target_feature = "neon",
+        any(not(target_arch = "arm"), feature = "unstable-arm-neon")
+    ))]
     #[test]
     fn chacha_8_djb_neon() {
         test_chacha::<neon::Matrix, R8, Djb>();
     }
 
-    #[cfg(target_feature = "neon")]
+    #[cfg(all(
+        target_feature = "neon",
+        any(not(target_arch = "arm"), feature = "unstable-arm-neon")
+    ))]
     #[test]
     fn chacha_8_ietf_neon() {
         test_chacha::<neon::Matrix, R8, Ietf>();
     }
 
-    #[cfg(target_feature = "neon")]
+    #[cfg(all(
+        target_feature = "neon",
+        any(not(target_arch = "arm"), feature = "unstable-arm-neon")
+    ))]
     #[test]
     fn chacha_12_djb_neon() {
         test_chacha::<neon::Matrix, R12, Djb>();
     }
 
-    #[cfg(target_feature = "neon")]
+    #[cfg(all(
+        target_feature = "neon",
+        any(not(target_arch = "arm"), feature = "unstable-arm-neon")
+    ))]
     #[test]
     fn chacha_12_ietf_neon() {
         test_chacha::<neon::Matrix, R12, Ietf>();
     }
 
-    #[cfg(target_feature = "neon")]
+    #[cfg(all(
+        target_feature = "neon",
+        any(not(target_arch = "arm"), feature = "unstable-arm-neon")
+    ))]
     #[test]
     fn chacha_20_djb_neon() {
         test_chacha::<neon::Matrix, R20, Djb>();
     }
 
-    #[cfg(target_feature = "neon")]
+    #[cfg(all(
+        target_feature = "neon",
+        any(not(target_arch = "arm"), feature = "unstable-arm-neon")
+    ))]
     #[test]
     fn chacha_20_ietf_neon() {
         test_chacha::<neon::Matrix, R20, Ietf>();
     }
 
+    #[cfg(target_feature = "avx512f")]
+    #[test]
+    fn chacha_4_djb_avx512() {
+        test_chacha::<avx512::Matrix, R4, Djb>();
+    }
+
+    #[cfg(target_feature = "avx512f")]
+    #[test]
+    fn chacha_4_ietf_avx512() {
+        test_chacha::<avx512::Matrix, R4, Ietf>();
+    }
+
+    #[cfg(target_feature = "avx512f")]
+    #[test]
+    fn chacha_6_djb_avx512() {
+        test_chacha::<avx512::Matrix, R6, Djb>();
+    }
+
+    #[cfg(target_feature = "avx512f")]
+    #[test]
+    fn chacha_6_ietf_avx512() {
+        test_chacha::<avx512::Matrix, R6, Ietf>();
+    }
+
     #[cfg(target_feature = "avx512f")]
     #[test]
     fn chacha_8_djb_avx512() {
@@ -152,6 +496,30 @@ mod tests {
         test_chacha::<avx512::Matrix, R20, Ietf>();
     }
 
+    #[cfg(target_feature = "avx2")]
+    #[test]
+    fn chacha_4_djb_avx2() {
+        test_chacha::<avx2::Matrix, R4, Djb>();
+    }
+
+    #[cfg(target_feature = "avx2")]
+    #[test]
+    fn chacha_4_ietf_avx2() {
+        test_chacha::<avx2::Matrix, R4, Ietf>();
+    }
+
+    #[cfg(target_feature = "avx2")]
+    #[test]
+    fn chacha_6_djb_avx2() {
+        test_chacha::<avx2::Matrix, R6, Djb>();
+    }
+
+    #[cfg(target_feature = "avx2")]
+    #[test]
+    fn chacha_6_ietf_avx2() {
+        test_chacha::<avx2::Matrix, R6, Ietf>();
+    }
+
     #[cfg(target_feature = "avx2")]
     #[test]
     fn chacha_8_djb_avx2() {
@@ -188,6 +556,30 @@ mod tests {
         test_chacha::<avx2::Matrix, R20, Ietf>();
     }
 
+    #[cfg(target_feature = "sse2")]
+    #[test]
+    fn chacha_4_djb_sse2() {
+        test_chacha::<sse2::Matrix, R4, Djb>();
+    }
+
+    #[cfg(target_feature = "sse2")]
+    #[test]
+    fn chacha_4_ietf_sse2() {
+        test_chacha::<sse2::Matrix, R4, Ietf>();
+    }
+
+    #[cfg(target_feature = "sse2")]
+    #[test]
+    fn chacha_6_djb_sse2() {
+        test_chacha::<sse2::Matrix, R6, Djb>();
+    }
+
+    #[cfg(target_feature = "sse2")]
+    #[test]
+    fn chacha_6_ietf_sse2() {
+        test_chacha::<sse2::Matrix, R6, Ietf>();
+    }
+
     #[cfg(target_feature = "sse2")]
     #[test]
     fn chacha_8_djb_sse2() {
@@ -224,6 +616,86 @@ mod tests {
         test_chacha::<sse2::Matrix, R20, Ietf>();
     }
 
+    #[cfg(target_feature = "ssse3")]
+    #[test]
+    fn chacha_4_djb_ssse3() {
+        test_chacha::<ssse3::Matrix, R4, Djb>();
+    }
+
+    #[cfg(target_feature = "ssse3")]
+    #[test]
+    fn chacha_4_ietf_ssse3() {
+        test_chacha::<ssse3::Matrix, R4, Ietf>();
+    }
+
+    #[cfg(target_feature = "ssse3")]
+    #[test]
+    fn chacha_6_djb_ssse3() {
+        test_chacha::<ssse3::Matrix, R6, Djb>();
+    }
+
+    #[cfg(target_feature = "ssse3")]
+    #[test]
+    fn chacha_6_ietf_ssse3() {
+        test_chacha::<ssse3::Matrix, R6, Ietf>();
+    }
+
+    #[cfg(target_feature = "ssse3")]
+    #[test]
+    fn chacha_8_djb_ssse3() {
+        test_chacha::<ssse3::Matrix, R8, Djb>();
+    }
+
+    #[cfg(target_feature = "ssse3")]
+    #[test]
+    fn chacha_8_ietf_ssse3() {
+        test_chacha::<ssse3::Matrix, R8, Ietf>();
+    }
+
+    #[cfg(target_feature = "ssse3")]
+    #[test]
+    fn chacha_12_djb_ssse3() {
+        test_chacha::<ssse3::Matrix, R12, Djb>();
+    }
+
+    #[cfg(target_feature = "ssse3")]
+    #[test]
+    fn chacha_12_ietf_ssse3() {
+        test_chacha::<ssse3::Matrix, R12, Ietf>();
+    }
+
+    #[cfg(target_feature = "ssse3")]
+    #[test]
+    fn chacha_20_djb_ssse3() {
+        test_chacha::<ssse3::Matrix, R20, Djb>();
+    }
+
+    #[cfg(target_feature = "ssse3")]
+    #[test]
+    fn chacha_20_ietf_ssse3() {
+        test_chacha::<ssse3::Matrix, R20, Ietf>();
+    }
+
+    #[test]
+    fn chacha_4_djb_soft() {
+        test_chacha::<soft::Matrix, R4, Djb>();
+    }
+
+    #[test]
+    fn chacha_4_ietf_soft() {
+        test_chacha::<soft::Matrix, R4, Ietf>();
+    }
+
+    #[test]
+    fn chacha_6_djb_soft() {
+        test_chacha::<soft::Matrix, R6, Djb>();
+    }
+
+    #[test]
+    fn chacha_6_ietf_soft() {
+        test_chacha::<soft::Matrix, R6, Ietf>();
+    }
+
     #[test]
     fn chacha_8_djb_soft() {
         test_chacha::<soft::Matrix, R8, Djb>();
@@ -254,6 +726,2680 @@ mod tests {
         test_chacha::<soft::Matrix, R20, Ietf>();
     }
 
+    #[test]
+    fn chacha_dispatch() {
+        test_chacha::<dispatch::Matrix, R8, Djb>();
+        test_chacha::<dispatch::Matrix, R20, Ietf>();
+    }
+
+    #[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
+    #[test]
+    fn dispatched_backends_agree() {
+        use super::Backend;
+
+        // Enough to cover both the interleaved and the leftover batches of AVX-512.
+        const BATCHES: usize = 11;
+        let mut rng = new_rng_secure();
+        let mut seed = [0; SEED_LEN_U8];
+        rng.fill_bytes(&mut seed);
+        let chacha = ChaChaCore::<soft::Matrix, R12, Djb>::from(seed);
+        let mut expected = [[0; BUF_LEN_U8]; BATCHES];
+        chacha.clone().fill(expected.as_flattened_mut());
+
+        let state = chacha.get_naked();
+        // Calling a backend the CPU doesn't support would be UB,
+        // so only the detected ones can be checked.
+        if Backend::Ssse3.is_supported() {
+            let mut actual = [[0; BUF_LEN_U8]; BATCHES];
+            ssse3::Matrix::apply::<R12, Djb, false>(state, &mut actual);
+            assert_eq!(actual, expected);
+        }
+        if Backend::Avx.is_supported() {
+            let mut actual = [[0; BUF_LEN_U8]; BATCHES];
+            unsafe { dispatch::apply_with::<R12, Djb, false>(Backend::Avx, state, &mut actual) };
+            assert_eq!(actual, expected);
+        }
+        if Backend::Avx2.is_supported() {
+            let mut actual = [[0; BUF_LEN_U8]; BATCHES];
+            avx2::Matrix::apply::<R12, Djb, false>(state, &mut actual);
+            assert_eq!(actual, expected);
+        }
+        if Backend::Avx512.is_supported() {
+            let mut actual = expected;
+            avx512::Matrix::apply::<R12, Djb, true>(state, &mut actual);
+            assert_eq!(actual, [[0; BUF_LEN_U8]; BATCHES]);
+        }
+    }
+
+    #[test]
+    fn forced_backends() {
+        use super::{
+            Backend, BackendReason, ChaCha20Ietf, Error, active_backend, clear_forced_backend,
+            force_backend,
+        };
+
+        assert!(Backend::Soft.is_supported());
+        assert_eq!("AVX512".parse::<Backend>(), Ok(Backend::Avx512));
+        assert_eq!("sse3".parse::<Backend>(), Err(Error::UnsupportedBackend));
+
+        let mut expected = [0; 3000];
+        ChaCha20Ietf::new([8; 8], 1, [2; 3]).fill(&mut expected);
+        // Other tests can run at the same time, but every backend produces
+        // the same output, so forcing one never breaks them.
+        for backend in Backend::ALL {
+            if !backend.is_supported() {
+                assert_eq!(force_backend(backend), Err(Error::UnsupportedBackend));
+                continue;
+            }
+            force_backend(backend).unwrap();
+            let info = active_backend();
+            assert_eq!(info.backend(), backend);
+            assert_eq!(info.reason(), BackendReason::Forced);
+            assert!(info.width() >= 32);
+            let mut actual = [0; 3000];
+            ChaCha20Ietf::new([8; 8], 1, [2; 3]).fill(&mut actual);
+            assert_eq!(actual, expected, "{backend}");
+        }
+        clear_forced_backend();
+        assert_ne!(active_backend().reason(), BackendReason::Forced);
+    }
+
+    #[cfg(feature = "autotune")]
+    #[test]
+    fn autotuned_backend() {
+        use super::backends::dispatch;
+
+        let tuned = dispatch::tune();
+        assert!(tuned.is_supported());
+    }
+
+    #[cfg(feature = "cipher")]
+    #[test]
+    fn cipher_rfc8439() {
+        use super::{ChaCha20Ietf, ChaChaCipher};
+        use cipher::{KeyIvInit, StreamCipher, StreamCipherSeek};
+
+        // RFC 8439 section 2.4.2
+        let key: [u8; 32] = core::array::from_fn(|i| i as u8);
+        let nonce = [0, 0, 0, 0, 0, 0, 0, 0x4a, 0, 0, 0, 0];
+        let mut chacha = ChaChaCipher::<ChaCha20Ietf>::new(&key.into(), &nonce.into());
+        chacha.seek(64);
+        let mut buf = *b"Ladies and Gentlemen of the class of '99: If I could offer you only one tip for the future, sunscreen would be it.";
+        // Uneven chunks to make sure the keystream is continuous between calls.
+        let (a, b) = buf.split_at_mut(7);
+        let (b, c) = b.split_at_mut(64);
+        chacha.apply_keystream(a);
+        chacha.apply_keystream(b);
+        chacha.apply_keystream(c);
+        let expected = [
+            0x6e, 0x2e, 0x35, 0x9a, 0x25, 0x68, 0xf9, 0x80, 0x41, 0xba, 0x07, 0x28, 0xdd, 0x0d,
+            0x69, 0x81, 0xe9, 0x7e, 0x7a, 0xec, 0x1d, 0x43, 0x60, 0xc2, 0x0a, 0x27, 0xaf, 0xcc,
+            0xfd, 0x9f, 0xae, 0x0b, 0xf9, 0x1b, 0x65, 0xc5, 0x52, 0x47, 0x33, 0xab, 0x8f, 0x59,
+            0x3d, 0xab, 0xcd, 0x62, 0xb3, 0x57, 0x16, 0x39, 0xd6, 0x24, 0xe6, 0x51, 0x52, 0xab,
+            0x8f, 0x53, 0x0c, 0x35, 0x9f, 0x08, 0x61, 0xd8, 0x07, 0xca, 0x0d, 0xbf, 0x50, 0x0d,
+            0x6a, 0x61, 0x56, 0xa3, 0x8e, 0x08, 0x8a, 0x22, 0xb6, 0x5e, 0x52, 0xbc, 0x51, 0x4d,
+            0x16, 0xcc, 0xf8, 0x06, 0x81, 0x8c, 0xe9, 0x1a, 0xb7, 0x79, 0x37, 0x36, 0x5a, 0xf9,
+            0x0b, 0xbf, 0x74, 0xa3, 0x5b, 0xe6, 0xb4, 0x0b, 0x8e, 0xed, 0xf2, 0x78, 0x5e, 0x42,
+            0x87, 0x4d,
+        ];
+        assert_eq!(buf, expected);
+        assert_eq!(chacha.current_pos::<u64>(), 64 + buf.len() as u64);
+    }
+
+    #[test]
+    fn fork_is_independent() {
+        let mut parent = ChaChaCore::<Matrix, R12, Djb>::from(0u8);
+        let mut child_a = parent.fork();
+        let mut child_b = parent.fork();
+        let blocks = [parent.get_block(), child_a.get_block(), child_b.get_block()];
+        assert_ne!(blocks[0], blocks[1]);
+        assert_ne!(blocks[0], blocks[2]);
+        assert_ne!(blocks[1], blocks[2]);
+    }
+
+    #[test]
+    fn stream_matches_nonce() {
+        let mut djb = ChaChaCore::<Matrix, R8, Djb>::new([0; 8], 0, [1, 2, 3]);
+        assert_eq!(djb.get_stream(), 1 | (2 << 32));
+        djb.set_stream(u128::MAX);
+        assert_eq!(djb.get_stream(), u64::MAX as u128);
+        let mut ietf = ChaChaCore::<Matrix, R8, Ietf>::new([0; 8], 0, [1, 2, 3]);
+        assert_eq!(ietf.get_stream(), 1 | (2 << 32) | (3 << 64));
+        ietf.set_stream(u128::MAX);
+        assert_eq!(ietf.get_stream(), u128::MAX >> 32);
+        assert_eq!(ietf.get_counter(), 0);
+    }
+
+    #[test]
+    fn substream_is_deterministic() {
+        let chacha = ChaChaCore::<Matrix, R20, Ietf>::new([69; 8], 420, [1, 2, 3]);
+        let a = chacha.substream(b"terrain");
+        let b = chacha.substream(b"terrain");
+        let c = chacha.substream(b"terrain\0");
+        assert_eq!(a.get_stream(), b.get_stream());
+        assert_ne!(a.get_stream(), c.get_stream());
+        assert_eq!(a.get_counter(), 0);
+    }
+
+    #[test]
+    fn floats_in_unit_range() {
+        let mut chacha = ChaChaCore::<Matrix, R8, Djb>::from(0u8);
+        for _ in 0..(TEST_COUNT * BUF_LEN_U8) {
+            let f = chacha.next_f32();
+            assert!((0.0..1.0).contains(&f));
+            let d = chacha.next_f64();
+            assert!((0.0..1.0).contains(&d));
+        }
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    fn core_size_is_documented() {
+        use super::{ChaCha20Djb, Salsa20};
+
+        // Keep the "Size and word-oriented output" section of `ChaChaCore` in sync with this.
+        assert_eq!(size_of::<ChaCha20Djb>(), 352);
+        assert_eq!(size_of::<Salsa20>(), 352);
+    }
+
+    #[test]
+    fn fill_u128_matches_fill() {
+        let mut chacha = ChaChaCore::<Matrix, R8, Ietf>::from(69u8);
+        let mut chacha_ref = ChaChaCore::<Matrix, R8, Ietf>::from(69u8);
+        let mut buf = [0u128; 37];
+        let mut buf_ref = [0u8; 37 * size_of::<u128>()];
+        chacha.fill_u128(&mut buf);
+        chacha_ref.fill(&mut buf_ref);
+        for (a, b) in buf.iter().zip(buf_ref.chunks_exact(size_of::<u128>())) {
+            assert_eq!(*a, u128::from_le_bytes(b.try_into().unwrap()));
+        }
+        assert_eq!(chacha.get_counter(), chacha_ref.get_counter());
+    }
+
+    #[test]
+    fn bounded_in_range() {
+        let mut chacha = ChaChaCore::<Matrix, R8, Djb>::from(0u8);
+        assert_eq!(chacha.next_bounded(0), 0);
+        assert_eq!(chacha.next_bounded(1), 0);
+        let mut seen = [false; 6];
+        for _ in 0..(TEST_COUNT * BUF_LEN_U8) {
+            let v = chacha.next_bounded(6);
+            seen[v as usize] = true;
+            assert!(chacha.next_bounded(u64::MAX / 3 + 7) < u64::MAX / 3 + 7);
+        }
+        assert!(seen.into_iter().all(|v| v));
+    }
+
+    #[test]
+    fn poly1305_vectors() {
+        use super::Poly1305;
+
+        // RFC 8439 section 2.5.2
+        let key = [
+            0x85, 0xd6, 0xbe, 0x78, 0x57, 0x55, 0x6d, 0x33, 0x7f, 0x44, 0x52, 0xfe, 0x42, 0xd5,
+            0x06, 0xa8, 0x01, 0x03, 0x80, 0x8a, 0xfb, 0x0d, 0xb2, 0xfd, 0x4a, 0xbf, 0xf6, 0xaf,
+            0x41, 0x49, 0xf5, 0x1b,
+        ];
+        let tag = Poly1305::mac(&key, b"Cryptographic Forum Research Group");
+        assert_eq!(
+            tag,
+            [
+                0xa8, 0x06, 0x1d, 0xc1, 0x30, 0x51, 0x36, 0xc6, 0xc2, 0x2b, 0x8b, 0xaf, 0x0c, 0x01,
+                0x27, 0xa9,
+            ]
+        );
+
+        // Maximal limb values, to exercise the final reduction.
+        let tag = Poly1305::mac(&[0xff; 32], &[0xff; 64]);
+        assert_eq!(
+            tag,
+            [
+                0x90, 0x0f, 0xe3, 0x2b, 0xc1, 0x5f, 0xa8, 0xd7, 0xbc, 0xa8, 0xef, 0xe4, 0xc7, 0xe3,
+                0x7e, 0xb1,
+            ]
+        );
+
+        // Long message fed in uneven pieces.
+        let key: [u8; 32] = core::array::from_fn(|i| (i * 7 + 3) as u8);
+        let msg: [u8; 1000] = core::array::from_fn(|i| (i * 13 + 1) as u8);
+        let expected = [
+            0x1d, 0xd1, 0x5d, 0x0d, 0x98, 0x71, 0xb9, 0x72, 0x5d, 0xab, 0xb7, 0xbe, 0xeb, 0xcc,
+            0xc2, 0x70,
+        ];
+        assert_eq!(Poly1305::mac(&key, &msg), expected);
+        let mut poly = Poly1305::new(&key);
+        msg.chunks(37).for_each(|chunk| poly.update(chunk));
+        assert!(poly.verify(&expected));
+
+        #[cfg(feature = "universal-hash")]
+        {
+            use universal_hash::{KeyInit, UniversalHash};
+
+            // Whole blocks go through unchanged, and padding matches the AEAD padding.
+            let mut poly = <Poly1305 as KeyInit>::new(&key.into());
+            UniversalHash::update_padded(&mut poly, &msg);
+            let mut expected = Poly1305::new(&key);
+            expected.update_padded(&msg);
+            assert_eq!(UniversalHash::finalize(poly), expected.finalize().into());
+            let mut poly = <Poly1305 as KeyInit>::new(&[0xff; 32].into());
+            UniversalHash::update(&mut poly, &[[0xff; 16].into(); 9]);
+            assert_eq!(
+                UniversalHash::finalize(poly),
+                Poly1305::mac(&[0xff; 32], &[0xff; 144]).into()
+            );
+        }
+    }
+
+    #[cfg(all(
+        target_feature = "neon",
+        any(not(target_arch = "arm"), feature = "unstable-arm-neon")
+    ))]
+    #[test]
+    fn poly1305_neon() {
+        test_poly1305::<super::poly1305::neon::Accumulator>();
+    }
+
+    #[cfg(target_feature = "avx2")]
+    #[test]
+    fn poly1305_avx2() {
+        test_poly1305::<super::poly1305::avx2::Accumulator>();
+    }
+
+    #[cfg(any(target_feature = "avx2", target_feature = "neon"))]
+    fn test_poly1305<B: super::poly1305::Backend>() {
+        use super::poly1305::{BLOCK_LEN, Backend, State, soft};
+
+        let mut rng = new_rng_secure();
+        for _ in 0..TEST_COUNT {
+            let mut key = [0; 32];
+            rng.fill_bytes(&mut key);
+            let mut state = State::new(&key);
+            let mut state_ref = state.clone();
+            for _ in 0..TEST_LEN {
+                let mut blocks = [[0; BLOCK_LEN]; TEST_LEN * 4];
+                blocks.iter_mut().for_each(|block| rng.fill_bytes(block));
+                let len = rng.usize() % blocks.len();
+                B::blocks(&mut state, &blocks[..len]);
+                soft::Accumulator::blocks(&mut state_ref, &blocks[..len]);
+                assert_eq!(state.finalize(), state_ref.finalize());
+            }
+        }
+    }
+
+    #[test]
+    fn aead_rfc8439() {
+        use super::{ChaCha20Poly1305, Error};
+
+        // RFC 8439 section 2.8.2
+        let key = core::array::from_fn(|i| 0x80 + i as u8);
+        let nonce = [
+            0x07, 0x00, 0x00, 0x00, 0x40, 0x41, 0x42, 0x43, 0x44, 0x45, 0x46, 0x47,
+        ];
+        let aad = [
+            0x50, 0x51, 0x52, 0x53, 0xc0, 0xc1, 0xc2, 0xc3, 0xc4, 0xc5, 0xc6, 0xc7,
+        ];
+        let plaintext = *b"Ladies and Gentlemen of the class of '99: If I could offer you only one tip for the future, sunscreen would be it.";
+        let aead = ChaCha20Poly1305::new(&key);
+
+        let mut buf = plaintext;
+        let tag = aead.encrypt_detached(&nonce, &aad, &mut buf).unwrap();
+        assert_eq!(
+            buf[..16],
+            [
+                0xd3, 0x1a, 0x8d, 0x34, 0x64, 0x8e, 0x60, 0xdb, 0x7b, 0x86, 0xaf, 0xbc, 0x53, 0xef,
+                0x7e, 0xc2,
+            ]
+        );
+        assert_eq!(
+            tag,
+            [
+                0x1a, 0xe1, 0x0b, 0x59, 0x4f, 0x09, 0xe2, 0x6a, 0x7e, 0x90, 0x2e, 0xcb, 0xd0, 0x60,
+                0x06, 0x91,
+            ]
+        );
+
+        // Tampering with anything must be caught, leaving the buffer untouched.
+        let ciphertext = buf;
+        assert_eq!(
+            aead.decrypt_detached(&nonce, &aad[1..], &mut buf, &tag),
+            Err(Error::TagMismatch)
+        );
+        buf[0] ^= 1;
+        assert_eq!(
+            aead.decrypt_detached(&nonce, &aad, &mut buf, &tag),
+            Err(Error::TagMismatch)
+        );
+        buf[0] ^= 1;
+        assert_eq!(buf, ciphertext);
+        assert_eq!(aead.decrypt_detached(&nonce, &aad, &mut buf, &tag), Ok(()));
+        assert_eq!(buf, plaintext);
+
+        #[cfg(feature = "aead")]
+        {
+            use aead::AeadInPlace;
+
+            let mut buf = plaintext;
+            let trait_tag = aead
+                .encrypt_in_place_detached(&nonce.into(), &aad, &mut buf)
+                .unwrap();
+            assert_eq!(buf, ciphertext);
+            assert_eq!(trait_tag[..], tag);
+            aead.decrypt_in_place_detached(&nonce.into(), &aad, &mut buf, &trait_tag)
+                .unwrap();
+            assert_eq!(buf, plaintext);
+        }
+    }
+
+    #[test]
+    fn aead_appended_tag() {
+        use super::{ChaCha20Poly1305, Error};
+
+        let aead = ChaCha20Poly1305::new(&[3; 32]);
+        let nonce = [9; 12];
+        let mut msg = [0x42; 77];
+        let tag = aead.encrypt_detached(&nonce, b"aad", &mut msg).unwrap();
+        let mut buf = [0x42; 77 + 16];
+        aead.seal_in_place(&nonce, b"aad", &mut buf).unwrap();
+        assert_eq!(buf[..77], msg);
+        assert_eq!(buf[77..], tag);
+        assert_eq!(
+            aead.open_in_place(&nonce, b"bad", &mut buf),
+            Err(Error::TagMismatch)
+        );
+        assert_eq!(
+            aead.open_in_place(&nonce, b"aad", &mut buf),
+            Ok(&mut [0x42; 77][..])
+        );
+        // Tag only.
+        let mut buf = [0; 16];
+        aead.seal_in_place(&nonce, &[], &mut buf).unwrap();
+        assert_eq!(aead.open_in_place(&nonce, &[], &mut buf), Ok(&mut [][..]));
+        assert_eq!(
+            aead.seal_in_place(&nonce, &[], &mut [0; 15]),
+            Err(Error::InvalidLength)
+        );
+        assert_eq!(
+            aead.open_in_place(&nonce, &[], &mut [0; 15]),
+            Err(Error::InvalidLength)
+        );
+
+        #[cfg(feature = "heapless")]
+        {
+            let mut vec = heapless::Vec::<u8, 93>::from_slice(&[0x42; 77]).unwrap();
+            aead.seal_heapless(&nonce, b"aad", &mut vec).unwrap();
+            assert_eq!(vec[..77], msg);
+            assert_eq!(vec[77..], tag);
+            aead.open_heapless(&nonce, b"aad", &mut vec).unwrap();
+            assert_eq!(vec[..], [0x42; 77]);
+            let mut vec = heapless::Vec::<u8, 92>::from_slice(&[0x42; 77]).unwrap();
+            assert_eq!(
+                aead.seal_heapless(&nonce, b"aad", &mut vec),
+                Err(Error::InvalidLength)
+            );
+            assert_eq!(vec[..], [0x42; 77]);
+        }
+    }
+
+    #[test]
+    fn aead_message_limit() {
+        use super::chacha_poly1305::check_message_len;
+        use super::{AEAD_MAX_MESSAGE_LEN, ChaCha20Ietf, Error};
+
+        // Every block after the one holding the Poly1305 key, including the final one
+        // (which the fallible `ChaCha` methods hold back).
+        assert_eq!(AEAD_MAX_MESSAGE_LEN, 274_877_906_880);
+        let chacha = ChaCha20Ietf::new([0; 8], 1, [0; 3]);
+        assert_eq!(chacha.remaining_bytes() + 64, AEAD_MAX_MESSAGE_LEN);
+        assert_eq!(check_message_len(AEAD_MAX_MESSAGE_LEN - 64), Ok(()));
+        assert_eq!(check_message_len(AEAD_MAX_MESSAGE_LEN), Ok(()));
+        assert_eq!(
+            check_message_len(AEAD_MAX_MESSAGE_LEN + 1),
+            Err(Error::InvalidLength)
+        );
+        assert_eq!(check_message_len(u64::MAX), Err(Error::InvalidLength));
+    }
+
+    #[test]
+    fn aead_incremental() {
+        use super::{ChaCha12Poly1305, Error};
+
+        let aead = ChaCha12Poly1305::new(&[3; 32]);
+        let nonce = [5; 12];
+        let aad: [u8; 77] = core::array::from_fn(|i| i as u8);
+        let plaintext: [u8; 1337] = core::array::from_fn(|i| (i * 7) as u8);
+        let mut whole = plaintext;
+        let tag = aead.encrypt_detached(&nonce, &aad, &mut whole).unwrap();
+
+        // Uneven pieces which cross both 64 and 256 byte boundaries.
+        let pieces = [0, 1, 63, 64, 100, 255, 300, 3, 551];
+        let mut encryptor = aead.encryptor(&nonce);
+        aad.chunks(10).for_each(|chunk| encryptor.update_aad(chunk));
+        let mut buf = plaintext;
+        let mut rest = &mut buf[..];
+        for len in pieces {
+            let (piece, tail) = rest.split_at_mut(len);
+            encryptor.update(piece).unwrap();
+            rest = tail;
+        }
+        assert!(rest.is_empty());
+        assert_eq!(encryptor.finalize(), tag);
+        assert_eq!(buf, whole);
+
+        let mut decryptor = aead.decryptor(&nonce);
+        decryptor.update_aad(&aad);
+        decryptor.update(&mut buf.clone()).unwrap();
+        assert_eq!(decryptor.finalize(&[0; 16]), Err(Error::TagMismatch));
+        let mut decryptor = aead.decryptor(&nonce);
+        decryptor.update_aad(&aad);
+        let mut rest = &mut buf[..];
+        for len in pieces.into_iter().rev() {
+            let (piece, tail) = rest.split_at_mut(len);
+            decryptor.update(piece).unwrap();
+            rest = tail;
+        }
+        assert_eq!(decryptor.finalize(&tag), Ok(()));
+        assert_eq!(buf, plaintext);
+
+        // Empty messages still need the additional data padded.
+        let tag = aead.encrypt_detached(&nonce, &aad, &mut []).unwrap();
+        let mut encryptor = aead.encryptor(&nonce);
+        encryptor.update_aad(&aad);
+        assert_eq!(encryptor.finalize(), tag);
+    }
+
+    #[test]
+    fn aead_incremental_message_limit() {
+        use super::{AEAD_MAX_MESSAGE_LEN, ChaCha20Poly1305, Error};
+
+        // Pretend almost the entire message has already gone through.
+        let aead = ChaCha20Poly1305::new(&[3; 32]);
+        let mut encryptor = aead.encryptor(&[5; 12]);
+        encryptor.update(&mut []).unwrap();
+        encryptor.inner.msg_len = Some(AEAD_MAX_MESSAGE_LEN - 10);
+        let mut buf = [0; 11];
+        assert_eq!(encryptor.update(&mut buf), Err(Error::InvalidLength));
+        assert_eq!(buf, [0; 11]);
+        assert_eq!(encryptor.update(&mut buf[..10]), Ok(()));
+        assert_ne!(buf[..10], [0; 10]);
+        assert_eq!(encryptor.update(&mut [0]), Err(Error::InvalidLength));
+        assert_eq!(encryptor.update(&mut []), Ok(()));
+
+        let mut decryptor = aead.decryptor(&[5; 12]);
+        decryptor.update(&mut []).unwrap();
+        decryptor.inner.msg_len = Some(AEAD_MAX_MESSAGE_LEN);
+        assert_eq!(decryptor.update(&mut [0]), Err(Error::InvalidLength));
+    }
+
+    #[test]
+    fn secretstream_matches_libsodium() {
+        use super::{Error, SecretStreamPull, SecretStreamPush, SecretStreamTag};
+
+        // Generated with libsodium's crypto_secretstream_xchacha20poly1305,
+        // calling rekey before the fourth message. Only the first 8 bytes and
+        // the MAC (which covers everything else) of each message are checked.
+        let key = core::array::from_fn(|i| (i * 3 + 1) as u8);
+        let header = core::array::from_fn(|i| (i + 100) as u8);
+        let msg: [u8; 300] = core::array::from_fn(|i| i as u8);
+        type Message<'a> = (&'a [u8], &'a [u8], SecretStreamTag, [u8; 8], [u8; 16]);
+        let messages: [Message; 5] = [
+            (
+                &[],
+                &[],
+                SecretStreamTag::MESSAGE,
+                [0x42, 0x3e, 0x38, 0x8a, 0x83, 0x2d, 0x20, 0xda],
+                [
+                    0x3e, 0x38, 0x8a, 0x83, 0x2d, 0x20, 0xda, 0xdf, 0xf5, 0x9b, 0x6a, 0x51, 0x17,
+                    0x18, 0x7c, 0xbf,
+                ],
+            ),
+            (
+                &msg[..100],
+                b"header",
+                SecretStreamTag::MESSAGE,
+                [0x20, 0x50, 0xb2, 0x1b, 0x8c, 0x85, 0xec, 0xb2],
+                [
+                    0xd9, 0x04, 0xe8, 0xee, 0xe9, 0x73, 0x0f, 0x43, 0xae, 0xa5, 0xcd, 0x16, 0x34,
+                    0x90, 0x84, 0x5b,
+                ],
+            ),
+            (
+                &msg,
+                &[],
+                SecretStreamTag::REKEY,
+                [0x96, 0x1e, 0xc7, 0x93, 0xf2, 0x19, 0x31, 0x00],
+                [
+                    0xd4, 0xe6, 0x42, 0xbf, 0xf5, 0xc2, 0x1b, 0x13, 0x68, 0xe7, 0x21, 0x84, 0xd1,
+                    0xbb, 0x19, 0x54,
+                ],
+            ),
+            (
+                b"abc",
+                &[b'x'; 20],
+                SecretStreamTag::PUSH,
+                [0x2b, 0x9b, 0xe0, 0x2e, 0x3b, 0x74, 0x88, 0xb1],
+                [
+                    0x3b, 0x74, 0x88, 0xb1, 0x0a, 0xa3, 0x18, 0x1f, 0xf7, 0x03, 0x6d, 0x75, 0xfd,
+                    0x60, 0x17, 0xc5,
+                ],
+            ),
+            (
+                b"end",
+                &[],
+                SecretStreamTag::FINAL,
+                [0xd7, 0xb3, 0x0e, 0x80, 0x21, 0x86, 0x84, 0xa2],
+                [
+                    0x21, 0x86, 0x84, 0xa2, 0xb5, 0x87, 0xa6, 0x4e, 0x6b, 0x98, 0x9d, 0xd2, 0x96,
+                    0xc3, 0x9a, 0x1f,
+                ],
+            ),
+        ];
+
+        let mut push = SecretStreamPush::with_header(&key, &header);
+        let mut pull = SecretStreamPull::new(&key, &header);
+        for (i, (msg, aad, tag, prefix, mac)) in messages.into_iter().enumerate() {
+            if i == 3 {
+                push.rekey();
+                pull.rekey();
+            }
+            let mut encrypted = [0; 300 + 17];
+            let encrypted = &mut encrypted[..msg.len() + 17];
+            push.push(msg, aad, tag, encrypted);
+            assert_eq!(encrypted[..8], prefix[..8.min(encrypted.len())]);
+            assert_eq!(encrypted[encrypted.len() - 16..], mac);
+
+            let mut decrypted = [0; 300];
+            let decrypted = &mut decrypted[..msg.len()];
+            assert_eq!(
+                pull.pull(&encrypted[..16], aad, &mut []),
+                Err(Error::InvalidLength)
+            );
+            encrypted[0] ^= 1;
+            assert_eq!(
+                pull.pull(encrypted, aad, decrypted),
+                Err(Error::TagMismatch)
+            );
+            encrypted[0] ^= 1;
+            assert_eq!(pull.pull(encrypted, aad, decrypted), Ok(tag));
+            assert_eq!(decrypted, msg);
+        }
+    }
+
+    #[test]
+    fn hchacha20_vector() {
+        use super::hchacha20;
+
+        // draft-irtf-cfrg-xchacha-03 section 2.2.1
+        let key = core::array::from_fn(|i| i as u8);
+        let input = [
+            0x00, 0x00, 0x00, 0x09, 0x00, 0x00, 0x00, 0x4a, 0x00, 0x00, 0x00, 0x00, 0x31, 0x41,
+            0x59, 0x27,
+        ];
+        assert_eq!(
+            hchacha20(&key, &input),
+            [
+                0x82, 0x41, 0x3b, 0x42, 0x27, 0xb2, 0x7b, 0xfe, 0xd3, 0x0e, 0x42, 0x50, 0x8a, 0x87,
+                0x7d, 0x73, 0xa0, 0xf9, 0xe4, 0xd5, 0x8a, 0x74, 0xa8, 0x53, 0xc1, 0x2e, 0xc4, 0x13,
+                0x26, 0xd3, 0xec, 0xdc,
+            ]
+        );
+    }
+
+    #[test]
+    fn xchacha20_matches_libsodium() {
+        use super::XChaCha20Djb;
+
+        // Generated with libsodium's crypto_stream_xchacha20.
+        let key = core::array::from_fn(|i| i as u32 * 0x04040404 + 0x03020100);
+        let nonce = core::array::from_fn(|i| i as u32 * 0x04040404 + 0x43424140);
+        let mut chacha = XChaCha20Djb::new_extended(key, 0, nonce);
+        let mut buf = [0; 300];
+        chacha.fill(&mut buf);
+        assert_eq!(
+            buf[..16],
+            [
+                0x85, 0xee, 0x31, 0x16, 0x33, 0x7d, 0x23, 0xc6, 0x22, 0x15, 0x34, 0x5c, 0x52, 0x26,
+                0x4d, 0x7f,
+            ]
+        );
+        assert_eq!(
+            buf[284..],
+            [
+                0xec, 0x3d, 0x3e, 0xad, 0x1f, 0x05, 0x68, 0xe3, 0x1a, 0x09, 0x73, 0x42, 0xd0, 0xf6,
+                0x2a, 0x60,
+            ]
+        );
+
+        #[cfg(feature = "cipher")]
+        {
+            use super::ChaChaCipher;
+            use cipher::{KeyIvInit, StreamCipher};
+
+            let key: [u8; 32] = core::array::from_fn(|i| i as u8);
+            let nonce: [u8; 24] = core::array::from_fn(|i| i as u8 + 0x40);
+            let mut cipher = ChaChaCipher::<XChaCha20Djb>::new(&key.into(), &nonce.into());
+            let mut from_cipher = [0; 300];
+            cipher.apply_keystream(&mut from_cipher);
+            assert_eq!(from_cipher, buf);
+        }
+    }
+
+    #[test]
+    fn xor_b2b_matches_xor() {
+        use super::{ChaCha8Djb, Salsa20};
+
+        let src: [u8; 1000] = core::array::from_fn(|i| (i * 31 + 7) as u8);
+        for len in [0, 1, 63, 64, 65, 255, 256, 257, 700, 1000] {
+            let mut expected = src;
+            let mut a = ChaCha8Djb::new([3; 8], u32::MAX as u64, [5; 3]);
+            a.xor(&mut expected[..len]);
+            a.xor(&mut expected[len..]);
+            let mut dst = [0; 1000];
+            let mut b = ChaCha8Djb::new([3; 8], u32::MAX as u64, [5; 3]);
+            b.xor_b2b(&src[..len], &mut dst[..len]);
+            b.xor_b2b(&src[len..], &mut dst[len..]);
+            assert_eq!(dst, expected);
+
+            let mut expected = src;
+            Salsa20::new([3; 8], 9, [5; 3]).xor(&mut expected[..len]);
+            let mut dst = [0; 1000];
+            Salsa20::new([3; 8], 9, [5; 3]).xor_b2b(&src[..len], &mut dst[..len]);
+            assert_eq!(dst[..len], expected[..len]);
+        }
+    }
+
+    #[test]
+    fn apply_keystream_at_offsets() {
+        use super::{ChaCha20Ietf, XChaCha12Djb};
+
+        let mut stream = [0; 1000];
+        let chacha = ChaCha20Ietf::new([1; 8], 0, [2; 3]);
+        ChaCha20Ietf::new([1; 8], 0, [2; 3]).fill(&mut stream);
+        for (start, len) in [
+            (0, 1000),
+            (1, 5),
+            (63, 2),
+            (64, 64),
+            (100, 500),
+            (255, 300),
+            (999, 1),
+        ] {
+            let mut buf = [0; 1000];
+            chacha.apply_keystream_at(start as u64, &mut buf[..len]);
+            assert_eq!(buf[..len], stream[start..start + len]);
+        }
+        // The instance itself is never advanced.
+        assert_eq!(chacha.get_counter(), 0);
+
+        let mut stream = [0; 1000];
+        let xchacha = XChaCha12Djb::new_extended([3; 8], 0, [4; 6]);
+        XChaCha12Djb::new_extended([3; 8], 0, [4; 6]).fill(&mut stream);
+        let mut buf = [0; 900];
+        xchacha.apply_keystream_at(100, &mut buf);
+        assert_eq!(buf, stream[100..]);
+    }
+
+    #[test]
+    fn seek_and_position() {
+        use super::{ChaCha12Ietf, ChaCha20Djb};
+
+        let mut stream = [0; 1000];
+        ChaCha20Djb::new([1; 8], 0, [2; 3]).fill(&mut stream);
+        let mut chacha = ChaCha20Djb::new([1; 8], 0, [2; 3]);
+        for (start, len) in [(0, 1000), (1, 5), (63, 2), (64, 64), (100, 500), (255, 300)] {
+            chacha.seek(start as u128);
+            assert_eq!(chacha.position(), start as u128);
+            let mut buf = [0; 1000];
+            chacha.fill(&mut buf[..len]);
+            assert_eq!(buf[..len], stream[start..start + len]);
+            // The single pass xor honors the offset as well.
+            chacha.seek(start as u128);
+            let mut buf = stream;
+            chacha.xor_b2b(&stream[start..start + len], &mut buf[..len]);
+            assert!(buf[..len].iter().all(|&x| x == 0));
+        }
+        chacha.seek(u64::MAX as u128 * 64 + 3);
+        assert_eq!(chacha.get_counter(), u64::MAX);
+        assert_eq!(chacha.position(), u64::MAX as u128 * 64 + 3);
+
+        let mut chacha = ChaCha12Ietf::new([1; 8], 0, [2; 3]);
+        chacha.seek(1 << 38);
+        assert_eq!(chacha.position(), 0);
+        chacha.seek(130);
+        chacha.fill(&mut [0; 62]);
+        assert_eq!(chacha.position(), 192);
+    }
+
+    #[test]
+    fn keystream_cursor_is_continuous() {
+        use super::{ChaCha8Djb, KeystreamCursor, XSalsa20};
+
+        let mut stream = [0; 2000];
+        ChaCha8Djb::new([1; 8], 5, [2; 3]).fill(&mut stream);
+        let mut cursor = KeystreamCursor::new(ChaCha8Djb::new([1; 8], 5, [2; 3]));
+        let mut buf = [0; 2000];
+        let mut pos = 0;
+        for len in [1, 2, 61, 64, 63, 300, 256, 5, 0, 700, 128] {
+            cursor.fill(&mut buf[pos..pos + len]);
+            pos += len;
+            assert_eq!(cursor.position(), 5 * 64 + pos as u128);
+        }
+        assert_eq!(buf[..pos], stream[..pos]);
+        let mut core = cursor.into_inner();
+        core.fill(&mut buf[pos..]);
+        assert_eq!(buf, stream);
+
+        // Starting from the middle of a block.
+        let mut stream = [0; 500];
+        XSalsa20::new_extended([3; 8], 0, [4; 6]).fill(&mut stream);
+        let mut core = XSalsa20::new_extended([3; 8], 0, [4; 6]);
+        core.seek(10);
+        let mut cursor = KeystreamCursor::new(core);
+        let mut buf = stream;
+        let (a, b) = buf[10..].split_at_mut(33);
+        cursor.xor(a);
+        cursor.xor(b);
+        assert!(buf[10..].iter().all(|&x| x == 0));
+    }
+
+    #[test]
+    fn fill_u32_slices() {
+        use super::ChaCha12Ietf;
+
+        let mut bytes = [0; 4 * 333];
+        ChaCha12Ietf::new([1; 8], 0, [2; 3]).fill(&mut bytes);
+        let mut chacha = ChaCha12Ietf::new([1; 8], 0, [2; 3]);
+        let mut words = [0; 333];
+        chacha.fill_u32(&mut words);
+        for (word, bytes) in words.iter().zip(bytes.chunks_exact(4)) {
+            assert_eq!(*word, u32::from_le_bytes(bytes.try_into().unwrap()));
+        }
+        assert_eq!(chacha.get_counter(), (4 * 333usize).div_ceil(64) as u64);
+    }
+
+    #[test]
+    fn fill_and_xor_u64_slices() {
+        use super::ChaCha20Djb;
+
+        let mut bytes = [0; 8 * 77];
+        ChaCha20Djb::new([1; 8], 0, [2; 3]).fill(&mut bytes);
+        let mut chacha = ChaCha20Djb::new([1; 8], 0, [2; 3]);
+        let mut words = [0; 77];
+        chacha.fill_u64(&mut words);
+        for (word, bytes) in words.iter().zip(bytes.chunks_exact(8)) {
+            assert_eq!(*word, u64::from_le_bytes(bytes.try_into().unwrap()));
+        }
+        assert_eq!(chacha.get_counter(), (8 * 77usize).div_ceil(64) as u64);
+        ChaCha20Djb::new([1; 8], 0, [2; 3]).xor_u64(&mut words);
+        assert!(words.iter().all(|&word| word == 0));
+    }
+
+    #[cfg(feature = "bytemuck")]
+    #[test]
+    fn fill_and_xor_pod() {
+        use super::ChaCha8Ietf;
+
+        let mut bytes = [0; 2 * 99];
+        ChaCha8Ietf::new([1; 8], 0, [2; 3]).fill(&mut bytes);
+        let mut halves = [0u16; 99];
+        ChaCha8Ietf::new([1; 8], 0, [2; 3]).fill_pod(&mut halves);
+        assert_eq!(bytemuck::cast_slice::<u16, u8>(&halves), bytes);
+
+        let mut pixels = [[0u8; 3]; 50];
+        ChaCha8Ietf::new([1; 8], 0, [2; 3]).fill_pod(&mut pixels);
+        assert_eq!(pixels.as_flattened(), &bytes[..150]);
+        ChaCha8Ietf::new([1; 8], 0, [2; 3]).xor_pod(&mut pixels);
+        assert!(pixels.as_flattened().iter().all(|&x| x == 0));
+    }
+
+    #[test]
+    fn keystream_iterators() {
+        use super::ChaCha12Djb;
+
+        let mut rng = ChaCha12Djb::from(7u8);
+        let block = ChaCha12Djb::from(7u8).get_block();
+        assert!(rng.iter_u8().take(100).eq(block[..100].iter().copied()));
+        let mut rng = ChaCha12Djb::from(7u8);
+        let expected = block
+            .chunks_exact(8)
+            .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()));
+        assert!(rng.iter_u64().take(32).eq(expected));
+        assert_eq!(rng.iter_u64().size_hint(), (usize::MAX, None));
+    }
+
+    #[test]
+    fn block_iterators() {
+        use super::ChaCha20Ietf;
+
+        let mut stream = [0; 1024];
+        ChaCha20Ietf::new([1; 8], 0, [2; 3]).fill(&mut stream);
+        let mut chacha = ChaCha20Ietf::new([1; 8], 0, [2; 3]);
+        assert!(
+            chacha
+                .iter_blocks()
+                .take(6)
+                .eq(stream[..384].as_chunks::<64>().0.iter().copied())
+        );
+        // Only the blocks which were actually yielded are skipped.
+        assert_eq!(chacha.get_counter(), 6);
+        assert!(
+            chacha
+                .iter_batches()
+                .take(2)
+                .eq(stream[384..].as_chunks::<256>().0.iter().copied())
+        );
+        assert_eq!(chacha.get_counter(), 14);
+    }
+
+    #[test]
+    fn xor_iterator() {
+        use super::{ChaCha8Ietf, CounterAdvance};
+
+        let data: [u8; 700] = core::array::from_fn(|i| i as u8);
+        let mut expected = data;
+        ChaCha8Ietf::new([4; 8], 0, [5; 3]).xor(&mut expected);
+
+        let mut chacha = ChaCha8Ietf::new([4; 8], 0, [5; 3]);
+        let iter = chacha.xor_iter(data.iter().copied());
+        assert_eq!(iter.len(), 700);
+        assert!(iter.eq(expected.iter().copied()));
+        assert_eq!(chacha.get_counter(), 11);
+
+        // Picks up partway through a block, and only counts what was actually yielded.
+        chacha.set_counter_advance(CounterAdvance::Exact);
+        chacha.seek(30);
+        assert!(
+            chacha
+                .xor_iter(data[30..].iter().copied())
+                .take(300)
+                .eq(expected[30..330].iter().copied())
+        );
+        assert_eq!(chacha.position(), 330);
+        chacha.xor_iter([]).for_each(drop);
+        assert_eq!(chacha.position(), 330);
+    }
+
+    #[test]
+    fn next_words_use_keystream() {
+        use super::ChaCha20Djb;
+
+        let mut stream = [0; 512];
+        ChaCha20Djb::new([3; 8], 0, [4; 3]).fill(&mut stream);
+        let mut chacha = ChaCha20Djb::new([3; 8], 0, [4; 3]);
+        assert_eq!(
+            chacha.next_u32(),
+            u32::from_le_bytes(stream[..4].try_into().unwrap())
+        );
+        assert_eq!(
+            chacha.next_u64(),
+            u64::from_le_bytes(stream[4..12].try_into().unwrap())
+        );
+        // The whole batch is buffered up front.
+        assert_eq!(chacha.get_counter(), 4);
+        for chunk in stream[12..256].chunks_exact(4) {
+            assert_eq!(
+                chacha.next_u32(),
+                u32::from_le_bytes(chunk.try_into().unwrap())
+            );
+        }
+        assert_eq!(
+            chacha.next_u64(),
+            u64::from_le_bytes(stream[256..264].try_into().unwrap())
+        );
+        assert_eq!(chacha.get_counter(), 8);
+    }
+
+    #[test]
+    fn try_from_slices() {
+        use super::{ChaCha20Djb, ChaCha20Poly1305, Error};
+
+        let seed = [7; 64];
+        let mut expected = ChaCha20Djb::from([7u8; SEED_LEN_U8]);
+        let mut chacha = ChaCha20Djb::try_from(&seed[..SEED_LEN_U8]).unwrap();
+        assert_eq!(chacha.get_block(), expected.get_block());
+        assert!(matches!(
+            ChaCha20Djb::try_from(&seed[..SEED_LEN_U8 - 1]),
+            Err(Error::InvalidLength)
+        ));
+        assert!(matches!(
+            ChaCha20Djb::try_from(&seed[..]),
+            Err(Error::InvalidLength)
+        ));
+
+        let mut buf = *b"hello world";
+        let tag = ChaCha20Poly1305::try_from(&seed[..32])
+            .unwrap()
+            .encrypt_detached(&[0; 12], &[], &mut buf)
+            .unwrap();
+        let aead = ChaCha20Poly1305::new(&[7; 32]);
+        assert!(aead.decrypt_detached(&[0; 12], &[], &mut buf, &tag).is_ok());
+        assert!(matches!(
+            ChaCha20Poly1305::try_from(&seed[..31]),
+            Err(Error::InvalidLength)
+        ));
+    }
+
+    #[test]
+    fn new_from_slices_lengths() {
+        use super::{ChaCha20Djb, ChaCha20Ietf, Error, XChaCha20Djb};
+
+        let key = [9; 32];
+        let nonce: [u8; 24] = core::array::from_fn(|i| i as u8);
+        let words: [u32; 6] = le_words(&nonce);
+
+        let mut expected = ChaCha20Djb::new(le_words(&key), 0, [words[0], words[1], 0]);
+        let mut chacha = ChaCha20Djb::new_from_slices(&key, &nonce[..8]).unwrap();
+        assert_eq!(chacha.get_block(), expected.get_block());
+
+        let mut expected = ChaCha20Ietf::new(le_words(&key), 0, [words[0], words[1], words[2]]);
+        let mut chacha = ChaCha20Ietf::new_from_slices(&key, &nonce[..12]).unwrap();
+        assert_eq!(chacha.get_block(), expected.get_block());
+
+        let mut expected = XChaCha20Djb::new_extended(le_words(&key), 0, words);
+        let mut chacha = XChaCha20Djb::new_from_slices(&key, &nonce).unwrap();
+        assert_eq!(chacha.get_block(), expected.get_block());
+
+        for (key_len, nonce_len) in [(31, 8), (33, 8), (32, 7), (32, 12), (32, 24)] {
+            assert!(matches!(
+                ChaCha20Djb::new_from_slices(&[0; 33][..key_len], &nonce[..nonce_len]),
+                Err(Error::InvalidLength)
+            ));
+        }
+        assert!(ChaCha20Ietf::new_from_slices(&key, &nonce[..8]).is_err());
+        assert!(XChaCha20Djb::new_from_slices(&key, &nonce[..8]).is_err());
+    }
+
+    #[test]
+    fn from_bytes_matches_new() {
+        use super::{ChaCha20Djb, ChaCha20Ietf, XChaCha20Djb};
+
+        let key: [u8; 32] = core::array::from_fn(|i| i as u8);
+        let nonce: [u8; 24] = core::array::from_fn(|i| !i as u8);
+        let words: [u32; 6] = le_words(&nonce);
+
+        let mut expected = ChaCha20Djb::new(le_words(&key), 1 << 40, [words[0], words[1], 0]);
+        let mut chacha = ChaCha20Djb::from_bytes(key, 1 << 40, nonce[..8].try_into().unwrap());
+        assert_eq!(chacha.get_block(), expected.get_block());
+
+        let mut expected = ChaCha20Ietf::new(le_words(&key), 7, [words[0], words[1], words[2]]);
+        let mut chacha = ChaCha20Ietf::from_bytes(key, 7, nonce[..12].try_into().unwrap());
+        assert_eq!(chacha.get_block(), expected.get_block());
+
+        let mut expected = XChaCha20Djb::new_extended(le_words(&key), 3, words);
+        let mut chacha = XChaCha20Djb::from_bytes(key, 3, nonce);
+        assert_eq!(chacha.get_block(), expected.get_block());
+    }
+
+    #[test]
+    fn from_key_zeroes_counter_and_nonce() {
+        use super::{ChaCha20Djb, ChaCha20Ietf, XChaCha20Djb};
+
+        let key = [42; 32];
+        let mut chacha = ChaCha20Djb::from_key(key);
+        assert_eq!(chacha.get_counter(), 0);
+        assert_eq!(
+            chacha.get_block(),
+            ChaCha20Djb::from_bytes(key, 0, [0; 8]).get_block()
+        );
+        assert_eq!(
+            ChaCha20Ietf::from_key(key).get_block(),
+            ChaCha20Ietf::from_bytes(key, 0, [0; 12]).get_block()
+        );
+        assert_eq!(
+            XChaCha20Djb::from_key(key).get_block(),
+            XChaCha20Djb::from_bytes(key, 0, [0; 24]).get_block()
+        );
+    }
+
+    #[test]
+    fn builder_validates_parameters() {
+        use super::{ChaCha12Djb, ChaCha20Ietf, ChaChaBuilder, Error, XChaCha8Djb};
+
+        let mut chacha: ChaCha12Djb = ChaChaBuilder::new()
+            .counter(u64::MAX)
+            .nonce(&[3; 8])
+            .key([4; 32])
+            .rounds()
+            .build()
+            .unwrap();
+        assert_eq!(
+            chacha.get_block(),
+            ChaCha12Djb::from_bytes([4; 32], u64::MAX, [3; 8]).get_block()
+        );
+        let mut chacha: XChaCha8Djb = ChaChaBuilder::new()
+            .key([4; 32])
+            .rounds()
+            .variant()
+            .build()
+            .unwrap();
+        assert_eq!(
+            chacha.get_block(),
+            XChaCha8Djb::from_key([4; 32]).get_block()
+        );
+
+        let result: Result<ChaCha20Ietf, _> = ChaChaBuilder::new().variant().build();
+        assert!(matches!(result, Err(Error::MissingKey)));
+        let result: Result<ChaCha20Ietf, _> = ChaChaBuilder::new()
+            .key([0; 32])
+            .nonce(&[0; 8])
+            .variant()
+            .build();
+        assert!(matches!(result, Err(Error::InvalidLength)));
+        let result: Result<ChaCha20Ietf, _> = ChaChaBuilder::new()
+            .key([0; 32])
+            .counter(1 << 32)
+            .variant()
+            .build();
+        assert!(matches!(result, Err(Error::CounterExhausted)));
+        assert!(matches!(
+            ChaChaBuilder::new().key([0; 32]).nonce(&[0; 25]).build(),
+            Err(Error::InvalidLength)
+        ));
+    }
+
+    #[test]
+    fn key_and_nonce_types() {
+        use super::{ChaCha20Djb, ChaCha20Ietf, ChaCha20Poly1305, Error, Key, Nonce64, Nonce96};
+
+        let key = Key::new([5; 32]);
+        assert!(key == Key::from([5; 32]));
+        assert!(key != Key::new([6; 32]));
+        assert!(matches!(
+            Key::try_from(&[0; 31][..]),
+            Err(Error::InvalidLength)
+        ));
+        assert!(Nonce96::try_from(&[1; 12][..]).unwrap() == Nonce96::new([1; 12]));
+
+        let mut chacha = ChaCha20Djb::from_parts(&key, 9, &Nonce64::new([1; 8]));
+        assert_eq!(
+            chacha.get_block(),
+            ChaCha20Djb::from_bytes([5; 32], 9, [1; 8]).get_block()
+        );
+        let mut chacha = ChaCha20Ietf::from_parts(&key, 9, &Nonce96::new([1; 12]));
+        assert_eq!(
+            chacha.get_block(),
+            ChaCha20Ietf::from_bytes([5; 32], 9, [1; 12]).get_block()
+        );
+
+        let mut buf = [0; 16];
+        let tag = ChaCha20Poly1305::from(&key).encrypt_detached(&[0; 12], &[], &mut buf);
+        let expected =
+            ChaCha20Poly1305::new(&[5; 32]).encrypt_detached(&[0; 12], &[], &mut [0; 16]);
+        assert_eq!(tag, expected);
+    }
+
+    #[test]
+    fn secret_key() {
+        use super::{
+            ChaCha20Djb, ChaCha20Ietf, ChaCha20Poly1305, Key, Nonce64, Nonce96, SecretKey,
+        };
+
+        let key = SecretKey::from(Key::new([5; 32]));
+        assert!(key == SecretKey::new([5; 32]));
+        assert!(key != SecretKey::new([6; 32]));
+
+        let mut chacha = ChaCha20Djb::from_parts(&key, 9, &Nonce64::new([1; 8]));
+        assert_eq!(
+            chacha.get_block(),
+            ChaCha20Djb::from_bytes([5; 32], 9, [1; 8]).get_block()
+        );
+        let mut chacha = ChaCha20Ietf::from_parts(&key, 9, &Nonce96::new([1; 12]));
+        assert_eq!(
+            chacha.get_block(),
+            ChaCha20Ietf::from_bytes([5; 32], 9, [1; 12]).get_block()
+        );
+
+        let mut buf = [0; 16];
+        let tag = ChaCha20Poly1305::from(&key).encrypt_detached(&[0; 12], &[], &mut buf);
+        let expected =
+            ChaCha20Poly1305::new(&[5; 32]).encrypt_detached(&[0; 12], &[], &mut [0; 16]);
+        assert_eq!(tag, expected);
+    }
+
+    #[test]
+    fn seed_round_trips() {
+        use super::{ChaCha20Ietf, Seed};
+
+        let flat: [u8; SEED_LEN_U8] = core::array::from_fn(|i| i as u8);
+        let seed = Seed::from(flat);
+        assert_eq!(seed.key.as_bytes()[..], flat[..32]);
+        assert_eq!(seed.nonce.as_bytes()[..], flat[40..]);
+        let mut chacha = ChaCha20Ietf::from(seed.clone());
+        assert_eq!(chacha.get_counter(), seed.counter as u32 as u64);
+        assert_eq!(chacha.get_block(), ChaCha20Ietf::from(flat).get_block());
+        assert_eq!(<[u8; SEED_LEN_U8]>::from(seed), flat);
+    }
+
+    #[test]
+    fn new_exact_matches_new() {
+        use super::{ChaCha12Djb, ChaCha12Ietf};
+
+        let nonce = u64::from_le_bytes(*b"\x01\x02\x03\x04\x05\x06\x07\x08");
+        let mut chacha = ChaCha12Djb::new_exact([7; 8], u64::MAX, nonce);
+        assert_eq!(chacha.get_counter(), u64::MAX);
+        assert_eq!(
+            chacha.get_block(),
+            ChaCha12Djb::from_bytes(
+                core::array::from_fn(|i| if i % 4 == 0 { 7 } else { 0 }),
+                u64::MAX,
+                [1, 2, 3, 4, 5, 6, 7, 8]
+            )
+            .get_block()
+        );
+        let mut chacha = ChaCha12Ietf::new_exact([7; 8], u32::MAX, [1, 2, 3]);
+        assert_eq!(chacha.get_counter(), u32::MAX as u64);
+        assert_eq!(
+            chacha.get_block(),
+            ChaCha12Ietf::new([7; 8], u32::MAX as u64, [1, 2, 3]).get_block()
+        );
+    }
+
+    #[test]
+    fn debug_redacts_key() {
+        use super::{ChaCha8Ietf, Key, Nonce64};
+        use core::fmt::Write;
+
+        struct Buf([u8; 256], usize);
+        impl Write for Buf {
+            fn write_str(&mut self, s: &str) -> core::fmt::Result {
+                self.0[self.1..self.1 + s.len()].copy_from_slice(s.as_bytes());
+                self.1 += s.len();
+                Ok(())
+            }
+        }
+
+        let mut buf = Buf([0; 256], 0);
+        let chacha = ChaCha8Ietf::new([0xdeadbeef; 8], 5, [1, 2, 3]);
+        write!(buf, "{chacha:?}").unwrap();
+        assert_eq!(
+            core::str::from_utf8(&buf.0[..buf.1]).unwrap(),
+            "ChaChaCore { rounds: 8, variant: Ietf, key: ***, counter: 5, nonce: [1, 2, 3], .. }"
+        );
+
+        let mut buf = Buf([0; 256], 0);
+        write!(buf, "{:?} {:?}", Key::new([0xaa; 32]), Nonce64::new([1; 8])).unwrap();
+        assert_eq!(
+            core::str::from_utf8(&buf.0[..buf.1]).unwrap(),
+            "Key(***) Nonce64([1, 1, 1, 1, 1, 1, 1, 1])"
+        );
+    }
+
+    #[test]
+    fn clones_are_positioned() {
+        use super::{ChaCha12Djb, ChaCha12Ietf};
+
+        let mut chacha = ChaCha12Djb::new([1; 8], 0, [2, 3, 0]);
+        let _ = chacha.next_u64();
+        let mut clone = chacha.clone();
+        assert_eq!(clone.next_u64(), chacha.next_u64());
+        assert_eq!(clone.get_block(), chacha.get_block());
+
+        let mut worker = chacha.clone_with_counter(100);
+        assert_eq!(
+            worker.get_block(),
+            ChaCha12Djb::new([1; 8], 100, [2, 3, 0]).get_block()
+        );
+        let nonce = u64::from_ne_bytes(unsafe { transmute([4u32, 5]) });
+        let mut worker = chacha.clone_with_nonce(nonce);
+        assert_eq!(
+            worker.get_block(),
+            ChaCha12Djb::new([1; 8], 0, [4, 5, 0]).get_block()
+        );
+
+        let chacha = ChaCha12Ietf::new([1; 8], 7, [2, 3, 4]);
+        let mut worker = chacha.clone_with_nonce([5, 6, 7]);
+        assert_eq!(
+            worker.get_block(),
+            ChaCha12Ietf::new([1; 8], 0, [5, 6, 7]).get_block()
+        );
+    }
+
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn zeroize_wipes_state() {
+        use super::{ChaCha20Djb, Key};
+        use zeroize::Zeroize;
+
+        let mut chacha = ChaCha20Djb::new([u32::MAX; 8], 9, [1, 2, 3]);
+        let _ = chacha.next_u32();
+        chacha.zeroize();
+        assert_eq!(
+            chacha.get_block(),
+            ChaCha20Djb::new([0; 8], 0, [0; 3]).get_block()
+        );
+
+        let mut key = Key::new([1; 32]);
+        key.zeroize();
+        assert_eq!(key.as_bytes(), &[0; 32]);
+    }
+
+    #[test]
+    fn state_bytes_round_trip() {
+        use super::{ChaCha8Djb, ChaCha8Ietf, Error, STATE_LEN_U8};
+
+        let mut chacha = ChaCha8Djb::new_128([1, 2, 3, 4], 1 << 33, [5, 6, 0]);
+        chacha.seek(chacha.position() + 10);
+        let state = chacha.to_state_bytes();
+        assert_eq!(state[..16], *b"expand 16-byte k");
+        assert_eq!(state[16..20], [1, 0, 0, 0]);
+        assert_eq!(state[48..56], (1u64 << 33).to_le_bytes());
+        assert_eq!(state[56..60], [5, 0, 0, 0]);
+        assert_eq!(state[64], 10);
+        let mut restored = ChaCha8Djb::from_state_bytes(&state).unwrap();
+        assert_eq!(restored.position(), chacha.position());
+        let (mut a, mut b) = ([0; 100], [0; 100]);
+        chacha.fill(&mut a);
+        restored.fill(&mut b);
+        assert_eq!(a, b);
+
+        let mut chacha = ChaCha8Ietf::new([9; 8], 7, [1, 2, 3]);
+        let state = chacha.to_state_bytes();
+        assert_eq!(state[48..52], [7, 0, 0, 0]);
+        assert_eq!(state[60..64], [3, 0, 0, 0]);
+        let mut restored = ChaCha8Ietf::from_state_bytes(&state).unwrap();
+        assert_eq!(restored.get_block(), chacha.get_block());
+
+        let mut state = [0; STATE_LEN_U8];
+        state[64] = 64;
+        assert!(matches!(
+            ChaCha8Ietf::from_state_bytes(&state),
+            Err(Error::InvalidState)
+        ));
+    }
+
+    #[test]
+    fn ietf_try_fill_stops_before_wrapping() {
+        use super::{ChaCha20Ietf, Error};
+
+        let mut chacha = ChaCha20Ietf::new([1; 8], 0, [0; 3]);
+        assert_eq!(chacha.remaining_bytes(), (u32::MAX as u64) * 64);
+        chacha.seek(10);
+        assert_eq!(chacha.remaining_bytes(), (u32::MAX as u64) * 64 - 10);
+
+        chacha.set_counter(u32::MAX as u64 - 3);
+        let mut buf = [0; 200];
+        chacha.try_xor(&mut buf[..130]).unwrap();
+        assert_eq!(chacha.get_counter(), u32::MAX as u64);
+        assert_eq!(chacha.remaining_bytes(), 0);
+        assert!(matches!(
+            chacha.try_fill(&mut buf[..1]),
+            Err(Error::CounterExhausted)
+        ));
+        assert!(chacha.try_fill(&mut []).is_ok());
+        assert_eq!(chacha.get_counter(), u32::MAX as u64);
+
+        // Seeking into the final block.
+        chacha.seek(u32::MAX as u128 * 64 + 5);
+        assert_eq!(chacha.remaining_bytes(), 0);
+        assert!(matches!(
+            chacha.try_xor(&mut buf[..1]),
+            Err(Error::CounterExhausted)
+        ));
+    }
+
+    #[test]
+    fn djb_counter_overflow_policies() {
+        use super::{ChaCha8Djb, CounterOverflow, Error};
+
+        let mut chacha = ChaCha8Djb::new([1; 8], u64::MAX - 2, [0; 3]);
+        assert_eq!(chacha.counter_overflow(), CounterOverflow::Wrap);
+        assert_eq!(chacha.remaining_bytes(), 128);
+        assert!(matches!(
+            chacha.try_xor(&mut [0; 129]),
+            Err(Error::CounterExhausted)
+        ));
+        // Wrapping is still the default for the infallible methods.
+        chacha.fill(&mut [0; 256]);
+        assert_eq!(chacha.get_counter(), 1);
+
+        chacha.set_counter(u64::MAX - 4);
+        chacha.set_counter_overflow(CounterOverflow::Saturate);
+        let mut clone = chacha.clone_with_counter(u64::MAX - 4);
+        assert_eq!(clone.counter_overflow(), CounterOverflow::Saturate);
+        clone.fill_block(&mut [0; BUF_LEN_U8]);
+        assert_eq!(clone.get_counter(), u64::MAX);
+        assert_eq!(clone.remaining_bytes(), 0);
+        assert!(clone.try_fill(&mut [0; 1]).is_err());
+        // Seeking into the final block.
+        clone.seek(u64::MAX as u128 * 64 + 5);
+        assert_eq!(clone.remaining_bytes(), 0);
+        assert!(clone.try_xor(&mut [0; 1]).is_err());
+
+        #[cfg(feature = "std")]
+        {
+            let result = std::panic::catch_unwind(move || chacha.fill(&mut [0; BUF_LEN_U8 + 1]));
+            assert!(result.is_err());
+        }
+    }
+
+    #[test]
+    fn counter_advance_policies() {
+        use super::{ChaCha12Ietf, CounterAdvance};
+
+        let mut expected = [0; 1000];
+        ChaCha12Ietf::new([3; 8], 0, [0; 3]).fill(&mut expected);
+        let mut chacha = ChaCha12Ietf::new([3; 8], 0, [0; 3]);
+        assert_eq!(chacha.counter_advance(), CounterAdvance::Block);
+        chacha.fill(&mut [0; 100]);
+        assert_eq!(chacha.get_counter(), 2);
+
+        chacha.set_counter(0);
+        chacha.set_counter_advance(CounterAdvance::Batch);
+        chacha.fill(&mut [0; 100]);
+        assert_eq!(chacha.get_counter(), 4);
+        let mut buf = [0; 10];
+        chacha.xor_b2b(&[0; 10], &mut buf);
+        assert_eq!(buf, expected[256..266]);
+        assert_eq!(chacha.get_counter(), 8);
+        // A seek offset only rounds up the rest of its own block.
+        chacha.seek(10);
+        chacha.fill(&mut buf);
+        assert_eq!(buf, expected[10..20]);
+        assert_eq!(chacha.get_counter(), 1);
+        let mut clone = chacha.clone();
+        chacha.seek(10);
+        clone.seek(10);
+        chacha.fill(&mut [0; 300]);
+        clone.fill_many(&mut [&mut [0; 100], &mut [0; 200]]);
+        assert_eq!(chacha.get_counter(), 5);
+        assert_eq!(clone.get_counter(), 5);
+
+        chacha.set_counter(0);
+        chacha.set_counter_advance(CounterAdvance::Exact);
+        let mut actual = [0; 1000];
+        let (a, rest) = actual.split_at_mut(7);
+        let (b, rest) = rest.split_at_mut(300);
+        let (c, d) = rest.split_at_mut(57);
+        chacha.fill(a);
+        chacha.xor_b2b(&[0; 300], b);
+        chacha.fill_many(&mut [c, &mut d[..1]]);
+        chacha.xor(&mut d[1..]);
+        assert_eq!(actual, expected);
+        assert_eq!(chacha.position(), 1000);
+        assert_eq!(chacha.clone().counter_advance(), CounterAdvance::Exact);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn alloc_outputs() {
+        use super::{ChaCha12Djb, CounterAdvance};
+        use alloc::vec::Vec;
+
+        let mut expected = [0; 300];
+        ChaCha12Djb::new([2; 8], 0, [0; 3]).fill(&mut expected);
+        let mut chacha = ChaCha12Djb::new([2; 8], 0, [0; 3]);
+        assert_eq!(chacha.keystream_vec(300), expected);
+        assert_eq!(chacha.get_counter(), 5);
+        assert!(chacha.keystream_vec(0).is_empty());
+
+        let data = [0x55; 300];
+        expected.iter_mut().for_each(|v| *v ^= 0x55);
+        chacha.set_counter(0);
+        assert_eq!(chacha.encrypt_to_vec(&data), expected);
+        assert_eq!(chacha.get_counter(), 5);
+
+        // Long enough to be built in several pieces, starting mid-block.
+        let data: Vec<u8> = (0..10_000).map(|i| i as u8).collect();
+        for advance in [
+            CounterAdvance::Block,
+            CounterAdvance::Batch,
+            CounterAdvance::Exact,
+        ] {
+            for (start, len) in [(0, 10_000), (7, 10_000), (7, 4153), (30, 20)] {
+                let mut chacha = ChaCha12Djb::new([2; 8], 0, [0; 3]);
+                chacha.set_counter_advance(advance);
+                chacha.seek(start);
+                let mut reference = chacha.clone();
+                let mut expected = data[..len].to_vec();
+                reference.xor(&mut expected);
+                assert_eq!(chacha.encrypt_to_vec(&data[..len]), expected);
+                assert_eq!(chacha.position(), reference.position());
+                chacha.seek(start);
+                reference.seek(start);
+                reference.fill(&mut expected);
+                assert_eq!(chacha.keystream_vec(len), expected);
+                assert_eq!(chacha.position(), reference.position());
+            }
+        }
+    }
+
+    #[test]
+    fn runtime_round_counts() {
+        use super::{ChaChaAnyIetf, ChaChaIetf, Error};
+
+        fn check<const DOUBLE_ROUNDS: usize>() {
+            let mut expected = [0; 300];
+            let mut reference = ChaChaIetf::<DOUBLE_ROUNDS>::new([1; 8], 3, [4; 3]);
+            reference.seek(reference.position() + 7);
+            reference.xor(&mut expected);
+            let mut actual = [0; 300];
+            let mut chacha = ChaChaAnyIetf::new(DOUBLE_ROUNDS, [1; 8], 3, [4; 3]).unwrap();
+            assert_eq!(chacha.double_rounds(), DOUBLE_ROUNDS);
+            chacha.seek(chacha.position() + 7);
+            chacha.clone().xor(&mut actual);
+            assert_eq!(actual, expected);
+            chacha.fill(&mut actual);
+            assert_eq!(chacha.get_counter(), reference.get_counter());
+            assert_eq!(chacha.next_u64(), reference.next_u64());
+        }
+
+        check::<2>();
+        check::<3>();
+        check::<4>();
+        check::<6>();
+        check::<10>();
+        assert!(matches!(
+            ChaChaAnyIetf::new(5, [1; 8], 3, [4; 3]),
+            Err(Error::UnsupportedRounds)
+        ));
+    }
+
+    #[test]
+    fn runtime_variants() {
+        use super::{ChaCha8AnyVariant, ChaCha8Djb, ChaCha8Ietf, Variants};
+
+        let mut djb = ChaCha8AnyVariant::new(Variants::Djb, [1; 8], u32::MAX as u64, [2, 3, 4]);
+        let mut ietf = ChaCha8AnyVariant::new(Variants::Ietf, [1; 8], u32::MAX as u64, [2, 3, 4]);
+        assert_eq!(djb.variant(), Variants::Djb);
+        assert_eq!(ietf.variant(), Variants::Ietf);
+        assert_eq!(
+            djb.get_stream(),
+            ChaCha8Djb::new([1; 8], 0, [2, 3, 4]).get_stream()
+        );
+        assert_eq!(
+            ietf.get_stream(),
+            ChaCha8Ietf::new([1; 8], 0, [2, 3, 4]).get_stream()
+        );
+
+        let mut expected = [0; 200];
+        let mut actual = [0; 200];
+        ChaCha8Djb::new([1; 8], u32::MAX as u64, [2, 3, 4]).fill(&mut expected);
+        djb.clone().fill(&mut actual);
+        assert_eq!(actual, expected);
+        ChaCha8Ietf::new([1; 8], u32::MAX as u64, [2, 3, 4]).fill(&mut expected);
+        ietf.clone().fill(&mut actual);
+        assert_eq!(actual, expected);
+
+        // The counters only diverge once the Ietf one wraps around.
+        djb.fill(&mut actual);
+        ietf.fill(&mut actual);
+        assert_eq!(djb.get_counter(), (u32::MAX as u64) + 4);
+        assert_eq!(ietf.get_counter(), 3);
+    }
+
+    #[test]
+    fn backend_aliases_agree() {
+        use crate::{ChaCha12Djb, ChaCha12DjbSoft};
+
+        let mut soft = ChaCha12DjbSoft::new([7; 8], 3, [9; 3]);
+        let mut default = ChaCha12Djb::new([7; 8], 3, [9; 3]);
+        assert_eq!(soft.get_block(), default.get_block());
+        #[cfg(target_feature = "sse2")]
+        assert_eq!(
+            crate::ChaCha20IetfSse2::new([7; 8], 3, [9; 3]).get_block(),
+            crate::ChaCha20IetfSoft::new([7; 8], 3, [9; 3]).get_block()
+        );
+    }
+
+    #[test]
+    fn single_blocks() {
+        let mut chacha = ChaChaCore::<Matrix, R20, Ietf>::new([5; 8], 1, [6; 3]);
+        let mut reference = ChaChaCore::<Matrix, R20, Ietf>::new([5; 8], 1, [6; 3]);
+        let mut expected = [0; 3 * MATRIX_SIZE_U8];
+        reference.fill(&mut expected);
+        let mut actual = [0; 3 * MATRIX_SIZE_U8];
+        for (i, chunk) in actual.chunks_exact_mut(MATRIX_SIZE_U8).enumerate() {
+            if i % 2 == 0 {
+                chunk.copy_from_slice(&chacha.get_block64());
+            } else {
+                chacha.fill_block64(chunk.try_into().unwrap());
+            }
+        }
+        assert_eq!(actual, expected);
+        assert_eq!(chacha.get_counter(), 4);
+
+        // Carrying into the high word of the 64-bit counter, with other round counts.
+        let mut chacha = ChaChaCore::<Matrix, R8, Djb>::new([7; 8], u32::MAX as u64, [8; 3]);
+        let mut expected = [0; 2 * MATRIX_SIZE_U8];
+        chacha.clone().fill(&mut expected);
+        assert_eq!(chacha.get_block64(), expected[..MATRIX_SIZE_U8]);
+        assert_eq!(chacha.get_block64(), expected[MATRIX_SIZE_U8..]);
+        assert_eq!(chacha.get_counter(), u32::MAX as u64 + 2);
+    }
+
+    #[test]
+    fn fill_many_matches_fill() {
+        let mut chacha = ChaChaCore::<Matrix, R12, Ietf>::new([3; 8], 9, [4; 3]);
+        let mut reference = chacha.clone();
+        chacha.seek(chacha.position() + 5);
+        reference.seek(reference.position() + 5);
+        let (mut a, mut b, mut c, mut d) = ([0; 70], [0; 512], [0; 0], [0; 190]);
+        chacha.fill_many(&mut [&mut a, &mut b, &mut c, &mut d]);
+        let mut expected = [0; 772];
+        reference.fill(&mut expected);
+        assert_eq!(a, expected[..70]);
+        assert_eq!(b, expected[70..582]);
+        assert_eq!(d, expected[582..]);
+        assert_eq!(chacha.get_counter(), reference.get_counter());
+        chacha.fill_many(&mut []);
+        assert_eq!(chacha.get_counter(), reference.get_counter());
+    }
+
+    #[test]
+    fn skipping_matches_discarding() {
+        const SKIPPED: usize = 100 + 3 * MATRIX_SIZE_U8;
+        let mut chacha = ChaChaCore::<Matrix, R8, Djb>::new([8; 8], 2, [1; 3]);
+        let mut full = [0; SKIPPED + 300];
+        chacha.clone().fill(&mut full);
+        chacha.skip_bytes(100);
+        chacha.skip_blocks(3);
+        assert_eq!(chacha.position(), (2 * MATRIX_SIZE_U8 + SKIPPED) as u128);
+        let mut actual = [0; 300];
+        chacha.fill(&mut actual);
+        assert_eq!(actual, full[SKIPPED..]);
+
+        let mut ietf = ChaChaCore::<Matrix, R8, Ietf>::new([8; 8], u32::MAX as u64, [1; 3]);
+        ietf.skip_blocks(2);
+        assert_eq!(ietf.get_counter(), 1);
+    }
+
+    #[test]
+    fn peek_block_leaves_counter() {
+        let mut chacha = ChaChaCore::<Matrix, R20, Djb>::new([2; 8], 11, [3; 3]);
+        chacha.seek(chacha.position() + 7);
+        let peeked = chacha.peek_block();
+        assert_eq!(chacha.get_counter(), 11);
+        assert_eq!(chacha.position(), 11 * MATRIX_SIZE_U8 as u128 + 7);
+        assert_eq!(chacha.peek_block(), peeked);
+        assert_eq!(chacha.get_block(), peeked);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn keystream_reader() {
+        use super::{ChaCha8Ietf, KeystreamReader};
+        use std::io::{BufRead, Read};
+
+        let mut expected = [0; 1000];
+        let mut chacha = ChaCha8Ietf::new([4; 8], 3, [5; 3]);
+        chacha.fill(&mut expected);
+        chacha = ChaCha8Ietf::new([4; 8], 3, [5; 3]);
+        chacha.seek(chacha.position() + 10);
+
+        let mut reader = KeystreamReader::new(chacha);
+        let mut actual = [0; 1000];
+        reader.read_exact(&mut actual[10..17]).unwrap();
+        assert_eq!(reader.fill_buf().unwrap()[0], expected[17]);
+        reader.consume(3);
+        reader.read_exact(&mut actual[20..600]).unwrap();
+        assert_eq!(reader.position(), 3 * MATRIX_SIZE_U8 as u128 + 600);
+        assert_eq!(actual[10..17], expected[10..17]);
+        assert_eq!(actual[20..600], expected[20..600]);
+
+        let mut chacha = reader.into_inner();
+        chacha.fill(&mut actual[600..]);
+        assert_eq!(actual[600..], expected[600..]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn encrypting_writer_handles_short_writes() {
+        use super::{ChaCha12Djb, EncryptingWriter};
+        use std::io::{Result, Write};
+
+        // Accepts at most 5 bytes per call, to exercise the partial write handling.
+        struct Trickle(std::vec::Vec<u8>);
+        impl Write for Trickle {
+            fn write(&mut self, buf: &[u8]) -> Result<usize> {
+                let len = buf.len().min(5);
+                self.0.extend_from_slice(&buf[..len]);
+                Ok(len)
+            }
+            fn flush(&mut self) -> Result<()> {
+                Ok(())
+            }
+        }
+
+        let plaintext = [9; 333];
+        let mut expected = plaintext;
+        ChaCha12Djb::new([1; 8], 2, [3; 3]).xor(&mut expected);
+        let chacha = ChaCha12Djb::new([1; 8], 2, [3; 3]);
+        let mut writer = EncryptingWriter::new(Trickle(std::vec::Vec::new()), chacha);
+        for chunk in plaintext.chunks(70) {
+            writer.write_all(chunk).unwrap();
+        }
+        assert_eq!(writer.position(), 2 * MATRIX_SIZE_U8 as u128 + 333);
+        let (inner, _) = writer.into_parts();
+        assert_eq!(inner.0, expected);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn decrypting_reader_handles_short_reads() {
+        use super::{ChaCha12Djb, DecryptingReader};
+        use std::io::{Read, Result};
+
+        // Produces at most 7 bytes per call, to exercise the partial read handling.
+        struct Trickle<'a>(&'a [u8]);
+        impl Read for Trickle<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+                let len = buf.len().min(7).min(self.0.len());
+                buf[..len].copy_from_slice(&self.0[..len]);
+                self.0 = &self.0[len..];
+                Ok(len)
+            }
+        }
+
+        let plaintext = [9; 333];
+        let mut ciphertext = plaintext;
+        ChaCha12Djb::new([1; 8], 2, [3; 3]).xor(&mut ciphertext);
+        let chacha = ChaCha12Djb::new([1; 8], 2, [3; 3]);
+        let mut reader = DecryptingReader::new(Trickle(&ciphertext), chacha);
+        let mut actual = [0; 333];
+        reader.read_exact(&mut actual).unwrap();
+        assert_eq!(actual, plaintext);
+        assert_eq!(reader.position(), 2 * MATRIX_SIZE_U8 as u128 + 333);
+    }
+
+    #[cfg(feature = "futures-io")]
+    #[test]
+    fn async_adapters_futures_io() {
+        use super::{AsyncDecryptingReader, AsyncEncryptingWriter, ChaCha20Ietf};
+        use core::pin::Pin;
+        use core::task::{Context, Poll, Waker};
+        use futures_io::{AsyncRead, AsyncWrite};
+        use std::io::Result;
+
+        // Alternates between `Pending` and moving at most 5 bytes.
+        struct Stutter(bool, std::vec::Vec<u8>);
+        impl Stutter {
+            fn ready(&mut self) -> bool {
+                self.0 = !self.0;
+                self.0
+            }
+        }
+        impl AsyncWrite for Stutter {
+            fn poll_write(
+                mut self: Pin<&mut Self>,
+                _: &mut Context,
+                buf: &[u8],
+            ) -> Poll<Result<usize>> {
+                if !self.ready() {
+                    return Poll::Pending;
+                }
+                let len = buf.len().min(5);
+                self.1.extend_from_slice(&buf[..len]);
+                Poll::Ready(Ok(len))
+            }
+            fn poll_flush(self: Pin<&mut Self>, _: &mut Context) -> Poll<Result<()>> {
+                Poll::Ready(Ok(()))
+            }
+            fn poll_close(self: Pin<&mut Self>, _: &mut Context) -> Poll<Result<()>> {
+                Poll::Ready(Ok(()))
+            }
+        }
+        impl AsyncRead for Stutter {
+            fn poll_read(
+                mut self: Pin<&mut Self>,
+                _: &mut Context,
+                buf: &mut [u8],
+            ) -> Poll<Result<usize>> {
+                if !self.ready() {
+                    return Poll::Pending;
+                }
+                let len = buf.len().min(5).min(self.1.len());
+                buf[..len].copy_from_slice(&self.1[..len]);
+                self.1.drain(..len);
+                Poll::Ready(Ok(len))
+            }
+        }
+
+        let mut cx = Context::from_waker(Waker::noop());
+        let plaintext = [6; 200];
+        let mut expected = plaintext;
+        ChaCha20Ietf::new([2; 8], 1, [3; 3]).xor(&mut expected);
+
+        let chacha = ChaCha20Ietf::new([2; 8], 1, [3; 3]);
+        let mut writer = AsyncEncryptingWriter::new(Stutter(false, std::vec::Vec::new()), chacha);
+        let mut written = 0;
+        while written < plaintext.len() {
+            if let Poll::Ready(len) =
+                Pin::new(&mut writer).poll_write(&mut cx, &plaintext[written..])
+            {
+                written += len.unwrap();
+            }
+        }
+        let (inner, _) = writer.into_parts();
+        assert_eq!(inner.1, expected);
+
+        let chacha = ChaCha20Ietf::new([2; 8], 1, [3; 3]);
+        let mut reader = AsyncDecryptingReader::new(inner, chacha);
+        let mut actual = [0; 200];
+        let mut read = 0;
+        while read < actual.len() {
+            if let Poll::Ready(len) = Pin::new(&mut reader).poll_read(&mut cx, &mut actual[read..])
+            {
+                read += len.unwrap();
+            }
+        }
+        assert_eq!(actual, plaintext);
+        assert_eq!(reader.position(), MATRIX_SIZE_U8 as u128 + 200);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn async_adapters_tokio() {
+        use super::{AsyncDecryptingReader, AsyncEncryptingWriter, ChaCha8Djb};
+        use core::pin::Pin;
+        use core::task::{Context, Poll, Waker};
+        use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+        let mut cx = Context::from_waker(Waker::noop());
+        let plaintext = [6; 100];
+        let chacha = ChaCha8Djb::new([2; 8], 1, [3; 3]);
+        let mut writer = AsyncEncryptingWriter::new(std::vec::Vec::new(), chacha);
+        for chunk in plaintext.chunks(30) {
+            let poll = Pin::new(&mut writer).poll_write(&mut cx, chunk);
+            assert!(matches!(poll, Poll::Ready(Ok(30 | 10))));
+        }
+        let (ciphertext, _) = writer.into_parts();
+
+        let chacha = ChaCha8Djb::new([2; 8], 1, [3; 3]);
+        let mut reader = AsyncDecryptingReader::new(&ciphertext[..], chacha);
+        let mut actual = [0; 100];
+        let mut buf = ReadBuf::new(&mut actual);
+        while buf.remaining() != 0 {
+            let mut limited = buf.take(33);
+            let poll = Pin::new(&mut reader).poll_read(&mut cx, &mut limited);
+            assert!(matches!(poll, Poll::Ready(Ok(()))));
+            let len = limited.filled().len();
+            unsafe { buf.assume_init(len) };
+            buf.advance(len);
+        }
+        assert_eq!(actual, plaintext);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn encrypt_copy_handles_short_reads() {
+        use super::ChaCha8Djb;
+        use std::io::{Read, Result};
+
+        struct Trickle<'a>(&'a [u8]);
+        impl Read for Trickle<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+                let len = buf.len().min(13).min(self.0.len());
+                buf[..len].copy_from_slice(&self.0[..len]);
+                self.0 = &self.0[len..];
+                Ok(len)
+            }
+        }
+
+        let plaintext = [3; 500];
+        let mut expected = plaintext;
+        ChaCha8Djb::new([5; 8], 0, [6; 3]).xor(&mut expected);
+        let mut chacha = ChaCha8Djb::new([5; 8], 0, [6; 3]);
+        let mut ciphertext = std::vec::Vec::new();
+        let copied = chacha.encrypt_copy(Trickle(&plaintext), &mut ciphertext);
+        assert_eq!(copied.unwrap(), 500);
+        assert_eq!(ciphertext, expected);
+        assert_eq!(chacha.position(), 500);
+    }
+
+    #[test]
+    fn rekey_uses_own_output() {
+        let mut chacha = ChaChaCore::<Matrix, R12, Ietf>::new([4; 8], 5, [6; 3]);
+        chacha.next_u32();
+        let block = chacha.clone().get_block64();
+        chacha.rekey();
+        let key =
+            core::array::from_fn(|i| u32::from_le_bytes(block[i * 4..][..4].try_into().unwrap()));
+        let mut expected = ChaChaCore::<Matrix, R12, Ietf>::new(key, 0, [6; 3]);
+        assert_eq!(chacha.get_counter(), 0);
+        assert_eq!(chacha.next_u32(), expected.next_u32());
+    }
+
+    #[test]
+    fn nonce_sequence_never_repeats() {
+        use super::{Error, NonceSequence};
+
+        let mut nonces = NonceSequence::new([9; 4]);
+        let first = nonces.next_nonce().unwrap();
+        let second = nonces.next_nonce().unwrap();
+        assert!(first < second);
+        assert_eq!(second[4..], 1u64.to_be_bytes());
+
+        let mut nonces = NonceSequence::resume([9; 4], u64::MAX - 1);
+        assert!(nonces.next_nonce().is_ok());
+        assert_eq!(nonces.next_nonce().unwrap()[4..], [0xff; 8]);
+        assert_eq!(nonces.counter(), None);
+        assert_eq!(nonces.next_nonce(), Err(Error::CounterExhausted));
+        let mut buf = [7; 5];
+        let aead = super::ChaCha20Poly1305::new(&[1; 32]);
+        assert_eq!(
+            nonces.seal(&aead, &[], &mut buf),
+            Err(Error::CounterExhausted)
+        );
+        assert_eq!(buf, [7; 5]);
+    }
+
+    #[cfg(feature = "encoding")]
+    #[test]
+    fn key_and_nonce_encodings() {
+        use super::{Error, Key, Nonce64, Nonce96};
+
+        let nonce = Nonce64::new(*b"foobar!!");
+        assert_eq!(nonce.encode_base64().as_str(), "Zm9vYmFyISE=");
+        assert_eq!(nonce.encode_hex().as_str(), "666f6f6261722121");
+        assert!(Nonce64::from_base64("Zm9vYmFyISE=").unwrap() == nonce);
+        assert!(Nonce64::from_hex("666F6F6261722121").unwrap() == nonce);
+
+        let key = Key::from_base64("AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8=").unwrap();
+        assert_eq!(*key.as_bytes(), core::array::from_fn(|i| i as u8));
+        let hex: [u8; 64] = core::array::from_fn(|i| b"0123456789abcdef"[(i * 7) % 16]);
+        let key = Key::from_hex(core::str::from_utf8(&hex).unwrap()).unwrap();
+        assert_eq!(key.as_bytes()[0], 0x07);
+
+        // Every possible byte value has to survive both round trips.
+        for start in (0..=255u8).step_by(12) {
+            let nonce = Nonce96::new(core::array::from_fn(|i| start.wrapping_add(i as u8)));
+            let hex = nonce.encode_hex();
+            let base64 = nonce.encode_base64();
+            assert!(Nonce96::from_hex(hex.as_str()).unwrap() == nonce);
+            assert!(Nonce96::from_base64(base64.as_str()).unwrap() == nonce);
+        }
+
+        assert_eq!(
+            Nonce64::from_hex("666f6f626172212").unwrap_err(),
+            Error::InvalidLength
+        );
+        assert_eq!(
+            Nonce64::from_hex("666f6f62617221g1").unwrap_err(),
+            Error::InvalidEncoding
+        );
+        assert_eq!(
+            Nonce64::from_base64("Zm9vYmFyISE").unwrap_err(),
+            Error::InvalidLength
+        );
+        assert_eq!(
+            Nonce64::from_base64("Zm9vYmFyISF=").unwrap_err(),
+            Error::InvalidEncoding
+        );
+        assert_eq!(
+            Nonce64::from_base64("Zm9vYmFyISEA").unwrap_err(),
+            Error::InvalidEncoding
+        );
+        assert_eq!(
+            Nonce64::from_base64("Zm9vYmF-ISE=").unwrap_err(),
+            Error::InvalidEncoding
+        );
+    }
+
+    #[test]
+    fn oneshot_xor() {
+        use super::{ChaCha12Ietf, chacha12_ietf_xor, chacha20_djb_xor};
+
+        // Generated with libsodium's crypto_stream_chacha20_xor_ic,
+        // with the counter crossing into its upper 32 bits.
+        let key = core::array::from_fn(|i| i as u8);
+        let nonce = core::array::from_fn(|i| i as u8 + 0x40);
+        let mut buf = [0; 200];
+        chacha20_djb_xor(&key, &nonce, u32::MAX as u64, &mut buf);
+        assert_eq!(
+            buf[..16],
+            [
+                0xb1, 0x36, 0x4d, 0xb3, 0x5e, 0xce, 0x03, 0x26, 0x9d, 0x28, 0x91, 0x1f, 0x2f, 0x72,
+                0xd3, 0xe9,
+            ]
+        );
+        assert_eq!(
+            buf[184..],
+            [
+                0x5b, 0x1d, 0x3b, 0x34, 0xca, 0x58, 0xb0, 0xe6, 0xeb, 0x68, 0xb5, 0xa2, 0xc1, 0x9a,
+                0x05, 0x99,
+            ]
+        );
+
+        let mut buf = [0; 200];
+        chacha12_ietf_xor(&[7; 32], &[9; 12], 3, &mut buf);
+        let mut expected = [0; 200];
+        ChaCha12Ietf::new([0x07070707; 8], 3, [0x09090909; 3]).fill(&mut expected);
+        assert_eq!(buf, expected);
+    }
+
+    #[test]
+    fn sodium_compat_matches_libsodium() {
+        use super::sodium_compat::*;
+
+        // Generated with libsodium, using the `_xor_ic` functions.
+        let k = core::array::from_fn(|i| i as u8);
+        let m: [u8; 150] = core::array::from_fn(|i| (i * 5) as u8);
+        let mut c = [0; 150];
+        let n = core::array::from_fn(|i| i as u8 + 0x40);
+        crypto_stream_chacha20_ietf_xor_ic(&mut c, &m, &n, 7, &k);
+        assert_eq!(
+            c[..16],
+            [
+                0x92, 0xa4, 0xa8, 0x29, 0xea, 0x5c, 0xd3, 0xdd, 0xbf, 0x34, 0xf9, 0xc2, 0x9c, 0x2e,
+                0x1b, 0xd5,
+            ]
+        );
+        assert_eq!(
+            c[134..],
+            [
+                0x28, 0x6e, 0x94, 0x0a, 0xb8, 0xd9, 0x6c, 0xaf, 0x25, 0x0f, 0x13, 0x75, 0xa6, 0x26,
+                0x2c, 0xc0,
+            ]
+        );
+        let n = core::array::from_fn(|i| i as u8 + 0x40);
+        crypto_stream_xchacha20_xor_ic(&mut c, &m, &n, 5, &k);
+        assert_eq!(
+            c[..16],
+            [
+                0xa4, 0x6a, 0x35, 0x76, 0x1e, 0x1b, 0xc1, 0x38, 0x95, 0x18, 0xf2, 0xb5, 0xf6, 0xf5,
+                0xef, 0x74,
+            ]
+        );
+        assert_eq!(
+            c[134..],
+            [
+                0x17, 0xf2, 0x43, 0xe5, 0x51, 0xaa, 0xb2, 0xbd, 0x77, 0x78, 0x1f, 0x4f, 0x33, 0x2b,
+                0xd3, 0x57,
+            ]
+        );
+
+        // The keystream functions are equivalent to xoring zeros.
+        let mut keystream = [0; 150];
+        crypto_stream_xchacha20(&mut keystream, &n, &k);
+        crypto_stream_xchacha20_xor(&mut c, &[0; 150], &n, &k);
+        assert_eq!(keystream, c);
+    }
+
+    #[test]
+    fn salsa20_matches_libsodium() {
+        use super::{Salsa20, XSalsa20};
+
+        // Generated with libsodium's crypto_stream_salsa20_xor_ic (with the counter
+        // crossing into its upper 32 bits) and crypto_stream_xsalsa20.
+        let key = core::array::from_fn(|i| i as u32 * 0x04040404 + 0x03020100);
+        let nonce = core::array::from_fn(|i| i as u32 * 0x04040404 + 0x43424140);
+        let mut salsa = Salsa20::new(key, u32::MAX as u64 - 1, nonce);
+        let mut buf = [0; 300];
+        salsa.fill(&mut buf);
+        assert_eq!(
+            buf[..16],
+            [
+                0xdd, 0x35, 0xc4, 0xa2, 0x8e, 0x93, 0x2a, 0x09, 0x41, 0x72, 0x2f, 0x89, 0x4f, 0x40,
+                0xbe, 0x37,
+            ]
+        );
+        assert_eq!(
+            buf[284..],
+            [
+                0x8e, 0x9b, 0x60, 0x0c, 0xd5, 0x91, 0x4f, 0x23, 0x54, 0x5d, 0xe8, 0x66, 0xe8, 0x56,
+                0xab, 0x0a,
+            ]
+        );
+
+        let nonce = core::array::from_fn(|i| i as u32 * 0x04040404 + 0x43424140);
+        let mut xsalsa = XSalsa20::new_extended(key, 0, nonce);
+        xsalsa.fill(&mut buf);
+        assert_eq!(
+            buf[..16],
+            [
+                0xf9, 0x7f, 0x0c, 0x22, 0x9f, 0xd9, 0x53, 0xef, 0x00, 0x80, 0xe8, 0x33, 0xbd, 0x9c,
+                0xf9, 0x0d,
+            ]
+        );
+        assert_eq!(
+            buf[284..],
+            [
+                0x50, 0x64, 0x6c, 0xc7, 0xcc, 0x1a, 0x08, 0x77, 0x8d, 0x93, 0x0a, 0xbb, 0x0c, 0xe2,
+                0x77, 0x8a,
+            ]
+        );
+    }
+
+    #[test]
+    fn salsa20_single_blocks() {
+        use super::{Salsa20, XSalsa20};
+
+        // Crossing into the upper 32 bits of the counter.
+        let salsa = Salsa20::new([3; 8], u32::MAX as u64 - 1, [5; 3]);
+        let mut expected = [0; 4 * MATRIX_SIZE_U8];
+        salsa.clone().fill(&mut expected);
+        let mut single = salsa.clone();
+        for block in expected.chunks_exact(MATRIX_SIZE_U8) {
+            assert_eq!(single.get_block64(), block);
+        }
+        for start in [10, 64, 100, 200] {
+            let mut seeked = salsa.clone();
+            seeked.seek(seeked.position() + start as u128);
+            let mut buf = [0; 4 * MATRIX_SIZE_U8];
+            seeked.fill(&mut buf[start..]);
+            assert_eq!(buf[start..], expected[start..]);
+        }
+
+        let mut xsalsa = XSalsa20::new_extended([3; 8], 0, [4; 6]);
+        let mut expected = [0; MATRIX_SIZE_U8 + 20];
+        xsalsa.clone().fill(&mut expected);
+        xsalsa.seek(20);
+        let mut buf = [0; MATRIX_SIZE_U8];
+        xsalsa.fill(&mut buf);
+        assert_eq!(buf, expected[20..]);
+    }
+
+    #[test]
+    fn chacha_128_bit_key() {
+        use super::{ChaCha8Djb, ChaCha20Djb};
+
+        // draft-strombergson-chacha-test-vectors TC1 (8 rounds).
+        let mut chacha = ChaCha8Djb::new_128([0; 4], 0, [0; 3]);
+        let mut buf = [0; 32];
+        chacha.fill(&mut buf);
+        assert_eq!(
+            buf,
+            [
+                0xe2, 0x8a, 0x5f, 0xa4, 0xa6, 0x7f, 0x8c, 0x5d, 0xef, 0xed, 0x3e, 0x6f, 0xb7, 0x30,
+                0x34, 0x86, 0xaa, 0x84, 0x27, 0xd3, 0x14, 0x19, 0xa7, 0x29, 0x57, 0x2d, 0x77, 0x79,
+                0x53, 0x49, 0x11, 0x20,
+            ]
+        );
+
+        // Computed with a scalar reference implementation, covering every backend lane.
+        let key = core::array::from_fn(|i| i as u32 * 0x04040404 + 0x03020100);
+        let nonce = [0x43424140, 0x47464544, 0];
+        let mut chacha = ChaCha20Djb::new_128(key, 0, nonce);
+        let mut buf = [0; 300];
+        chacha.fill(&mut buf);
+        assert_eq!(
+            buf[..16],
+            [
+                0x4f, 0x4b, 0x90, 0xf8, 0xbb, 0x09, 0x71, 0x99, 0xf4, 0x1e, 0x6b, 0xd1, 0xa6, 0x05,
+                0xb0, 0xcd,
+            ]
+        );
+        assert_eq!(
+            buf[284..],
+            [
+                0xc6, 0xae, 0xdf, 0x83, 0x20, 0x9b, 0x1b, 0x41, 0xf1, 0x1b, 0xec, 0x1d, 0x0e, 0x7a,
+                0x30, 0x94,
+            ]
+        );
+    }
+
+    #[test]
+    fn chunk_cipher_matches_libsodium() {
+        use super::{ChunkCipher, Error};
+
+        // Generated with libsodium, deriving the subkeys and nonce as described in the module docs.
+        let cipher = ChunkCipher::new(&core::array::from_fn(|i| i as u8));
+        let id = core::array::from_fn(|i| (i * 11 + 5) as u8);
+        assert_eq!(
+            cipher.chunk_nonce(&id),
+            [
+                0x49, 0x72, 0x3c, 0xe8, 0x80, 0xb5, 0x9a, 0x4d, 0x77, 0xdd, 0x00, 0x42, 0x2b, 0x18,
+                0xfa, 0x33, 0xdd, 0x5c, 0xc0, 0x7d, 0x88, 0x35, 0xff, 0xc3,
+            ]
+        );
+        let plain: [u8; 50] = core::array::from_fn(|i| (i * 3) as u8);
+        let mut buf = plain;
+        let tag = cipher.seal_chunk(&id, &mut buf).unwrap();
+        assert_eq!(
+            buf[..16],
+            [
+                0x0c, 0x39, 0x2c, 0x82, 0x0c, 0xbe, 0xe6, 0x29, 0xc2, 0xef, 0xa1, 0xd6, 0x7f, 0x3e,
+                0x34, 0x3b,
+            ]
+        );
+        assert_eq!(
+            buf[34..],
+            [
+                0x78, 0xa9, 0x96, 0xab, 0x0c, 0xa0, 0x60, 0x3f, 0xf7, 0xe6, 0x81, 0x7d, 0x15, 0xac,
+                0x56, 0xa0,
+            ]
+        );
+        assert_eq!(
+            tag,
+            [
+                0xb7, 0x4f, 0x0a, 0x3e, 0x9d, 0xbd, 0x75, 0x63, 0xfd, 0xe4, 0x0f, 0x5e, 0x16, 0xed,
+                0xd9, 0x79,
+            ]
+        );
+        let mut other_id = id;
+        other_id[31] ^= 1;
+        assert_eq!(
+            cipher.open_chunk(&other_id, &mut buf, &tag),
+            Err(Error::TagMismatch)
+        );
+        cipher.open_chunk(&id, &mut buf, &tag).unwrap();
+        assert_eq!(buf, plain);
+    }
+
+    #[test]
+    fn drbg_lifecycle() {
+        use super::{ChaChaDrbg, Error};
+
+        // Generated with a python reference of the algorithm described in the module docs.
+        let entropy = core::array::from_fn(|i| i as u8);
+        let personalization = core::array::from_fn(|i| i as u8 + 100);
+        let mut drbg = ChaChaDrbg::instantiate(&entropy, Some(&personalization), 2);
+        let mut buf = [0; 100];
+        drbg.generate(&mut buf, None).unwrap();
+        assert_eq!(
+            buf[..16],
+            [
+                0xb0, 0x85, 0x8d, 0x79, 0x2b, 0x35, 0x3e, 0x65, 0x49, 0x4e, 0x46, 0xd8, 0x26, 0x50,
+                0xf8, 0xdc,
+            ]
+        );
+        assert_eq!(
+            buf[84..],
+            [
+                0xcc, 0xa7, 0x58, 0x38, 0x7f, 0x98, 0x56, 0xa1, 0x14, 0x59, 0xb7, 0x88, 0x4b, 0x4b,
+                0x30, 0x55,
+            ]
+        );
+        drbg.generate(&mut buf, Some(&[0xaa; 32])).unwrap();
+        assert_eq!(
+            buf[..16],
+            [
+                0x8d, 0xce, 0x2a, 0x8f, 0x35, 0x4d, 0xb4, 0x59, 0x3e, 0x35, 0xac, 0x01, 0x54, 0xfd,
+                0x49, 0xee,
+            ]
+        );
+        assert_eq!(
+            buf[84..],
+            [
+                0xae, 0xda, 0x29, 0xe9, 0x2c, 0x71, 0x5e, 0x2c, 0xad, 0xa7, 0x3b, 0x7b, 0x8f, 0x9b,
+                0xb6, 0x77,
+            ]
+        );
+        assert!(drbg.needs_reseed());
+        let before = buf;
+        assert_eq!(drbg.generate(&mut buf, None), Err(Error::ReseedRequired));
+        assert_eq!(buf, before);
+        drbg.reseed(&[7; 32], Some(&[0xaa; 32]));
+        let mut buf = [0; 64];
+        drbg.generate(&mut buf, None).unwrap();
+        assert_eq!(
+            buf[..16],
+            [
+                0x65, 0x9d, 0x06, 0x09, 0x7d, 0xcd, 0xfd, 0xc5, 0x1d, 0x0d, 0x4a, 0x41, 0x7b, 0x38,
+                0xc3, 0x3d,
+            ]
+        );
+        assert_eq!(
+            buf[48..],
+            [
+                0xf7, 0xcb, 0xe4, 0xfd, 0x0b, 0x4c, 0x48, 0x3d, 0xa7, 0x66, 0x45, 0x61, 0xa7, 0xa9,
+                0xdd, 0x7c,
+            ]
+        );
+
+        #[cfg(feature = "getrandom")]
+        {
+            let before = buf;
+            drbg.generate_with_prediction_resistance(&mut buf, None)
+                .unwrap();
+            assert_ne!(buf, before);
+        }
+    }
+
+    #[test]
+    fn kdf_matches_xchacha20() {
+        use super::derive_subkey;
+
+        // Generated with libsodium's crypto_stream_xchacha20, using the nonce described in the module docs.
+        let master_key = core::array::from_fn(|i| i as u8);
+        let subkey: [u8; 64] = derive_subkey(&master_key, 0x0123456789abcdef, b"sessions");
+        assert_eq!(
+            subkey[..16],
+            [
+                0x92, 0x27, 0x2d, 0x5a, 0x3e, 0x5c, 0x8a, 0x70, 0xa5, 0x97, 0x6c, 0x52, 0x6b, 0x4a,
+                0x3c, 0x9b,
+            ]
+        );
+        assert_eq!(
+            subkey[48..],
+            [
+                0x99, 0xed, 0x8f, 0xa3, 0x40, 0x1a, 0x8e, 0xdd, 0xbc, 0x42, 0x8e, 0x30, 0x7a, 0x69,
+                0xf7, 0xa6,
+            ]
+        );
+        // Shorter subkeys are prefixes of longer ones.
+        let short: [u8; 16] = derive_subkey(&master_key, 0x0123456789abcdef, b"sessions");
+        assert_eq!(short, subkey[..16]);
+    }
+
+    #[test]
+    fn openssh_matches_reference() {
+        use super::{Error, OpenSshChaChaPoly};
+
+        let key: [u8; 64] = core::array::from_fn(|i| (i * 3 + 1) as u8);
+        let seq = 0x01020304;
+        let mut packet = [0; 104];
+        packet[..4].copy_from_slice(&100u32.to_be_bytes());
+        packet[4..]
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, x)| *x = (i * 7) as u8);
+        let plain = packet;
+        let cipher = OpenSshChaChaPoly::new(&key);
+        let tag = cipher.seal(seq, &mut packet).unwrap();
+        assert_eq!(
+            packet[..16],
+            [
+                0x96, 0x8a, 0x38, 0x27, 0x9b, 0x10, 0xac, 0xa5, 0xd1, 0x50, 0xfa, 0x4e, 0x07, 0x1d,
+                0xc3, 0x0d,
+            ]
+        );
+        assert_eq!(
+            packet[88..],
+            [
+                0x56, 0xf4, 0xfd, 0x5c, 0x96, 0x5e, 0x72, 0x62, 0x26, 0x56, 0x1f, 0x7b, 0x2b, 0xe6,
+                0x46, 0x5b,
+            ]
+        );
+        assert_eq!(
+            tag,
+            [
+                0xb0, 0xbd, 0x93, 0x9b, 0x56, 0x84, 0xfe, 0xae, 0xa2, 0x58, 0xad, 0xf2, 0xeb, 0x9d,
+                0x23, 0x2f,
+            ]
+        );
+        assert_eq!(
+            cipher.decrypt_length(seq, packet[..4].try_into().unwrap()),
+            100u32.to_be_bytes()
+        );
+        assert_eq!(
+            cipher.open(seq + 1, &mut packet, &tag),
+            Err(Error::TagMismatch)
+        );
+        cipher.open(seq, &mut packet, &tag).unwrap();
+        assert_eq!(packet, plain);
+        assert_eq!(cipher.seal(seq, &mut [0; 3]), Err(Error::InvalidLength));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn random_nonce_aead() {
+        use super::{Error, decrypt_with_random_nonce};
+
+        // Generated with libsodium's crypto_aead_xchacha20poly1305_ietf_encrypt.
+        let key = core::array::from_fn(|i| i as u8);
+        let mut sealed = [0; 54];
+        sealed[..24]
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, x)| *x = i as u8 + 0x40);
+        sealed[24..].copy_from_slice(&[
+            0xb5, 0x4d, 0x71, 0x11, 0xb3, 0x8b, 0x59, 0x77, 0xfb, 0xd4, 0xe3, 0xdf, 0xd8, 0xf2,
+            0xf6, 0x4a, 0xf7, 0x06, 0xc1, 0xae, 0x77, 0x17, 0x5c, 0x76, 0x95, 0xb7, 0x9c, 0x42,
+            0xe9, 0x09,
+        ]);
+        assert_eq!(
+            decrypt_with_random_nonce(&key, b"aad", &sealed).unwrap(),
+            b"attack at dawn"
+        );
+        assert_eq!(
+            decrypt_with_random_nonce(&key, b"bad", &sealed),
+            Err(Error::TagMismatch)
+        );
+        assert_eq!(
+            decrypt_with_random_nonce(&key, b"aad", &sealed[..39]),
+            Err(Error::InvalidLength)
+        );
+
+        #[cfg(feature = "getrandom")]
+        {
+            use super::{RANDOM_NONCE_OVERHEAD, encrypt_with_random_nonce};
+
+            let a = encrypt_with_random_nonce(&key, b"aad", b"attack at dawn");
+            let b = encrypt_with_random_nonce(&key, b"aad", b"attack at dawn");
+            assert_eq!(a.len(), 14 + RANDOM_NONCE_OVERHEAD);
+            assert_ne!(a, b);
+            assert_eq!(
+                decrypt_with_random_nonce(&key, b"aad", &a).unwrap(),
+                b"attack at dawn"
+            );
+            assert_eq!(
+                decrypt_with_random_nonce(&key, &[], &b),
+                Err(Error::TagMismatch)
+            );
+        }
+    }
+
+    #[test]
+    fn sector_cipher() {
+        use super::{Error, SectorCipher12, chacha12_djb_xor};
+
+        let key = [11; 32];
+        let cipher = SectorCipher12::new(&key, 4096);
+        let mut sectors = [0x5a; 3 * 4096];
+        cipher
+            .encrypt_sectors(u32::MAX as u64, &mut sectors)
+            .unwrap();
+        for (i, sector) in sectors.chunks_exact(4096).enumerate() {
+            let mut expected = [0x5a; 4096];
+            let index = u32::MAX as u64 + i as u64;
+            chacha12_djb_xor(&key, &index.to_le_bytes(), 0, &mut expected);
+            assert_eq!(sector, expected);
+        }
+        cipher
+            .decrypt_sector(u32::MAX as u64 + 1, &mut sectors[4096..8192])
+            .unwrap();
+        assert!(sectors[4096..8192].iter().all(|&x| x == 0x5a));
+        assert_eq!(
+            cipher.encrypt_sector(0, &mut sectors[..4095]),
+            Err(Error::InvalidLength)
+        );
+        assert_eq!(
+            cipher.encrypt_sectors(0, &mut sectors[..5000]),
+            Err(Error::InvalidLength)
+        );
+    }
+
+    #[test]
+    fn quic_header_protection() {
+        use super::quic_hp_mask;
+
+        // RFC 9001 appendix A.5
+        let key = [
+            0x25, 0xa2, 0x82, 0xb9, 0xe8, 0x2f, 0x06, 0xf2, 0x1f, 0x48, 0x89, 0x17, 0xa4, 0xfc,
+            0x8f, 0x1b, 0x73, 0x57, 0x36, 0x85, 0x60, 0x85, 0x97, 0xd0, 0xef, 0xcb, 0x07, 0x6b,
+            0x0a, 0xb7, 0xa7, 0xa4,
+        ];
+        let sample = [
+            0x5e, 0x5c, 0xd5, 0x5c, 0x41, 0xf6, 0x90, 0x80, 0x57, 0x5d, 0x79, 0x99, 0xc2, 0x5a,
+            0x5b, 0xfb,
+        ];
+        assert_eq!(quic_hp_mask(&key, &sample), [0xae, 0xfe, 0xfe, 0x7d, 0x03]);
+    }
+
+    #[test]
+    fn tls_record_nonces() {
+        use super::{ChaCha20Poly1305, Error, TlsRecordCipher, tls13_nonce};
+
+        // RFC 8446 section 5.3, using the IV from the RFC 8448 simple 1-RTT handshake.
+        let iv = [
+            0x5d, 0x31, 0x3e, 0xb2, 0x67, 0x12, 0x76, 0xee, 0x13, 0x00, 0x0b, 0x30,
+        ];
+        assert_eq!(tls13_nonce(&iv, 0), iv);
+        assert_eq!(
+            tls13_nonce(&iv, 0x0102030405060708),
+            [
+                0x5d, 0x31, 0x3e, 0xb2, 0x66, 0x10, 0x75, 0xea, 0x16, 0x06, 0x0c, 0x38,
+            ]
+        );
+
+        let key = [5; 32];
+        let mut sender = TlsRecordCipher::new(&key, &iv);
+        let mut receiver = TlsRecordCipher::new(&key, &iv);
+        for seq in 0..3 {
+            let mut buf = [seq as u8; 33];
+            let tag = sender.seal(b"header", &mut buf).unwrap();
+            let mut expected = [seq as u8; 33];
+            let expected_tag = ChaCha20Poly1305::new(&key)
+                .encrypt_detached(&tls13_nonce(&iv, seq), b"header", &mut expected)
+                .unwrap();
+            assert_eq!((buf, tag), (expected, expected_tag));
+            assert_eq!(
+                receiver.open(b"wrong", &mut buf, &tag),
+                Err(Error::TagMismatch)
+            );
+            assert_eq!(receiver.sequence_number(), Some(seq));
+            receiver.open(b"header", &mut buf, &tag).unwrap();
+            assert_eq!(buf, [seq as u8; 33]);
+        }
+        assert_eq!(sender.sequence_number(), Some(3));
+    }
+
+    #[test]
+    fn wireguard_transport() {
+        use super::{
+            ChaCha20Poly1305, Error, REJECT_AFTER_MESSAGES, WireGuardReceiver, WireGuardSender,
+            wireguard_nonce,
+        };
+
+        assert_eq!(
+            wireguard_nonce(0x0102030405060708),
+            [0, 0, 0, 0, 8, 7, 6, 5, 4, 3, 2, 1]
+        );
+
+        let key = [7; 32];
+        let mut sender = WireGuardSender::new(&key);
+        let receiver = WireGuardReceiver::new(&key);
+        for expected in 0..3 {
+            let mut buf = [expected as u8; 40];
+            let (counter, tag) = sender.seal(&mut buf).unwrap();
+            assert_eq!(counter, expected);
+            // Exactly the same as the underlying AEAD with the encoded counter.
+            let mut plain = [expected as u8; 40];
+            let aead_tag = ChaCha20Poly1305::new(&key)
+                .encrypt_detached(&wireguard_nonce(counter), &[], &mut plain)
+                .unwrap();
+            assert_eq!((buf, tag), (plain, aead_tag));
+            assert_eq!(
+                receiver.open(counter + 1, &mut buf, &tag),
+                Err(Error::TagMismatch)
+            );
+            assert_eq!(receiver.open(counter, &mut buf, &tag), Ok(()));
+            assert_eq!(buf, [expected as u8; 40]);
+        }
+        assert!(!sender.needs_rekey());
+        assert_eq!(
+            receiver.open(REJECT_AFTER_MESSAGES, &mut [], &[0; 16]),
+            Err(Error::CounterExhausted)
+        );
+    }
+
+    #[test]
+    fn shuffle_is_permutation() {
+        let mut chacha = ChaChaCore::<Matrix, R12, Ietf>::from(0u8);
+        let mut values: [usize; 100] = core::array::from_fn(|i| i);
+        chacha.shuffle(&mut values);
+        assert!(values.iter().enumerate().any(|(i, &v)| i != v));
+        values.sort_unstable();
+        assert!(values.iter().enumerate().all(|(i, &v)| i == v));
+
+        let mut buf = [0; 1000];
+        chacha.fill_range(&mut buf, 69..420);
+        assert!(buf.iter().all(|v| (69..420).contains(v)));
+        assert_eq!(chacha.next_range(7..8), 7);
+    }
+
+    #[test]
+    fn alphanumeric_charset() {
+        let mut chacha = ChaChaCore::<Matrix, R20, Djb>::from(0u8);
+        let mut buf = [0; 1000];
+        chacha.fill_ascii_alphanumeric(&mut buf);
+        assert!(buf.iter().all(u8::is_ascii_alphanumeric));
+    }
+
+    #[cfg(feature = "getrandom")]
+    #[test]
+    fn arc4random_matches_chacha20() {
+        use super::{Arc4Random, ChaCha20Djb};
+
+        let seed: [u8; 40] = core::array::from_fn(|i| i as u8);
+        let keystream = |seed: &[u8]| {
+            let mut result = [0; 1024];
+            let nonce = seed[32..].try_into().unwrap();
+            ChaCha20Djb::from_bytes(seed[..32].try_into().unwrap(), 0, nonce).fill(&mut result);
+            result
+        };
+        // Each 1 KiB buffer rekeys with its first 40 bytes, and hands out the rest.
+        let first = keystream(&seed);
+        let mut second = keystream(&first[..40]);
+        // A `next_u32` with only 2 bytes left rekeys without using them, so they're
+        // encrypted along with the rest of the buffer (ending up at the very end of it).
+        second[1022] ^= first[1022];
+        second[1023] ^= first[1023];
+
+        let mut rng = Arc4Random::from_seed(&seed, usize::MAX);
+        let mut buf = [0; 982];
+        rng.buf(&mut buf);
+        assert_eq!(buf, first[40..1022]);
+        assert_eq!(
+            rng.next_u32(),
+            u32::from_ne_bytes(second[40..44].try_into().unwrap())
+        );
+        let mut buf = [0; 980];
+        rng.buf(&mut buf);
+        assert_eq!(buf, second[44..]);
+    }
+
+    #[cfg(feature = "getrandom")]
+    #[test]
+    fn arc4random_stir_accounting() {
+        use super::{Arc4Random, ChaCha20Djb};
+
+        let seed = [7; 40];
+        let mut expected = [0; 1024];
+        ChaCha20Djb::from_bytes([7; 32], 0, [7; 8]).fill(&mut expected);
+        // Requests smaller than what's left come from the seed.
+        let mut rng = Arc4Random::from_seed(&seed, 101);
+        let mut buf = [0; 100];
+        rng.buf(&mut buf);
+        assert_eq!(buf, expected[40..140]);
+        assert_eq!(rng.until_stir(), 1);
+        // Anything else stirs first, and still counts against the fresh allowance.
+        rng.buf(&mut buf);
+        assert_ne!(buf, expected[140..240]);
+        assert_eq!(rng.until_stir(), 1_600_000 - 100);
+        rng.next_u32();
+        assert_eq!(rng.until_stir(), 1_600_000 - 104);
+
+        // A fresh instance stirs on its first request.
+        let mut rng = Arc4Random::new();
+        rng.next_u32();
+        assert_eq!(rng.until_stir(), 1_600_000 - 4);
+    }
+
+    #[cfg(feature = "getrandom")]
+    #[test]
+    fn reseeding_threshold() {
+        use super::{ChaCha12Djb, Reseeding};
+
+        let mut rng = Reseeding::<ChaCha12Djb>::new(100);
+        let mut plain = rng.core.clone();
+        let mut expected = [0; 300];
+        plain.clone().fill(&mut expected);
+
+        // Exactly `threshold` bytes come from the original seed, without reseeding yet.
+        let mut buf = [0; 100];
+        rng.fill(&mut buf);
+        assert_eq!(buf, expected[..100]);
+        assert_eq!(rng.remaining, 0);
+        plain.fill(&mut [0; 100]);
+        assert_eq!(rng.core.get_block(), plain.clone().get_block());
+
+        // The very next byte comes from a new seed.
+        let mut buf = [0; 200];
+        rng.fill(&mut buf);
+        assert_ne!(buf[..100], expected[100..200]);
+        assert_eq!(rng.remaining, 0);
+        assert_ne!(rng.core.get_block(), plain.get_block());
+    }
+
+    #[cfg(feature = "getrandom")]
+    #[test]
+    fn reseeding_many_thresholds() {
+        use super::{ChaCha12Djb, Reseeding};
+
+        let mut rng = Reseeding::<ChaCha12Djb>::new(64);
+        let mut expected = [0; 64 * 4];
+        rng.core.clone().fill(&mut expected);
+
+        // A single call spanning four reseeds, ending partway through the fifth seed.
+        let mut buf = [0; 64 * 4 + 10];
+        rng.fill(&mut buf);
+        assert_eq!(rng.remaining, 54);
+        let (pieces, _) = buf.as_chunks::<64>();
+        assert_eq!(pieces[0], expected[..64]);
+        for (i, piece) in pieces.iter().enumerate().skip(1) {
+            assert_ne!(piece[..], expected[i * 64..][..64]);
+            assert!(pieces[..i].iter().all(|earlier| earlier != piece));
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn save_and_load_state() {
+        use super::{ChaCha12Ietf, STATE_LEN_U8};
+        use std::io::ErrorKind;
+
+        let mut chacha = ChaCha12Ietf::new([3; 8], 11, [1, 2, 3]);
+        chacha.seek(chacha.position() + 5);
+        let mut file = [0; STATE_LEN_U8 + 1];
+        chacha.save_state(&mut &mut file[..]).unwrap();
+        assert_eq!(file[..STATE_LEN_U8], chacha.to_state_bytes());
+        let mut restored = ChaCha12Ietf::load_state(&mut &file[..]).unwrap();
+        assert_eq!(restored.position(), chacha.position());
+        assert_eq!(restored.get_block(), chacha.get_block());
+
+        let short = ChaCha12Ietf::load_state(&mut &file[..STATE_LEN_U8 - 1]);
+        assert_eq!(short.unwrap_err().kind(), ErrorKind::UnexpectedEof);
+        file[STATE_LEN_U8 - 1] = u8::MAX;
+        let invalid = ChaCha12Ietf::load_state(&mut &file[..]);
+        assert_eq!(invalid.unwrap_err().kind(), ErrorKind::InvalidData);
+        // Too small to hold the whole state.
+        assert!(chacha.save_state(&mut &mut [0; 8][..]).is_err());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn error_conversions() {
+        use super::Error;
+        use std::error::Error as _;
+        use std::io::{self, ErrorKind};
+        use std::string::ToString;
+
+        assert_eq!(
+            Error::TagMismatch.to_string(),
+            "authentication tag mismatch"
+        );
+        assert!(Error::InvalidLength.source().is_none());
+        let error = io::Error::from(Error::TagMismatch);
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        assert_eq!(
+            error.into_inner().unwrap().to_string(),
+            "authentication tag mismatch"
+        );
+        assert_eq!(
+            io::Error::from(Error::MissingKey).kind(),
+            ErrorKind::InvalidInput
+        );
+        assert_eq!(
+            io::Error::from(Error::EntropyUnavailable).kind(),
+            ErrorKind::Other
+        );
+        let entropy = getrandom::Error::new_custom(1);
+        assert_eq!(Error::from(entropy), Error::EntropyUnavailable);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn sync_handle_is_contiguous() {
+        use super::{ChaCha12Djb, SyncChaCha};
+
+        let mut rng = new_rng_secure();
+        let mut seed = [0; SEED_LEN_U8];
+        rng.fill_bytes(&mut seed);
+        let shared = SyncChaCha::new(ChaCha12Djb::from(seed));
+        let mut handle = shared.handle();
+        let mut buf = [0; BUF_LEN_U8 * 8];
+        let mut buf_ref = [0; BUF_LEN_U8 * 8];
+        let mut start = 0;
+        while start < buf.len() {
+            let end = (start + rng.usize() % BUF_LEN_U8 * 2).min(buf.len());
+            handle.fill(&mut buf[start..end]);
+            start = end;
+        }
+        ChaCha12Djb::from(seed).fill(&mut buf_ref);
+        assert_eq!(buf, buf_ref);
+    }
+
     fn test_chacha<M: Machine, R: DoubleRounds, V: Variant>() {
         let mut rng = new_rng_secure();
         for i in 0..TEST_COUNT {
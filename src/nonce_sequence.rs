@@ -0,0 +1,97 @@
+/*!
+Module containing [`NonceSequence`], which hands out the nonces for an AEAD key so that none of
+them can ever be used twice.
+
+Each nonce is a fixed 4-byte prefix followed by a 64-bit big-endian counter, so the nonces are
+strictly increasing (as byte strings) for as long as the sequence lasts. The prefix lets several
+senders share a key without coordinating, as long as each one is given a different prefix. Once
+the counter has been used up the sequence refuses to hand out anything else, rather than wrapping
+back around to a nonce which has already been used.
+*/
+
+use crate::Error;
+use crate::chacha_poly1305::*;
+use crate::poly1305::POLY1305_TAG_LEN;
+use crate::rounds::*;
+
+/// Source of unique nonces for a single AEAD key.
+///
+/// Deliberately doesn't implement `Clone`, since two copies of the same
+/// sequence would hand out the same nonces.
+///
+/// ```
+/// use chachacha::{ChaCha20Poly1305, NonceSequence};
+///
+/// let aead = ChaCha20Poly1305::new(&[69; 32]);
+/// let mut nonces = NonceSequence::new([0, 0, 0, 1]);
+/// let mut message = *b"hello world";
+/// let (nonce, tag) = nonces.seal(&aead, b"header", &mut message).unwrap();
+/// assert_eq!(nonce, [0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0]);
+/// assert!(aead.decrypt_detached(&nonce, b"header", &mut message, &tag).is_ok());
+/// assert_eq!(&message, b"hello world");
+/// assert_eq!(nonces.counter(), Some(1));
+/// ```
+#[derive(Debug)]
+pub struct NonceSequence {
+    prefix: [u8; 4],
+    counter: Option<u64>,
+}
+
+impl NonceSequence {
+    /// Creates a new `NonceSequence` instance using `prefix`, with the counter starting at 0.
+    #[inline]
+    pub fn new(prefix: [u8; 4]) -> Self {
+        Self::resume(prefix, 0)
+    }
+
+    /// Creates a new `NonceSequence` instance using `prefix`, with the counter starting
+    /// at `counter`, for continuing a sequence whose progress was persisted elsewhere.
+    ///
+    /// Resuming from anything other than the counter of the next unused nonce
+    /// defeats the whole point of this type.
+    #[inline]
+    pub fn resume(prefix: [u8; 4], counter: u64) -> Self {
+        Self {
+            prefix,
+            counter: Some(counter),
+        }
+    }
+
+    /// Returns the counter of the next nonce, or `None` if they've all been used up.
+    #[inline]
+    pub fn counter(&self) -> Option<u64> {
+        self.counter
+    }
+
+    /// Returns the next nonce, which will never be returned again.
+    ///
+    /// Returns [`Error::CounterExhausted`] if every nonce has already been used.
+    #[inline]
+    pub fn next_nonce(&mut self) -> Result<[u8; AEAD_NONCE_LEN], Error> {
+        let counter = self.counter.ok_or(Error::CounterExhausted)?;
+        self.counter = counter.checked_add(1);
+        let mut nonce = [0; AEAD_NONCE_LEN];
+        nonce[..4].copy_from_slice(&self.prefix);
+        nonce[4..].copy_from_slice(&counter.to_be_bytes());
+        Ok(nonce)
+    }
+
+    /// Encrypts `buf` in place with the next nonce, returning the nonce (which must
+    /// be sent along with the message) and the tag authenticating `buf` and `aad`.
+    ///
+    /// Returns [`Error::CounterExhausted`] if every nonce has already been used, and
+    /// [`Error::InvalidLength`] (without using up a nonce) if `buf` is longer than
+    /// [`AEAD_MAX_MESSAGE_LEN`]. In both cases `buf` is left untouched.
+    #[inline]
+    pub fn seal<R: DoubleRounds>(
+        &mut self,
+        aead: &ChaChaPoly1305<R>,
+        aad: &[u8],
+        buf: &mut [u8],
+    ) -> Result<([u8; AEAD_NONCE_LEN], [u8; POLY1305_TAG_LEN]), Error> {
+        check_message_len(buf.len() as u64)?;
+        let nonce = self.next_nonce()?;
+        let tag = aead.encrypt_detached(&nonce, aad, buf)?;
+        Ok((nonce, tag))
+    }
+}
@@ -0,0 +1,152 @@
+/*!
+Module containing stateless helpers which xor a buffer with the keystream of a single ChaCha
+instance, for when constructing and managing one of the ChaCha types is more trouble than it's
+worth.
+
+Keys and nonces are taken as bytes (interpreted as little-endian words, exactly as in
+[RFC 8439]), so the output of the 20 round versions matches any other implementation.
+
+Also contains [`OneShot`], an AEAD encryption bound to a single nonce which is consumed by
+encrypting, so a given object can't ever be used to encrypt two messages.
+
+[RFC 8439]: https://datatracker.ietf.org/doc/html/rfc8439
+*/
+
+use crate::chacha_poly1305::*;
+use crate::poly1305::POLY1305_TAG_LEN;
+use crate::rounds::*;
+use crate::util::le_words;
+use crate::variations::*;
+use crate::{ChaCha, Error, NonceSequence};
+
+/// ChaCha-Poly1305 (with `R` rounds) encryption of exactly one message under a fixed nonce.
+///
+/// [`Self::encrypt`] takes `self` by value, and `OneShot` deliberately doesn't implement
+/// `Clone`, so the compiler rejects any attempt to encrypt a second message with it.
+///
+/// ```
+/// use chachacha::{ChaCha20Poly1305, OneShot};
+///
+/// let key = [69; 32];
+/// let nonce = [7; 12];
+/// let mut message = *b"hello world";
+/// let one_shot: OneShot = OneShot::new(&key, &nonce);
+/// let tag = one_shot.encrypt(b"header", &mut message).unwrap();
+///
+/// let aead = ChaCha20Poly1305::new(&key);
+/// assert!(aead.decrypt_detached(&nonce, b"header", &mut message, &tag).is_ok());
+/// assert_eq!(&message, b"hello world");
+/// ```
+///
+/// ```compile_fail
+/// use chachacha::OneShot;
+///
+/// let one_shot: OneShot = OneShot::new(&[69; 32], &[7; 12]);
+/// let _ = one_shot.encrypt(&[], &mut [1, 2, 3]);
+/// let _ = one_shot.encrypt(&[], &mut [4, 5, 6]);
+/// ```
+pub struct OneShot<R = R20> {
+    aead: ChaChaPoly1305<R>,
+    nonce: [u8; AEAD_NONCE_LEN],
+}
+
+impl<R: DoubleRounds> OneShot<R> {
+    /// Creates a new `OneShot` instance which will encrypt with `key` and `nonce`.
+    #[inline]
+    pub fn new(key: &[u8; AEAD_KEY_LEN], nonce: &[u8; AEAD_NONCE_LEN]) -> Self {
+        Self {
+            aead: ChaChaPoly1305::new(key),
+            nonce: *nonce,
+        }
+    }
+
+    /// Creates a new `OneShot` instance which will encrypt with `key`
+    /// and the next nonce of `nonces`.
+    ///
+    /// Returns [`Error::CounterExhausted`] if `nonces` has been used up.
+    #[inline]
+    pub fn from_sequence(
+        key: &[u8; AEAD_KEY_LEN],
+        nonces: &mut NonceSequence,
+    ) -> Result<Self, Error> {
+        Ok(Self::new(key, &nonces.next_nonce()?))
+    }
+
+    /// Returns the nonce which will be used, which must be sent along with the message.
+    #[inline]
+    pub fn nonce(&self) -> &[u8; AEAD_NONCE_LEN] {
+        &self.nonce
+    }
+
+    /// Encrypts `buf` in place, returning the tag authenticating it and `aad`.
+    ///
+    /// Returns [`Error::InvalidLength`] if `buf` is longer than [`AEAD_MAX_MESSAGE_LEN`],
+    /// in which case it's left untouched.
+    #[inline]
+    pub fn encrypt(self, aad: &[u8], buf: &mut [u8]) -> Result<[u8; POLY1305_TAG_LEN], Error> {
+        self.aead.encrypt_detached(&self.nonce, aad, buf)
+    }
+}
+
+/// Xors `buf` with the keystream of ChaCha20 (as specified in RFC 8439), starting at block `counter`.
+///
+/// ```
+/// use chachacha::chacha20_ietf_xor;
+///
+/// let mut buf = *b"attack at dawn";
+/// chacha20_ietf_xor(&[69; 32], &[0; 12], 1, &mut buf);
+/// chacha20_ietf_xor(&[69; 32], &[0; 12], 1, &mut buf);
+/// assert_eq!(&buf, b"attack at dawn");
+/// ```
+#[inline]
+pub fn chacha20_ietf_xor(key: &[u8; 32], nonce: &[u8; 12], counter: u32, buf: &mut [u8]) {
+    ietf_xor::<R20>(key, nonce, counter, buf);
+}
+
+/// Xors `buf` with the keystream of ChaCha12 (with a 96-bit nonce), starting at block `counter`.
+#[inline]
+pub fn chacha12_ietf_xor(key: &[u8; 32], nonce: &[u8; 12], counter: u32, buf: &mut [u8]) {
+    ietf_xor::<R12>(key, nonce, counter, buf);
+}
+
+/// Xors `buf` with the keystream of ChaCha8 (with a 96-bit nonce), starting at block `counter`.
+#[inline]
+pub fn chacha8_ietf_xor(key: &[u8; 32], nonce: &[u8; 12], counter: u32, buf: &mut [u8]) {
+    ietf_xor::<R8>(key, nonce, counter, buf);
+}
+
+/// Xors `buf` with the keystream of the original ChaCha20 (with a 64-bit nonce),
+/// starting at block `counter`.
+#[inline]
+pub fn chacha20_djb_xor(key: &[u8; 32], nonce: &[u8; 8], counter: u64, buf: &mut [u8]) {
+    djb_xor::<R20>(key, nonce, counter, buf);
+}
+
+/// Xors `buf` with the keystream of the original ChaCha12 (with a 64-bit nonce),
+/// starting at block `counter`.
+#[inline]
+pub fn chacha12_djb_xor(key: &[u8; 32], nonce: &[u8; 8], counter: u64, buf: &mut [u8]) {
+    djb_xor::<R12>(key, nonce, counter, buf);
+}
+
+/// Xors `buf` with the keystream of the original ChaCha8 (with a 64-bit nonce),
+/// starting at block `counter`.
+#[inline]
+pub fn chacha8_djb_xor(key: &[u8; 32], nonce: &[u8; 8], counter: u64, buf: &mut [u8]) {
+    djb_xor::<R8>(key, nonce, counter, buf);
+}
+
+#[inline]
+fn ietf_xor<R: DoubleRounds>(key: &[u8; 32], nonce: &[u8; 12], counter: u32, buf: &mut [u8]) {
+    let mut key = le_words(key);
+    ChaCha::<R, Ietf>::new(key, counter as u64, le_words(nonce)).xor(buf);
+    key.fill(0);
+}
+
+#[inline]
+fn djb_xor<R: DoubleRounds>(key: &[u8; 32], nonce: &[u8; 8], counter: u64, buf: &mut [u8]) {
+    let [n0, n1] = le_words(nonce);
+    let mut key = le_words(key);
+    ChaCha::<R, Djb>::new(key, counter, [n0, n1, 0]).xor(buf);
+    key.fill(0);
+}
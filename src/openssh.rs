@@ -0,0 +1,141 @@
+/*!
+Module containing [`OpenSshChaChaPoly`], the `chacha20-poly1305@openssh.com` cipher used by
+[OpenSSH] (and most other SSH implementations).
+
+It differs from the RFC 8439 AEAD in several ways. The 64-byte key is split into two independent
+ChaCha20 keys: the first half encrypts the packet payload, while the second half is only used to
+encrypt the 4-byte packet length (so the receiver can decrypt it before the rest of the packet
+has arrived). Both use the original 64-bit nonce variant of ChaCha20, with the packet sequence
+number (big-endian) as the nonce. As usual, block 0 of the payload key's keystream provides the
+one-time Poly1305 key and the payload is encrypted starting at block 1, but the tag is computed
+directly over the encrypted packet (length included) without any padding or length suffix.
+
+[OpenSSH]: https://cvsweb.openbsd.org/src/usr.bin/ssh/PROTOCOL.chacha20poly1305?annotate=HEAD
+*/
+
+use crate::poly1305::*;
+use crate::util::le_words;
+use crate::{ChaCha, Error};
+use crate::{rounds::R20, variations::Djb};
+
+/// Size (in 8-bit integers) of a `chacha20-poly1305@openssh.com` key.
+pub const OPENSSH_KEY_LEN: usize = 64;
+/// Size (in 8-bit integers) of the packet length at the start of every SSH packet.
+pub const OPENSSH_LENGTH_LEN: usize = 4;
+
+/// The `chacha20-poly1305@openssh.com` cipher.
+///
+/// Packets passed to [`Self::seal`] and [`Self::open`] include the 4-byte length field.
+///
+/// ```
+/// use chachacha::OpenSshChaChaPoly;
+///
+/// let cipher = OpenSshChaChaPoly::new(&[69; 64]);
+/// let mut packet = *b"\x00\x00\x00\x0bhello world";
+/// let tag = cipher.seal(3, &mut packet).unwrap();
+/// // The receiver reads the length first, to know how much more to wait for.
+/// let len = cipher.decrypt_length(3, packet[..4].try_into().unwrap());
+/// assert_eq!(u32::from_be_bytes(len), 11);
+/// assert!(cipher.open(3, &mut packet, &tag).is_ok());
+/// assert_eq!(&packet[4..], b"hello world");
+/// ```
+pub struct OpenSshChaChaPoly {
+    main_key: [u32; 8],
+    header_key: [u32; 8],
+}
+
+impl OpenSshChaChaPoly {
+    /// Creates a new `OpenSshChaChaPoly` instance using `key`, as derived by the SSH key exchange.
+    #[inline]
+    pub fn new(key: &[u8; OPENSSH_KEY_LEN]) -> Self {
+        let (main_key, header_key) = key.split_at(32);
+        Self {
+            main_key: le_words(main_key),
+            header_key: le_words(header_key),
+        }
+    }
+
+    /// Encrypts the packet length of the packet with sequence number `seq`.
+    #[inline]
+    pub fn encrypt_length(
+        &self,
+        seq: u32,
+        length: &[u8; OPENSSH_LENGTH_LEN],
+    ) -> [u8; OPENSSH_LENGTH_LEN] {
+        let mut length = *length;
+        instance(&self.header_key, seq).xor(&mut length);
+        length
+    }
+
+    /// Decrypts the packet length of the packet with sequence number `seq`.
+    ///
+    /// The length is unauthenticated until the whole packet has been passed to [`Self::open`],
+    /// so it should only be used to determine how much data to wait for.
+    #[inline]
+    pub fn decrypt_length(
+        &self,
+        seq: u32,
+        length: &[u8; OPENSSH_LENGTH_LEN],
+    ) -> [u8; OPENSSH_LENGTH_LEN] {
+        self.encrypt_length(seq, length)
+    }
+
+    /// Encrypts `packet` (length field included) in place, returning the tag authenticating it.
+    ///
+    /// Returns [`Error::InvalidLength`] if `packet` is too short to contain a length field.
+    #[inline]
+    pub fn seal(&self, seq: u32, packet: &mut [u8]) -> Result<[u8; POLY1305_TAG_LEN], Error> {
+        let (length, payload) = packet
+            .split_first_chunk_mut::<OPENSSH_LENGTH_LEN>()
+            .ok_or(Error::InvalidLength)?;
+        instance(&self.header_key, seq).xor(length);
+        let (mut chacha, mut poly) = self.init(seq);
+        chacha.xor(payload);
+        poly.update(packet);
+        Ok(poly.finalize())
+    }
+
+    /// Authenticates `packet` (length field included, still encrypted) against `tag`,
+    /// and if successful decrypts it in place.
+    ///
+    /// Returns [`Error::InvalidLength`] if `packet` is too short to contain a length field, and
+    /// [`Error::TagMismatch`] if authentication fails. In both cases `packet` is left untouched.
+    #[inline]
+    pub fn open(
+        &self,
+        seq: u32,
+        packet: &mut [u8],
+        tag: &[u8; POLY1305_TAG_LEN],
+    ) -> Result<(), Error> {
+        if packet.len() < OPENSSH_LENGTH_LEN {
+            return Err(Error::InvalidLength);
+        }
+        let (mut chacha, mut poly) = self.init(seq);
+        poly.update(packet);
+        if !poly.verify(tag) {
+            return Err(Error::TagMismatch);
+        }
+        let (length, payload) = packet.split_at_mut(OPENSSH_LENGTH_LEN);
+        instance(&self.header_key, seq).xor(length);
+        chacha.xor(payload);
+        Ok(())
+    }
+
+    #[inline]
+    fn init(&self, seq: u32) -> (ChaCha<R20, Djb>, Poly1305) {
+        let mut chacha = instance(&self.main_key, seq);
+        // Only consumes the first 32 bytes of block 0, which
+        // leaves the counter at 1 for the payload.
+        let mut poly_key = [0; POLY1305_KEY_LEN];
+        chacha.fill(&mut poly_key);
+        let poly = Poly1305::new(&poly_key);
+        poly_key.fill(0);
+        (chacha, poly)
+    }
+}
+
+#[inline]
+fn instance(key: &[u32; 8], seq: u32) -> ChaCha<R20, Djb> {
+    let [n0, n1] = le_words(&(seq as u64).to_be_bytes());
+    ChaCha::new(*key, 0, [n0, n1, 0])
+}
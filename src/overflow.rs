@@ -0,0 +1,22 @@
+/*!
+Module containing [`CounterOverflow`], which controls what the [`Djb`](crate::ChaCha20Djb)
+variants do once their 64-bit counter runs out.
+
+Reaching the end of a 64-bit counter would take centuries of continuous output, so wrapping
+around is the default. Long-lived deployments which would rather fail loudly than ever repeat
+keystream can opt into one of the other policies with `set_counter_overflow`.
+*/
+
+/// Behavior of a [`Djb`](crate::ChaCha20Djb) instance when producing output would require
+/// the block at a counter of `u64::MAX` (or anything beyond it).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum CounterOverflow {
+    /// The counter silently wraps around to 0, repeating the keystream.
+    #[default]
+    Wrap,
+    /// The counter never wraps: the infallible methods panic instead,
+    /// and `try_fill`/`try_xor` should be used to get an error.
+    Saturate,
+    /// Panics in debug builds, but wraps in release builds.
+    DebugPanic,
+}
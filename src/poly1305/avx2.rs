@@ -0,0 +1,129 @@
+use super::*;
+#[cfg(target_arch = "x86")]
+use core::arch::x86::*;
+#[cfg(target_arch = "x86_64")]
+use core::arch::x86_64::*;
+use core::mem::transmute;
+
+/// The amount of blocks we process in parallel.
+const LANES: usize = 4;
+
+pub struct Accumulator;
+
+impl Backend for Accumulator {
+    #[inline]
+    fn blocks(state: &mut State, blocks: &[[u8; BLOCK_LEN]]) {
+        let (groups, rem) = blocks.as_chunks::<LANES>();
+        if !groups.is_empty() {
+            unsafe { groups_avx2(state, groups) };
+        }
+        soft::Accumulator::blocks(state, rem);
+    }
+}
+
+/// Limbs of an accumulator (or multiplier), with one lane per 64-bit integer.
+type Limbs = [__m256i; 5];
+
+#[inline]
+unsafe fn groups_avx2(state: &mut State, groups: &[[[u8; BLOCK_LEN]; LANES]]) {
+    unsafe {
+        let r1 = state.r;
+        let r2 = soft::mul(&r1, &r1);
+        let r3 = soft::mul(&r2, &r1);
+        let r4 = soft::mul(&r2, &r2);
+        // Every group except the last multiplies each lane by r^4.
+        let r_bulk: Limbs = core::array::from_fn(|i| _mm256_set1_epi64x(r4[i] as i64));
+        // The last group multiplies the lanes by r^4, r^3, r^2, and r^1 respectively.
+        let r_last: Limbs = core::array::from_fn(|i| {
+            _mm256_set_epi64x(r1[i] as i64, r2[i] as i64, r3[i] as i64, r4[i] as i64)
+        });
+
+        // The existing accumulator is folded into the first lane.
+        let mut h: Limbs = core::array::from_fn(|i| _mm256_set_epi64x(0, 0, 0, state.h[i] as i64));
+        let (last, bulk) = groups.split_last().unwrap();
+        for group in bulk {
+            h = mul(add(h, load(group)), &r_bulk);
+        }
+        h = mul(add(h, load(last)), &r_last);
+
+        // Sum the lanes back into a single accumulator.
+        let lanes: [[u64; LANES]; 5] = transmute(h);
+        state.h = soft::carry(lanes.map(|lane| lane.into_iter().sum()));
+    }
+}
+
+#[inline(always)]
+unsafe fn load(group: &[[u8; BLOCK_LEN]; LANES]) -> Limbs {
+    unsafe {
+        let m = group.each_ref().map(soft::limbs::<false>);
+        core::array::from_fn(|i| {
+            _mm256_set_epi64x(
+                m[3][i] as i64,
+                m[2][i] as i64,
+                m[1][i] as i64,
+                m[0][i] as i64,
+            )
+        })
+    }
+}
+
+#[inline(always)]
+unsafe fn add(a: Limbs, b: Limbs) -> Limbs {
+    unsafe { core::array::from_fn(|i| _mm256_add_epi64(a[i], b[i])) }
+}
+
+/// Vectorized equivalent of [`soft::mul`].
+#[inline(always)]
+unsafe fn mul(h: Limbs, r: &Limbs) -> Limbs {
+    unsafe {
+        let [h0, h1, h2, h3, h4] = h;
+        let [r0, r1, r2, r3, r4] = *r;
+        let s1 = times_five(r1);
+        let s2 = times_five(r2);
+        let s3 = times_five(r3);
+        let s4 = times_five(r4);
+
+        let d0 = sum_of_products([(h0, r0), (h1, s4), (h2, s3), (h3, s2), (h4, s1)]);
+        let mut d1 = sum_of_products([(h0, r1), (h1, r0), (h2, s4), (h3, s3), (h4, s2)]);
+        let mut d2 = sum_of_products([(h0, r2), (h1, r1), (h2, r0), (h3, s4), (h4, s3)]);
+        let mut d3 = sum_of_products([(h0, r3), (h1, r2), (h2, r1), (h3, r0), (h4, s4)]);
+        let mut d4 = sum_of_products([(h0, r4), (h1, r3), (h2, r2), (h3, r1), (h4, r0)]);
+
+        let mask = _mm256_set1_epi64x(LIMB_MASK as i64);
+        let mut c = _mm256_srli_epi64(d0, 26);
+        let h0 = _mm256_and_si256(d0, mask);
+        d1 = _mm256_add_epi64(d1, c);
+        c = _mm256_srli_epi64(d1, 26);
+        let h1 = _mm256_and_si256(d1, mask);
+        d2 = _mm256_add_epi64(d2, c);
+        c = _mm256_srli_epi64(d2, 26);
+        let h2 = _mm256_and_si256(d2, mask);
+        d3 = _mm256_add_epi64(d3, c);
+        c = _mm256_srli_epi64(d3, 26);
+        let h3 = _mm256_and_si256(d3, mask);
+        d4 = _mm256_add_epi64(d4, c);
+        c = _mm256_srli_epi64(d4, 26);
+        let h4 = _mm256_and_si256(d4, mask);
+        let h0 = _mm256_add_epi64(h0, times_five(c));
+        c = _mm256_srli_epi64(h0, 26);
+        let h0 = _mm256_and_si256(h0, mask);
+        let h1 = _mm256_add_epi64(h1, c);
+        [h0, h1, h2, h3, h4]
+    }
+}
+
+#[inline(always)]
+unsafe fn sum_of_products(terms: [(__m256i, __m256i); 5]) -> __m256i {
+    unsafe {
+        terms
+            .into_iter()
+            .map(|(a, b)| _mm256_mul_epu32(a, b))
+            .reduce(|a, b| _mm256_add_epi64(a, b))
+            .unwrap()
+    }
+}
+
+#[inline(always)]
+unsafe fn times_five(value: __m256i) -> __m256i {
+    unsafe { _mm256_add_epi64(value, _mm256_slli_epi64(value, 2)) }
+}
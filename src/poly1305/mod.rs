@@ -0,0 +1,310 @@
+/*!
+Module containing [`Poly1305`], the one-time authenticator designed by Daniel J. Bernstein
+and most often paired with ChaCha to form an AEAD.
+
+The structure mirrors the ChaCha backends: the portable [`soft`] implementation is a port of
+the 32-bit variant of [poly1305-donna], which represents the 130-bit accumulator as five 26-bit
+limbs so every multiplication fits in a `u64` without overflow. The vectorized implementations
+keep that same radix-2<sup>26</sup> representation, but evaluate several blocks at once by
+splitting the message into interleaved lanes. Each lane is multiplied by r<sup>N</sup>
+(where N is the number of lanes) instead of r, and the final group of blocks multiplies the lanes
+by r<sup>N</sup>, ..., r<sup>1</sup> respectively, so summing the lanes at the end gives exactly
+the same result as evaluating one block at a time. AVX2 processes four lanes at once, and Neon
+processes two.
+
+Only the widest implementation available is used, and (like the ChaCha backends) none of this
+is accessible by the end-user of this crate. With the `universal-hash` feature enabled,
+[`Poly1305`] implements the RustCrypto [`UniversalHash`](universal_hash::UniversalHash) trait, so
+it can be dropped into generic code written against the `poly1305` crate.
+
+[poly1305-donna]: https://github.com/floodyberry/poly1305-donna
+*/
+
+// Only the fallback for targets without a vectorized implementation, but it's
+// always compiled so the tests can check the other backends against it.
+pub mod soft;
+
+cfg_if::cfg_if! {
+    if #[cfg(all(any(target_arch = "x86_64", target_arch = "x86"), target_feature = "avx2"))] {
+        pub mod avx2;
+        use avx2::Accumulator;
+    } else if #[cfg(all(any(target_arch = "aarch64", target_arch = "arm64ec"), target_feature = "neon"))] {
+        pub mod neon;
+        use neon::Accumulator;
+    } else {
+        use soft::Accumulator;
+    }
+}
+
+/// Size (in 8-bit integers) of a Poly1305 key.
+pub const POLY1305_KEY_LEN: usize = 32;
+/// Size (in 8-bit integers) of a Poly1305 tag.
+pub const POLY1305_TAG_LEN: usize = 16;
+/// Size (in 8-bit integers) of the blocks Poly1305 operates on.
+pub const BLOCK_LEN: usize = 16;
+/// Mask for a single 26-bit limb.
+pub const LIMB_MASK: u32 = 0x3ffffff;
+
+/// Core trait which must be implemented for all supported Poly1305 backends.
+pub trait Backend {
+    /// Absorbs `blocks` into the accumulator of `state`.
+    ///
+    /// These are always full blocks, never the (padded) final block of a message.
+    fn blocks(state: &mut State, blocks: &[[u8; BLOCK_LEN]]);
+}
+
+/// Raw Poly1305 state, shared between all backends.
+#[derive(Clone)]
+pub struct State {
+    /// The clamped multiplier, in 26-bit limbs.
+    pub r: [u32; 5],
+    /// The accumulator, in (partially reduced) 26-bit limbs.
+    pub h: [u32; 5],
+    /// The value added to the accumulator to produce the final tag.
+    pub pad: [u32; 4],
+}
+
+impl State {
+    /// Creates a new `State` with an empty accumulator.
+    #[inline]
+    pub fn new(key: &[u8; POLY1305_KEY_LEN]) -> Self {
+        let r = [
+            le_u32(&key[0..]) & 0x3ffffff,
+            (le_u32(&key[3..]) >> 2) & 0x3ffff03,
+            (le_u32(&key[6..]) >> 4) & 0x3ffc0ff,
+            (le_u32(&key[9..]) >> 6) & 0x3f03fff,
+            (le_u32(&key[12..]) >> 8) & 0x00fffff,
+        ];
+        let pad = [
+            le_u32(&key[16..]),
+            le_u32(&key[20..]),
+            le_u32(&key[24..]),
+            le_u32(&key[28..]),
+        ];
+        Self { r, h: [0; 5], pad }
+    }
+
+    /// Computes the tag from the current accumulator.
+    #[inline]
+    pub fn finalize(&self) -> [u8; POLY1305_TAG_LEN] {
+        let [mut h0, mut h1, mut h2, mut h3, mut h4] = self.h;
+
+        // Fully carry h.
+        let mut c = h1 >> 26;
+        h1 &= LIMB_MASK;
+        h2 += c;
+        c = h2 >> 26;
+        h2 &= LIMB_MASK;
+        h3 += c;
+        c = h3 >> 26;
+        h3 &= LIMB_MASK;
+        h4 += c;
+        c = h4 >> 26;
+        h4 &= LIMB_MASK;
+        h0 += c * 5;
+        c = h0 >> 26;
+        h0 &= LIMB_MASK;
+        h1 += c;
+
+        // Compute h + -p.
+        let mut g0 = h0.wrapping_add(5);
+        c = g0 >> 26;
+        g0 &= LIMB_MASK;
+        let mut g1 = h1.wrapping_add(c);
+        c = g1 >> 26;
+        g1 &= LIMB_MASK;
+        let mut g2 = h2.wrapping_add(c);
+        c = g2 >> 26;
+        g2 &= LIMB_MASK;
+        let mut g3 = h3.wrapping_add(c);
+        c = g3 >> 26;
+        g3 &= LIMB_MASK;
+        let mut g4 = h4.wrapping_add(c).wrapping_sub(1 << 26);
+
+        // Select h if h < p, or h + -p if h >= p, in constant time.
+        let mut mask = (g4 >> 31).wrapping_sub(1);
+        g0 &= mask;
+        g1 &= mask;
+        g2 &= mask;
+        g3 &= mask;
+        g4 &= mask;
+        mask = !mask;
+        h0 = (h0 & mask) | g0;
+        h1 = (h1 & mask) | g1;
+        h2 = (h2 & mask) | g2;
+        h3 = (h3 & mask) | g3;
+        h4 = (h4 & mask) | g4;
+
+        // h = h % (2^128)
+        h0 |= h1 << 26;
+        h1 = (h1 >> 6) | (h2 << 20);
+        h2 = (h2 >> 12) | (h3 << 14);
+        h3 = (h3 >> 18) | (h4 << 8);
+
+        // tag = (h + pad) % (2^128)
+        let mut f = h0 as u64 + self.pad[0] as u64;
+        h0 = f as u32;
+        f = h1 as u64 + self.pad[1] as u64 + (f >> 32);
+        h1 = f as u32;
+        f = h2 as u64 + self.pad[2] as u64 + (f >> 32);
+        h2 = f as u32;
+        f = h3 as u64 + self.pad[3] as u64 + (f >> 32);
+        h3 = f as u32;
+
+        let mut tag = [0; POLY1305_TAG_LEN];
+        tag[0..4].copy_from_slice(&h0.to_le_bytes());
+        tag[4..8].copy_from_slice(&h1.to_le_bytes());
+        tag[8..12].copy_from_slice(&h2.to_le_bytes());
+        tag[12..16].copy_from_slice(&h3.to_le_bytes());
+        tag
+    }
+}
+
+/// Incremental Poly1305 state.
+///
+/// **A Poly1305 key must never be used to authenticate more than one message.**
+///
+/// ```
+/// use chachacha::Poly1305;
+///
+/// let key = [69; 32];
+/// let mut poly = Poly1305::new(&key);
+/// poly.update(b"hello ");
+/// poly.update(b"world");
+/// assert_eq!(poly.finalize(), Poly1305::mac(&key, b"hello world"));
+/// ```
+#[derive(Clone)]
+pub struct Poly1305 {
+    state: State,
+    buf: [u8; BLOCK_LEN],
+    buf_len: usize,
+}
+
+impl Poly1305 {
+    /// Creates a new `Poly1305` instance using the one-time `key`.
+    #[inline]
+    pub fn new(key: &[u8; POLY1305_KEY_LEN]) -> Self {
+        Self {
+            state: State::new(key),
+            buf: [0; BLOCK_LEN],
+            buf_len: 0,
+        }
+    }
+
+    /// Computes the Poly1305 tag of `msg` using the one-time `key`.
+    #[inline]
+    pub fn mac(key: &[u8; POLY1305_KEY_LEN], msg: &[u8]) -> [u8; POLY1305_TAG_LEN] {
+        let mut poly = Self::new(key);
+        poly.update(msg);
+        poly.finalize()
+    }
+
+    /// Feeds `data` into the authenticator.
+    #[inline]
+    pub fn update(&mut self, mut data: &[u8]) {
+        if self.buf_len > 0 {
+            let len = data.len().min(BLOCK_LEN - self.buf_len);
+            self.buf[self.buf_len..self.buf_len + len].copy_from_slice(&data[..len]);
+            self.buf_len += len;
+            data = &data[len..];
+            if self.buf_len < BLOCK_LEN {
+                return;
+            }
+            Accumulator::blocks(&mut self.state, &[self.buf]);
+            self.buf_len = 0;
+        }
+        let (blocks, rem) = data.as_chunks();
+        Accumulator::blocks(&mut self.state, blocks);
+        self.buf[..rem.len()].copy_from_slice(rem);
+        self.buf_len = rem.len();
+    }
+
+    /// Feeds `data` into the authenticator, then pads it with zeros to
+    /// the next 16-byte boundary (as done by the ChaCha20-Poly1305 AEAD).
+    #[inline]
+    pub fn update_padded(&mut self, data: &[u8]) {
+        self.update(data);
+        if self.buf_len > 0 {
+            self.buf[self.buf_len..].fill(0);
+            Accumulator::blocks(&mut self.state, &[self.buf]);
+            self.buf_len = 0;
+        }
+    }
+
+    /// Consumes `self`, returning the tag of all data that's been fed in.
+    #[inline]
+    pub fn finalize(mut self) -> [u8; POLY1305_TAG_LEN] {
+        if self.buf_len > 0 {
+            self.buf[self.buf_len] = 1;
+            self.buf[self.buf_len + 1..].fill(0);
+            soft::block::<true>(&mut self.state, &self.buf);
+        }
+        self.state.finalize()
+    }
+
+    /// Consumes `self`, returning `true` if the tag of all data that's been
+    /// fed in matches `tag`. The comparison is done in constant time.
+    #[inline]
+    pub fn verify(self, tag: &[u8; POLY1305_TAG_LEN]) -> bool {
+        crate::util::ct_eq(&self.finalize(), tag)
+    }
+}
+
+/// Reads a little-endian `u32` from the start of `bytes`.
+#[inline(always)]
+pub fn le_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes(bytes[..4].try_into().unwrap())
+}
+
+#[cfg(feature = "universal-hash")]
+mod universal_hash_impls {
+    use super::*;
+    use universal_hash::consts::{U4, U16, U32};
+    use universal_hash::crypto_common::{BlockSizeUser, KeySizeUser, ParBlocksSizeUser};
+    use universal_hash::{Block, Key, KeyInit, ParBlocks, UhfBackend, UhfClosure, UniversalHash};
+
+    impl KeySizeUser for Poly1305 {
+        type KeySize = U32;
+    }
+
+    impl KeyInit for Poly1305 {
+        #[inline]
+        fn new(key: &Key<Self>) -> Self {
+            Self::new(key.as_ref())
+        }
+    }
+
+    impl BlockSizeUser for Poly1305 {
+        type BlockSize = U16;
+    }
+
+    // Matches the widest (AVX2) accumulator, so vectorized backends get whole groups of blocks.
+    impl ParBlocksSizeUser for Poly1305 {
+        type ParBlocksSize = U4;
+    }
+
+    impl UhfBackend for Poly1305 {
+        #[inline]
+        fn proc_block(&mut self, block: &Block<Self>) {
+            self.update(block);
+        }
+
+        #[inline]
+        fn proc_par_blocks(&mut self, blocks: &ParBlocks<Self>) {
+            let blocks: [[u8; BLOCK_LEN]; 4] = core::array::from_fn(|i| blocks[i].into());
+            self.update(blocks.as_flattened());
+        }
+    }
+
+    impl UniversalHash for Poly1305 {
+        #[inline]
+        fn update_with_backend(&mut self, f: impl UhfClosure<BlockSize = Self::BlockSize>) {
+            f.call(self);
+        }
+
+        #[inline]
+        fn finalize(self) -> Block<Self> {
+            Self::finalize(self).into()
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use super::*;
+use core::arch::aarch64::*;
+
+/// The amount of blocks we process in parallel.
+const LANES: usize = 2;
+
+pub struct Accumulator;
+
+impl Backend for Accumulator {
+    #[inline]
+    fn blocks(state: &mut State, blocks: &[[u8; BLOCK_LEN]]) {
+        let (groups, rem) = blocks.as_chunks::<LANES>();
+        if !groups.is_empty() {
+            unsafe { groups_neon(state, groups) };
+        }
+        soft::Accumulator::blocks(state, rem);
+    }
+}
+
+/// Limbs of an accumulator, with one lane per 64-bit integer.
+type Limbs = [uint64x2_t; 5];
+/// Limbs of a multiplier, with one lane per 32-bit integer.
+type Multiplier = [uint32x2_t; 5];
+
+#[inline]
+unsafe fn groups_neon(state: &mut State, groups: &[[[u8; BLOCK_LEN]; LANES]]) {
+    unsafe {
+        let r1 = state.r;
+        let r2 = soft::mul(&r1, &r1);
+        // Every group except the last multiplies each lane by r^2.
+        let r_bulk: Multiplier = core::array::from_fn(|i| vdup_n_u32(r2[i]));
+        // The last group multiplies the lanes by r^2 and r^1 respectively.
+        let r_last: Multiplier = core::array::from_fn(|i| vld1_u32([r2[i], r1[i]].as_ptr()));
+
+        // The existing accumulator is folded into the first lane.
+        let mut h: Limbs = core::array::from_fn(|i| vld1q_u64([state.h[i] as u64, 0].as_ptr()));
+        let (last, bulk) = groups.split_last().unwrap();
+        for group in bulk {
+            h = mul(add(h, load(group)), &r_bulk);
+        }
+        h = mul(add(h, load(last)), &r_last);
+
+        // Sum the lanes back into a single accumulator.
+        state.h = soft::carry(h.map(|limb| vaddvq_u64(limb)));
+    }
+}
+
+#[inline(always)]
+unsafe fn load(group: &[[u8; BLOCK_LEN]; LANES]) -> Limbs {
+    unsafe {
+        let m = group.each_ref().map(soft::limbs::<false>);
+        core::array::from_fn(|i| vld1q_u64([m[0][i] as u64, m[1][i] as u64].as_ptr()))
+    }
+}
+
+#[inline(always)]
+unsafe fn add(a: Limbs, b: Limbs) -> Limbs {
+    unsafe { core::array::from_fn(|i| vaddq_u64(a[i], b[i])) }
+}
+
+/// Vectorized equivalent of [`soft::mul`].
+#[inline(always)]
+unsafe fn mul(h: Limbs, r: &Multiplier) -> Limbs {
+    unsafe {
+        // Every limb fits in 32 bits, so narrowing is lossless.
+        let [h0, h1, h2, h3, h4] = h.map(|limb| vmovn_u64(limb));
+        let [r0, r1, r2, r3, r4] = *r;
+        let s1 = vmul_n_u32(r1, 5);
+        let s2 = vmul_n_u32(r2, 5);
+        let s3 = vmul_n_u32(r3, 5);
+        let s4 = vmul_n_u32(r4, 5);
+
+        let d0 = sum_of_products([(h0, r0), (h1, s4), (h2, s3), (h3, s2), (h4, s1)]);
+        let mut d1 = sum_of_products([(h0, r1), (h1, r0), (h2, s4), (h3, s3), (h4, s2)]);
+        let mut d2 = sum_of_products([(h0, r2), (h1, r1), (h2, r0), (h3, s4), (h4, s3)]);
+        let mut d3 = sum_of_products([(h0, r3), (h1, r2), (h2, r1), (h3, r0), (h4, s4)]);
+        let mut d4 = sum_of_products([(h0, r4), (h1, r3), (h2, r2), (h3, r1), (h4, r0)]);
+
+        let mask = vdupq_n_u64(LIMB_MASK as u64);
+        let mut c = vshrq_n_u64::<26>(d0);
+        let h0 = vandq_u64(d0, mask);
+        d1 = vaddq_u64(d1, c);
+        c = vshrq_n_u64::<26>(d1);
+        let h1 = vandq_u64(d1, mask);
+        d2 = vaddq_u64(d2, c);
+        c = vshrq_n_u64::<26>(d2);
+        let h2 = vandq_u64(d2, mask);
+        d3 = vaddq_u64(d3, c);
+        c = vshrq_n_u64::<26>(d3);
+        let h3 = vandq_u64(d3, mask);
+        d4 = vaddq_u64(d4, c);
+        c = vshrq_n_u64::<26>(d4);
+        let h4 = vandq_u64(d4, mask);
+        let h0 = vaddq_u64(h0, vaddq_u64(c, vshlq_n_u64::<2>(c)));
+        c = vshrq_n_u64::<26>(h0);
+        let h0 = vandq_u64(h0, mask);
+        let h1 = vaddq_u64(h1, c);
+        [h0, h1, h2, h3, h4]
+    }
+}
+
+#[inline(always)]
+unsafe fn sum_of_products(terms: [(uint32x2_t, uint32x2_t); 5]) -> uint64x2_t {
+    unsafe {
+        let [(a, b), rest @ ..] = terms;
+        rest.into_iter()
+            .fold(vmull_u32(a, b), |acc, (a, b)| vmlal_u32(acc, a, b))
+    }
+}
@@ -0,0 +1,115 @@
+use super::*;
+
+pub struct Accumulator;
+
+impl Backend for Accumulator {
+    #[inline]
+    fn blocks(state: &mut State, blocks: &[[u8; BLOCK_LEN]]) {
+        blocks
+            .iter()
+            .for_each(|block| self::block::<false>(state, block));
+    }
+}
+
+/// Absorbs a single block into the accumulator of `state`.
+///
+/// `FINAL` should only be used for the padded final block of a message,
+/// which doesn't get the implicit 2<sup>128</sup> bit set.
+#[inline]
+pub fn block<const FINAL: bool>(state: &mut State, block: &[u8; BLOCK_LEN]) {
+    let m = limbs::<FINAL>(block);
+    let mut h = state.h;
+    for i in 0..h.len() {
+        h[i] += m[i];
+    }
+    state.h = mul(&h, &state.r);
+}
+
+/// Splits `block` into 26-bit limbs, setting the 2<sup>128</sup> bit unless `FINAL`.
+#[inline(always)]
+pub fn limbs<const FINAL: bool>(block: &[u8; BLOCK_LEN]) -> [u32; 5] {
+    let hibit = if FINAL { 0 } else { 1 << 24 };
+    [
+        le_u32(&block[0..]) & LIMB_MASK,
+        (le_u32(&block[3..]) >> 2) & LIMB_MASK,
+        (le_u32(&block[6..]) >> 4) & LIMB_MASK,
+        (le_u32(&block[9..]) >> 6) & LIMB_MASK,
+        (le_u32(&block[12..]) >> 8) | hibit,
+    ]
+}
+
+/// Computes `a * b` modulo 2<sup>130</sup> - 5, with the result only partially reduced.
+#[inline(always)]
+pub fn mul(a: &[u32; 5], b: &[u32; 5]) -> [u32; 5] {
+    let [a0, a1, a2, a3, a4] = a.map(|v| v as u64);
+    let [b0, b1, b2, b3, b4] = b.map(|v| v as u64);
+    let s1 = b1 * 5;
+    let s2 = b2 * 5;
+    let s3 = b3 * 5;
+    let s4 = b4 * 5;
+
+    let d0 = a0 * b0 + a1 * s4 + a2 * s3 + a3 * s2 + a4 * s1;
+    let mut d1 = a0 * b1 + a1 * b0 + a2 * s4 + a3 * s3 + a4 * s2;
+    let mut d2 = a0 * b2 + a1 * b1 + a2 * b0 + a3 * s4 + a4 * s3;
+    let mut d3 = a0 * b3 + a1 * b2 + a2 * b1 + a3 * b0 + a4 * s4;
+    let mut d4 = a0 * b4 + a1 * b3 + a2 * b2 + a3 * b1 + a4 * b0;
+
+    let mut c = d0 >> 26;
+    let h0 = d0 & LIMB_MASK as u64;
+    d1 += c;
+    c = d1 >> 26;
+    let h1 = d1 & LIMB_MASK as u64;
+    d2 += c;
+    c = d2 >> 26;
+    let h2 = d2 & LIMB_MASK as u64;
+    d3 += c;
+    c = d3 >> 26;
+    let h3 = d3 & LIMB_MASK as u64;
+    d4 += c;
+    c = d4 >> 26;
+    let h4 = d4 & LIMB_MASK as u64;
+    let h0 = h0 + c * 5;
+    c = h0 >> 26;
+    let h0 = h0 & LIMB_MASK as u64;
+    let h1 = h1 + c;
+    [h0 as u32, h1 as u32, h2 as u32, h3 as u32, h4 as u32]
+}
+
+/// Partially reduces `h`, whose limbs may be well beyond 26 bits
+/// (as happens when summing the lanes of a vectorized accumulator).
+#[cfg_attr(
+    not(any(
+        all(
+            any(target_arch = "x86_64", target_arch = "x86"),
+            target_feature = "avx2"
+        ),
+        all(
+            any(target_arch = "aarch64", target_arch = "arm64ec"),
+            target_feature = "neon"
+        ),
+    )),
+    allow(dead_code)
+)]
+#[inline(always)]
+pub fn carry(h: [u64; 5]) -> [u32; 5] {
+    let [h0, h1, h2, h3, h4] = h;
+    let mut c = h0 >> 26;
+    let h0 = h0 & LIMB_MASK as u64;
+    let h1 = h1 + c;
+    c = h1 >> 26;
+    let h1 = h1 & LIMB_MASK as u64;
+    let h2 = h2 + c;
+    c = h2 >> 26;
+    let h2 = h2 & LIMB_MASK as u64;
+    let h3 = h3 + c;
+    c = h3 >> 26;
+    let h3 = h3 & LIMB_MASK as u64;
+    let h4 = h4 + c;
+    c = h4 >> 26;
+    let h4 = h4 & LIMB_MASK as u64;
+    let h0 = h0 + c * 5;
+    c = h0 >> 26;
+    let h0 = h0 & LIMB_MASK as u64;
+    let h1 = h1 + c;
+    [h0 as u32, h1 as u32, h2 as u32, h3 as u32, h4 as u32]
+}
@@ -0,0 +1,48 @@
+/*!
+Module containing [`quic_hp_mask`], the ChaCha20 based header protection of [QUIC].
+
+QUIC encrypts packet payloads with ChaCha20-Poly1305, then masks parts of the header (the packet
+number and some of the flags) using a separate header protection key. The mask is the first 5
+bytes of ChaCha20 keystream, with the counter and nonce taken from a 16-byte sample of the
+encrypted payload: the first 4 bytes are the (little-endian) block counter, and the remaining 12
+are the nonce.
+
+[QUIC]: https://datatracker.ietf.org/doc/html/rfc9001#section-5.4.4
+*/
+
+use crate::ChaCha;
+use crate::rounds::R20;
+use crate::util::le_words;
+use crate::variations::Ietf;
+
+/// Size (in 8-bit integers) of a QUIC header protection key.
+pub const QUIC_HP_KEY_LEN: usize = 32;
+/// Size (in 8-bit integers) of the ciphertext sample used for header protection.
+pub const QUIC_HP_SAMPLE_LEN: usize = 16;
+/// Size (in 8-bit integers) of a header protection mask.
+pub const QUIC_HP_MASK_LEN: usize = 5;
+
+/// Computes the header protection mask for `sample` using the header protection `key`.
+///
+/// The first byte masks the low bits of the first header byte (4 bits for long headers,
+/// 5 for short headers), and the rest mask the packet number.
+///
+/// ```
+/// use chachacha::quic_hp_mask;
+///
+/// let mask = quic_hp_mask(&[69; 32], &[0; 16]);
+/// let mut packet_number = [0x12, 0x34];
+/// packet_number.iter_mut().zip(&mask[1..]).for_each(|(p, m)| *p ^= m);
+/// ```
+#[inline]
+pub fn quic_hp_mask(
+    key: &[u8; QUIC_HP_KEY_LEN],
+    sample: &[u8; QUIC_HP_SAMPLE_LEN],
+) -> [u8; QUIC_HP_MASK_LEN] {
+    let [counter, n0, n1, n2] = le_words(sample);
+    let mut key = le_words(key);
+    let mut mask = [0; QUIC_HP_MASK_LEN];
+    ChaCha::<R20, Ietf>::new(key, counter as u64, [n0, n1, n2]).fill(&mut mask);
+    key.fill(0);
+    mask
+}
@@ -0,0 +1,96 @@
+/*!
+Module containing [`encrypt_with_random_nonce`] and [`decrypt_with_random_nonce`], which take care
+of nonce management entirely, for applications that just want to encrypt some data with a key.
+
+Encryption uses XChaCha20-Poly1305 (as specified in [draft-irtf-cfrg-xchacha]), whose 192-bit
+nonces are large enough to be chosen at random without any realistic chance of ever repeating.
+A fresh nonce is pulled from the operating system's entropy source for every message and stored
+in front of the ciphertext, so the output is laid out as `nonce || ciphertext || tag`.
+
+[draft-irtf-cfrg-xchacha]: https://datatracker.ietf.org/doc/html/draft-irtf-cfrg-xchacha-03
+*/
+
+use crate::Error;
+use crate::chacha_poly1305::*;
+use crate::poly1305::POLY1305_TAG_LEN;
+use alloc::vec::Vec;
+
+/// Size (in 8-bit integers) of the nonce stored in front of the ciphertext.
+pub const RANDOM_NONCE_LEN: usize = XAEAD_NONCE_LEN;
+/// Amount (in 8-bit integers) by which the output of [`encrypt_with_random_nonce`]
+/// is larger than the plaintext.
+pub const RANDOM_NONCE_OVERHEAD: usize = RANDOM_NONCE_LEN + POLY1305_TAG_LEN;
+
+/// Encrypts `plaintext` under `key` with a random nonce, authenticating it and `aad`.
+///
+/// Returns `nonce || ciphertext || tag`, which can be passed to [`decrypt_with_random_nonce`].
+///
+/// # Panics
+///
+/// If the operating system is unable to provide entropy, or `plaintext`
+/// is longer than [`AEAD_MAX_MESSAGE_LEN`].
+///
+/// ```
+/// use chachacha::{decrypt_with_random_nonce, encrypt_with_random_nonce};
+///
+/// let key = [69; 32];
+/// let sealed = encrypt_with_random_nonce(&key, b"user 42", b"hello world");
+/// let opened = decrypt_with_random_nonce(&key, b"user 42", &sealed).unwrap();
+/// assert_eq!(opened, b"hello world");
+/// ```
+#[cfg(feature = "getrandom")]
+#[inline]
+pub fn encrypt_with_random_nonce(
+    key: &[u8; AEAD_KEY_LEN],
+    aad: &[u8],
+    plaintext: &[u8],
+) -> Vec<u8> {
+    try_encrypt_with_random_nonce(key, aad, plaintext)
+        .expect("unable to generate nonce from OS entropy, or plaintext too long")
+}
+
+/// Encrypts `plaintext` under `key` with a random nonce, authenticating it and `aad`.
+///
+/// Returns `nonce || ciphertext || tag`, which can be passed to [`decrypt_with_random_nonce`].
+///
+/// Returns [`Error::InvalidLength`] if `plaintext` is longer than [`AEAD_MAX_MESSAGE_LEN`].
+#[cfg(feature = "getrandom")]
+#[inline]
+pub fn try_encrypt_with_random_nonce(
+    key: &[u8; AEAD_KEY_LEN],
+    aad: &[u8],
+    plaintext: &[u8],
+) -> Result<Vec<u8>, Error> {
+    let mut nonce = [0; RANDOM_NONCE_LEN];
+    getrandom::fill(&mut nonce)?;
+    let mut result = Vec::with_capacity(plaintext.len() + RANDOM_NONCE_OVERHEAD);
+    result.extend_from_slice(&nonce);
+    result.extend_from_slice(plaintext);
+    let (aead, nonce) = xchacha20_poly1305(key, &nonce);
+    let tag = aead.encrypt_detached(&nonce, aad, &mut result[RANDOM_NONCE_LEN..])?;
+    result.extend_from_slice(&tag);
+    Ok(result)
+}
+
+/// Authenticates the output of [`encrypt_with_random_nonce`] and `aad` using `key`,
+/// returning the plaintext if successful.
+///
+/// Returns [`Error::InvalidLength`] if `sealed` is too short to contain a nonce and tag,
+/// and [`Error::TagMismatch`] if authentication fails.
+#[inline]
+pub fn decrypt_with_random_nonce(
+    key: &[u8; AEAD_KEY_LEN],
+    aad: &[u8],
+    sealed: &[u8],
+) -> Result<Vec<u8>, Error> {
+    let (nonce, rest) = sealed
+        .split_first_chunk::<RANDOM_NONCE_LEN>()
+        .ok_or(Error::InvalidLength)?;
+    let (ciphertext, tag) = rest
+        .split_last_chunk::<POLY1305_TAG_LEN>()
+        .ok_or(Error::InvalidLength)?;
+    let mut result = ciphertext.to_vec();
+    let (aead, nonce) = xchacha20_poly1305(key, nonce);
+    aead.decrypt_detached(&nonce, aad, &mut result, tag)?;
+    Ok(result)
+}
@@ -0,0 +1,197 @@
+/*!
+Module containing the [`Read`] adapters: [`KeystreamReader`], which exposes the output of a
+ChaCha instance through [`Read`] and [`BufRead`], and [`DecryptingReader`], which decrypts
+whatever is read from another reader.
+
+The keystream never ends, so reads from a `KeystreamReader` always succeed and fill as much of
+the destination as they can. Output is generated a batch at a time into an internal buffer, which
+[`BufRead`] hands out directly, while reads of at least a whole batch skip the buffer and go
+straight through the ChaCha instance.
+
+A `DecryptingReader` works in place on the caller's buffer instead, advancing the keystream by
+exactly as many bytes as the inner reader produced, so reads of any size line up byte for byte.
+*/
+
+use crate::ChaCha;
+use crate::rounds::*;
+use crate::util::*;
+use crate::variations::*;
+use std::io::{BufRead, Read, Result};
+
+/// Infinite reader over the keystream of a ChaCha instance with `R` rounds of the `V` variant.
+///
+/// ```
+/// use chachacha::{ChaCha20Djb, KeystreamReader};
+/// use std::io::Read;
+///
+/// let mut expected = [0; 1000];
+/// ChaCha20Djb::new([69; 8], 0, [0; 3]).fill(&mut expected);
+///
+/// let reader = KeystreamReader::new(ChaCha20Djb::new([69; 8], 0, [0; 3]));
+/// let mut actual = Vec::new();
+/// reader.take(1000).read_to_end(&mut actual).unwrap();
+/// assert_eq!(actual, expected);
+/// ```
+pub struct KeystreamReader<R, V> {
+    core: ChaCha<R, V>,
+    buf: [u8; BUF_LEN_U8],
+    index: usize,
+}
+
+impl<R, V> KeystreamReader<R, V>
+where
+    R: DoubleRounds,
+    V: Variant,
+{
+    /// Creates a new `KeystreamReader` which continues from the current position of `core`.
+    #[inline]
+    pub fn new(mut core: ChaCha<R, V>) -> Self {
+        let mut buf = [0; BUF_LEN_U8];
+        let offset = (core.position() % MATRIX_SIZE_U8 as u128) as usize;
+        if offset != 0 {
+            // `core` was sought into the middle of a block, so buffer from the start of it.
+            core.seek(core.position() - offset as u128);
+            core.fill(&mut buf);
+        }
+        Self {
+            core,
+            buf,
+            index: if offset != 0 { offset } else { BUF_LEN_U8 },
+        }
+    }
+
+    /// Returns the position (in bytes) in the keystream that the next read will start at.
+    #[inline]
+    pub fn position(&self) -> u128 {
+        self.core.position() - (BUF_LEN_U8 - self.index) as u128
+    }
+
+    /// Consumes `self`, returning the underlying ChaCha instance positioned
+    /// exactly where the next read would have started.
+    #[inline]
+    pub fn into_inner(mut self) -> ChaCha<R, V> {
+        let position = self.position();
+        self.core.seek(position);
+        self.buf.fill(0);
+        self.core
+    }
+}
+
+/// Reader which xors the data read from `I` with the keystream of a ChaCha
+/// instance with `R` rounds of the `V` variant.
+///
+/// ```
+/// use chachacha::{ChaCha20Ietf, DecryptingReader};
+/// use std::io::Read;
+///
+/// let mut ciphertext = *b"attack at dawn, unless it rains";
+/// ChaCha20Ietf::new([69; 8], 1, [7; 3]).xor(&mut ciphertext);
+///
+/// let chacha = ChaCha20Ietf::new([69; 8], 1, [7; 3]);
+/// let mut reader = DecryptingReader::new(&ciphertext[..], chacha);
+/// let mut plaintext = String::new();
+/// reader.read_to_string(&mut plaintext).unwrap();
+/// assert_eq!(plaintext, "attack at dawn, unless it rains");
+/// ```
+pub struct DecryptingReader<I, R, V> {
+    inner: I,
+    core: ChaCha<R, V>,
+}
+
+impl<I, R, V> DecryptingReader<I, R, V>
+where
+    I: Read,
+    R: DoubleRounds,
+    V: Variant,
+{
+    /// Creates a new `DecryptingReader` instance, which reads from `inner`
+    /// starting from the current position of `core`.
+    #[inline]
+    pub fn new(inner: I, core: ChaCha<R, V>) -> Self {
+        Self { inner, core }
+    }
+
+    /// Returns the position (in bytes) in the keystream that the next read will start at.
+    #[inline]
+    pub fn position(&self) -> u128 {
+        self.core.position()
+    }
+
+    /// Returns a reference to the inner reader.
+    #[inline]
+    pub fn get_ref(&self) -> &I {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the inner reader.
+    ///
+    /// Anything read directly from it bypasses the decryption.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut I {
+        &mut self.inner
+    }
+
+    /// Consumes `self`, returning the inner reader along with the ChaCha
+    /// instance positioned exactly where the next read would have started.
+    #[inline]
+    pub fn into_parts(self) -> (I, ChaCha<R, V>) {
+        (self.inner, self.core)
+    }
+}
+
+impl<I, R, V> Read for DecryptingReader<I, R, V>
+where
+    I: Read,
+    R: DoubleRounds,
+    V: Variant,
+{
+    #[inline]
+    fn read(&mut self, dst: &mut [u8]) -> Result<usize> {
+        let len = self.inner.read(dst)?;
+        let start = self.core.position();
+        self.core.xor(&mut dst[..len]);
+        // `xor` always finishes on a block boundary, so step back to the exact byte.
+        self.core.seek(start + len as u128);
+        Ok(len)
+    }
+}
+
+impl<R, V> Read for KeystreamReader<R, V>
+where
+    R: DoubleRounds,
+    V: Variant,
+{
+    #[inline]
+    fn read(&mut self, dst: &mut [u8]) -> Result<usize> {
+        if self.index == BUF_LEN_U8 && dst.len() >= BUF_LEN_U8 {
+            let len = dst.len() - dst.len() % BUF_LEN_U8;
+            self.core.fill(&mut dst[..len]);
+            return Ok(len);
+        }
+        let src = self.fill_buf()?;
+        let len = dst.len().min(src.len());
+        dst[..len].copy_from_slice(&src[..len]);
+        self.consume(len);
+        Ok(len)
+    }
+}
+
+impl<R, V> BufRead for KeystreamReader<R, V>
+where
+    R: DoubleRounds,
+    V: Variant,
+{
+    #[inline]
+    fn fill_buf(&mut self) -> Result<&[u8]> {
+        if self.index == BUF_LEN_U8 {
+            self.core.fill(&mut self.buf);
+            self.index = 0;
+        }
+        Ok(&self.buf[self.index..])
+    }
+
+    #[inline]
+    fn consume(&mut self, amt: usize) {
+        self.index = (self.index + amt).min(BUF_LEN_U8);
+    }
+}
@@ -0,0 +1,144 @@
+/*!
+Module containing the [`Reseeding`] wrapper, which periodically throws away the seed of a
+ChaCha instance and replaces it with fresh entropy from the operating system.
+
+When the `std` feature is enabled on unix targets, the wrapper also remembers the id of the
+process it was seeded in. If it ever finds itself running in a different process (meaning
+someone called `fork()` while it was alive), it reseeds before producing any more output, so
+parent and child never share a keystream.
+*/
+
+use crate::rounds::*;
+use crate::variations::*;
+use crate::{ChaCha, Error};
+
+/// Wrapper around a ChaCha instance that transparently reseeds it from OS
+/// entropy after a configurable amount of output has been produced.
+///
+/// ```
+/// use chachacha::{ChaCha12Djb, Reseeding};
+///
+/// // Reseed after every 64 KiB of output.
+/// let mut rng = Reseeding::<ChaCha12Djb>::new(1 << 16);
+/// let mut buf = [0; 1000];
+/// rng.fill(&mut buf);
+/// assert!(buf.iter().any(|&v| v != 0));
+/// ```
+pub struct Reseeding<C> {
+    pub(crate) core: C,
+    threshold: u64,
+    pub(crate) remaining: u64,
+    #[cfg(all(feature = "std", unix))]
+    pid: u32,
+}
+
+impl<R, V> Reseeding<ChaCha<R, V>>
+where
+    R: DoubleRounds,
+    V: Variant,
+{
+    /// Creates a new `Reseeding` instance which will reseed itself after
+    /// every `threshold` bytes of output.
+    ///
+    /// A `threshold` of 0 is treated as 1.
+    ///
+    /// # Panics
+    ///
+    /// If the operating system is unable to provide entropy.
+    #[inline]
+    pub fn new(threshold: u64) -> Self {
+        Self::try_new(threshold).expect("unable to seed from OS entropy")
+    }
+
+    /// Creates a new `Reseeding` instance which will reseed itself after
+    /// every `threshold` bytes of output.
+    ///
+    /// A `threshold` of 0 is treated as 1.
+    #[inline]
+    pub fn try_new(threshold: u64) -> Result<Self, Error> {
+        let threshold = threshold.max(1);
+        Ok(Self {
+            core: ChaCha::try_from_entropy()?,
+            threshold,
+            remaining: threshold,
+            #[cfg(all(feature = "std", unix))]
+            pid: std::process::id(),
+        })
+    }
+
+    /// Returns the amount of output (in bytes) between reseeds.
+    #[inline]
+    pub fn threshold(&self) -> u64 {
+        self.threshold
+    }
+
+    /// Immediately replaces the current seed with fresh OS entropy.
+    ///
+    /// # Panics
+    ///
+    /// If the operating system is unable to provide entropy.
+    #[inline]
+    pub fn reseed(&mut self) {
+        self.try_reseed().expect("unable to reseed from OS entropy");
+    }
+
+    /// Immediately replaces the current seed with fresh OS entropy.
+    ///
+    /// If this fails the current seed is left untouched, but the wrapper will
+    /// attempt to reseed again before producing any further output.
+    #[inline]
+    pub fn try_reseed(&mut self) -> Result<(), Error> {
+        self.remaining = 0;
+        self.core = ChaCha::try_from_entropy()?;
+        self.remaining = self.threshold;
+        #[cfg(all(feature = "std", unix))]
+        {
+            self.pid = std::process::id();
+        }
+        Ok(())
+    }
+
+    /// Fills `dst` with bytes from the output of `self`, reseeding as many times
+    /// as needed to ensure no single seed produces more than `threshold` bytes.
+    ///
+    /// # Panics
+    ///
+    /// If a reseed is required and the operating system is unable to provide entropy.
+    #[inline]
+    pub fn fill(&mut self, dst: &mut [u8]) {
+        self.slice::<false>(dst);
+    }
+
+    /// Xors `dst` with bytes from the output of `self`, reseeding as many times
+    /// as needed to ensure no single seed produces more than `threshold` bytes.
+    ///
+    /// # Panics
+    ///
+    /// If a reseed is required and the operating system is unable to provide entropy.
+    #[inline]
+    pub fn xor(&mut self, dst: &mut [u8]) {
+        self.slice::<true>(dst);
+    }
+
+    #[inline]
+    fn slice<const XOR: bool>(&mut self, mut dst: &mut [u8]) {
+        #[cfg(all(feature = "std", unix))]
+        if self.pid != std::process::id() {
+            self.reseed();
+        }
+        while !dst.is_empty() {
+            if self.remaining == 0 {
+                self.reseed();
+            }
+            let len = (dst.len() as u64).min(self.remaining) as usize;
+            let (cur, rest) = dst.split_at_mut(len);
+            if XOR {
+                self.core.xor(cur);
+            } else {
+                self.core.fill(cur);
+            }
+            self.remaining -= len as u64;
+            dst = rest;
+        }
+    }
+}
@@ -1,22 +1,60 @@
 /*!
-Module containing the standard ChaCha round counts.
+Module containing the standard ChaCha round counts, along with [`Rounds`] for any other.
 */
 
+/// Amount of rounds performed by a ChaCha instance.
+///
+/// Together with [`Variant`](crate::Variant), this allows writing code once for every
+/// standard configuration instead of once per type alias:
+///
+/// ```
+/// use chachacha::{ChaChaCore, DoubleRounds, Soft, Variant};
+/// use chachacha::{ChaCha8Ietf, ChaCha20Djb, Djb, Ietf, R8, R20};
+///
+/// fn first_word<R: DoubleRounds, V: Variant>(key: [u8; 32]) -> u32 {
+///     ChaChaCore::<Soft, R, V>::from_key(key).next_u32()
+/// }
+///
+/// assert_eq!(first_word::<R20, Djb>([7; 32]), ChaCha20Djb::from_key([7; 32]).next_u32());
+/// assert_eq!(first_word::<R8, Ietf>([7; 32]), ChaCha8Ietf::from_key([7; 32]).next_u32());
+/// ```
 pub trait DoubleRounds {
+    /// Amount of double rounds (so half the amount of rounds).
     const COUNT: usize;
 }
 
+/// 4 rounds.
+pub struct R4;
+impl DoubleRounds for R4 {
+    const COUNT: usize = 2;
+}
+
+/// 6 rounds.
+pub struct R6;
+impl DoubleRounds for R6 {
+    const COUNT: usize = 3;
+}
+
+/// 8 rounds.
 pub struct R8;
 impl DoubleRounds for R8 {
     const COUNT: usize = 4;
 }
 
+/// 12 rounds.
 pub struct R12;
 impl DoubleRounds for R12 {
     const COUNT: usize = 6;
 }
 
+/// 20 rounds.
 pub struct R20;
 impl DoubleRounds for R20 {
     const COUNT: usize = 10;
 }
+
+/// Round count of `DOUBLE_ROUNDS` double rounds, for when none of the standard ones will do.
+pub struct Rounds<const DOUBLE_ROUNDS: usize>;
+impl<const DOUBLE_ROUNDS: usize> DoubleRounds for Rounds<DOUBLE_ROUNDS> {
+    const COUNT: usize = DOUBLE_ROUNDS;
+}
@@ -0,0 +1,215 @@
+/*!
+Module containing [`Salsa`], a [`Machine`] implementing Salsa20 (the predecessor of ChaCha), which
+allows the [`Salsa20`](crate::Salsa20) and [`XSalsa20`](crate::XSalsa20) types to share all of
+the `ChaChaCore` machinery.
+
+Salsa arranges its 16 integers differently from ChaCha. The constants run along the diagonal,
+the key is split between the first and third rows, and the nonce and counter sit in the middle:
+
+```text
+"expa"   Key      Key      Key
+Key      "nd 3"   Nonce    Nonce
+Counter  Counter  "2-by"   Key
+Key      Key      Key      "te k"
+```
+
+The machine is stored lane-major (each of the 16 integers holds all `DEPTH` instances side by
+side), so every step of the quarter round is a plain element-wise operation across the lanes.
+The compiler turns those into whatever SIMD instructions the target supports, which keeps this
+portable without giving up the batched performance of the ChaCha backends.
+*/
+
+use crate::rounds::*;
+use crate::util::*;
+use core::ops::Add;
+
+type Lanes = [u32; DEPTH];
+
+#[derive(Clone)]
+pub struct Salsa {
+    state: [Lanes; MATRIX_SIZE_U32],
+}
+
+impl Add for Salsa {
+    type Output = Self;
+
+    #[inline]
+    fn add(mut self, rhs: Self) -> Self::Output {
+        for (a, b) in self.state.iter_mut().zip(rhs.state) {
+            for i in 0..DEPTH {
+                a[i] = a[i].wrapping_add(b[i]);
+            }
+        }
+        self
+    }
+}
+
+impl Salsa {
+    /// Broadcasts `matrix` to every lane.
+    #[inline]
+    fn broadcast(matrix: [u32; MATRIX_SIZE_U32]) -> Self {
+        Self {
+            state: matrix.map(|value| [value; DEPTH]),
+        }
+    }
+
+    #[inline(always)]
+    fn step(&mut self, dst: usize, a: usize, b: usize, rotation: u32) {
+        for i in 0..DEPTH {
+            let sum = self.state[a][i].wrapping_add(self.state[b][i]);
+            self.state[dst][i] ^= sum.rotate_left(rotation);
+        }
+    }
+
+    #[inline(always)]
+    fn quarter_round(&mut self, a: usize, b: usize, c: usize, d: usize) {
+        self.step(b, a, d, 7);
+        self.step(c, b, a, 9);
+        self.step(d, c, b, 13);
+        self.step(a, d, c, 18);
+    }
+}
+
+impl Machine for Salsa {
+    #[inline]
+    fn new_djb(state: &ChaChaNaked) -> Self {
+        let mut result = Self::broadcast(matrix(state));
+        for i in 1..DEPTH {
+            let counter = (result.state[8][i] as u64 | (result.state[9][i] as u64) << 32)
+                .wrapping_add(i as u64);
+            result.state[8][i] = counter as u32;
+            result.state[9][i] = (counter >> 32) as u32;
+        }
+        result
+    }
+
+    #[inline]
+    fn new_ietf(state: &ChaChaNaked) -> Self {
+        let mut result = Self::broadcast(matrix(state));
+        for i in 1..DEPTH {
+            result.state[8][i] = result.state[8][i].wrapping_add(i as u32);
+        }
+        result
+    }
+
+    #[inline]
+    fn increment_djb(&mut self) {
+        for i in 0..DEPTH {
+            let counter = (self.state[8][i] as u64 | (self.state[9][i] as u64) << 32)
+                .wrapping_add(DEPTH as u64);
+            self.state[8][i] = counter as u32;
+            self.state[9][i] = (counter >> 32) as u32;
+        }
+    }
+
+    #[inline]
+    fn increment_ietf(&mut self) {
+        for i in 0..DEPTH {
+            self.state[8][i] = self.state[8][i].wrapping_add(DEPTH as u32);
+        }
+    }
+
+    #[inline]
+    fn double_round(&mut self) {
+        // Column rounds
+        self.quarter_round(0, 4, 8, 12);
+        self.quarter_round(5, 9, 13, 1);
+        self.quarter_round(10, 14, 2, 6);
+        self.quarter_round(15, 3, 7, 11);
+        // Row rounds
+        self.quarter_round(0, 1, 2, 3);
+        self.quarter_round(5, 6, 7, 4);
+        self.quarter_round(10, 11, 8, 9);
+        self.quarter_round(15, 12, 13, 14);
+    }
+
+    #[inline]
+    fn subkey<R: DoubleRounds>(key: &[u32; 8], input: &[u32; 4]) -> [u32; 8] {
+        hsalsa::<R>(key, input)
+    }
+
+    #[inline]
+    fn single_block<R: DoubleRounds>(state: &ChaChaNaked) -> [u32; MATRIX_SIZE_U32] {
+        let mut initial = matrix(state);
+        let mut result = initial;
+        for _ in 0..R::COUNT {
+            // Column rounds
+            quarter_round(&mut result, 0, 4, 8, 12);
+            quarter_round(&mut result, 5, 9, 13, 1);
+            quarter_round(&mut result, 10, 14, 2, 6);
+            quarter_round(&mut result, 15, 3, 7, 11);
+            // Row rounds
+            quarter_round(&mut result, 0, 1, 2, 3);
+            quarter_round(&mut result, 5, 6, 7, 4);
+            quarter_round(&mut result, 10, 11, 8, 9);
+            quarter_round(&mut result, 15, 12, 13, 14);
+        }
+        result
+            .iter_mut()
+            .zip(initial)
+            .for_each(|(a, b)| *a = a.wrapping_add(b));
+        initial.fill(0);
+        result
+    }
+
+    #[inline]
+    fn fetch_result(self, buf: &mut [u8; BUF_LEN_U8]) {
+        for (i, block) in buf.chunks_exact_mut(MATRIX_SIZE_U8).enumerate() {
+            for (dst, lanes) in block.chunks_exact_mut(size_of::<u32>()).zip(&self.state) {
+                dst.copy_from_slice(&lanes[i].to_le_bytes());
+            }
+        }
+    }
+}
+
+/// Scalar version of [`Salsa::quarter_round`], for computing a single block.
+#[inline(always)]
+fn quarter_round(matrix: &mut [u32; MATRIX_SIZE_U32], a: usize, b: usize, c: usize, d: usize) {
+    matrix[b] ^= matrix[a].wrapping_add(matrix[d]).rotate_left(7);
+    matrix[c] ^= matrix[b].wrapping_add(matrix[a]).rotate_left(9);
+    matrix[d] ^= matrix[c].wrapping_add(matrix[b]).rotate_left(13);
+    matrix[a] ^= matrix[d].wrapping_add(matrix[c]).rotate_left(18);
+}
+
+/// Lays the rows of `state` out as a Salsa matrix.
+///
+/// The last row holds the counter and nonce exactly like ChaCha, so both
+/// variants work unmodified: [`Djb`](crate::variations::Djb) ends up with a
+/// 64-bit counter in the third row, while [`Ietf`](crate::variations::Ietf)
+/// has a 32-bit counter followed by a 96-bit nonce.
+#[inline]
+fn matrix(state: &ChaChaNaked) -> [u32; MATRIX_SIZE_U32] {
+    let [c0, c1, c2, c3] = unsafe { state.row_a.u32x4 };
+    let [k0, k1, k2, k3] = unsafe { state.row_b.u32x4 };
+    let [k4, k5, k6, k7] = unsafe { state.row_c.u32x4 };
+    let [d0, d1, d2, d3] = unsafe { state.row_d.u32x4 };
+    [
+        c0, k0, k1, k2, //
+        k3, c1, d2, d3, //
+        d0, d1, c2, k4, //
+        k5, k6, k7, c3, //
+    ]
+}
+
+/// Computes HSalsa with `R` rounds over `key` and `input`, returning the
+/// diagonal and middle of the permuted matrix (without the feed-forward addition).
+#[inline]
+pub(crate) fn hsalsa<R: DoubleRounds>(key: &[u32; 8], input: &[u32; 4]) -> [u32; 8] {
+    let naked = ChaChaNaked {
+        row_a: ROW_A,
+        row_b: Row {
+            u32x4: [key[0], key[1], key[2], key[3]],
+        },
+        row_c: Row {
+            u32x4: [key[4], key[5], key[6], key[7]],
+        },
+        row_d: Row {
+            u32x4: [input[2], input[3], input[0], input[1]],
+        },
+    };
+    let mut salsa = Salsa::broadcast(matrix(&naked));
+    for _ in 0..R::COUNT {
+        salsa.double_round();
+    }
+    [0, 5, 10, 15, 6, 7, 8, 9].map(|i| salsa.state[i][0])
+}
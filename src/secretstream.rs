@@ -0,0 +1,305 @@
+/*!
+Module containing [`SecretStreamPush`] and [`SecretStreamPull`], a port of libsodium's
+[`crypto_secretstream_xchacha20poly1305`] which is byte-for-byte compatible with it.
+
+A stream is a sequence of messages, each encrypted with (a slight variation of) ChaCha20-Poly1305
+under a key and nonce that are derived from the previous message (starting from a 24-byte header
+and an XChaCha style HChaCha20 subkey). Messages therefore can't be reordered, duplicated, or
+dropped without detection, and every message carries an encrypted [`SecretStreamTag`] so the end
+of the stream is authenticated as well. A stream which doesn't finish with
+[`SecretStreamTag::FINAL`] has been truncated.
+
+Each encrypted message is laid out as `tag (1 byte) || ciphertext || mac (16 bytes)`, which is
+[`SECRETSTREAM_ABYTES`] longer than the plaintext.
+
+[`crypto_secretstream_xchacha20poly1305`]: https://doc.libsodium.org/secret-key_cryptography/secretstream
+*/
+
+use crate::chacha_poly1305::finish;
+use crate::hchacha::hchacha;
+use crate::poly1305::*;
+use crate::rounds::*;
+use crate::util::le_words;
+use crate::variations::*;
+use crate::{ChaCha, Error};
+
+/// Size (in 8-bit integers) of a secretstream key.
+pub const SECRETSTREAM_KEY_LEN: usize = 32;
+/// Size (in 8-bit integers) of a secretstream header.
+pub const SECRETSTREAM_HEADER_LEN: usize = 24;
+/// Size (in 8-bit integers) an encrypted message has over its plaintext.
+pub const SECRETSTREAM_ABYTES: usize = 1 + POLY1305_TAG_LEN;
+
+/// Tag attached to every message of a secretstream.
+///
+/// Tags are bit flags (exactly like in libsodium), so [`Self::FINAL`]
+/// is the combination of [`Self::PUSH`] and [`Self::REKEY`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SecretStreamTag(pub u8);
+
+impl SecretStreamTag {
+    /// An ordinary message.
+    pub const MESSAGE: Self = Self(0);
+    /// Marks the end of a set of messages, without ending the stream.
+    pub const PUSH: Self = Self(1);
+    /// Forces the key to be replaced after this message.
+    pub const REKEY: Self = Self(2);
+    /// Marks the end of the stream.
+    pub const FINAL: Self = Self(Self::PUSH.0 | Self::REKEY.0);
+}
+
+/// Encrypting half of a secretstream.
+///
+/// ```
+/// use chachacha::{SECRETSTREAM_ABYTES, SecretStreamPull, SecretStreamPush, SecretStreamTag};
+///
+/// let key = [69; 32];
+/// let header = [42; 24];
+/// let mut push = SecretStreamPush::with_header(&key, &header);
+/// let mut first = [0; 5 + SECRETSTREAM_ABYTES];
+/// push.push(b"hello", &[], SecretStreamTag::MESSAGE, &mut first);
+/// let mut last = [0; 5 + SECRETSTREAM_ABYTES];
+/// push.push(b"world", &[], SecretStreamTag::FINAL, &mut last);
+///
+/// let mut pull = SecretStreamPull::new(&key, &header);
+/// let mut msg = [0; 5];
+/// assert_eq!(pull.pull(&first, &[], &mut msg), Ok(SecretStreamTag::MESSAGE));
+/// assert_eq!(&msg, b"hello");
+/// assert_eq!(pull.pull(&last, &[], &mut msg), Ok(SecretStreamTag::FINAL));
+/// assert_eq!(&msg, b"world");
+/// ```
+pub struct SecretStreamPush {
+    state: State,
+}
+
+impl SecretStreamPush {
+    /// Creates a new `SecretStreamPush` instance using `key` and a random header,
+    /// returning the header which must be sent ahead of the stream.
+    ///
+    /// # Panics
+    ///
+    /// If the operating system is unable to provide entropy.
+    #[cfg(feature = "getrandom")]
+    #[inline]
+    pub fn new(key: &[u8; SECRETSTREAM_KEY_LEN]) -> (Self, [u8; SECRETSTREAM_HEADER_LEN]) {
+        Self::try_new(key).expect("unable to seed from OS entropy")
+    }
+
+    /// Creates a new `SecretStreamPush` instance using `key` and a random header,
+    /// returning the header which must be sent ahead of the stream.
+    #[cfg(feature = "getrandom")]
+    #[inline]
+    pub fn try_new(
+        key: &[u8; SECRETSTREAM_KEY_LEN],
+    ) -> Result<(Self, [u8; SECRETSTREAM_HEADER_LEN]), Error> {
+        let mut header = [0; SECRETSTREAM_HEADER_LEN];
+        getrandom::fill(&mut header)?;
+        Ok((Self::with_header(key, &header), header))
+    }
+
+    /// Creates a new `SecretStreamPush` instance using `key` and `header`.
+    ///
+    /// **A header must never be used for more than one stream under the same key,**
+    /// so unless you have a very good reason prefer the random header of `new`.
+    #[inline]
+    pub fn with_header(
+        key: &[u8; SECRETSTREAM_KEY_LEN],
+        header: &[u8; SECRETSTREAM_HEADER_LEN],
+    ) -> Self {
+        Self {
+            state: State::new(key, header),
+        }
+    }
+
+    /// Encrypts `msg` along with `tag`, authenticating `aad` as well, into `out`.
+    ///
+    /// # Panics
+    ///
+    /// If the length of `out` isn't exactly `msg.len() + SECRETSTREAM_ABYTES`.
+    #[inline]
+    pub fn push(&mut self, msg: &[u8], aad: &[u8], tag: SecretStreamTag, out: &mut [u8]) {
+        assert_eq!(
+            out.len(),
+            msg.len() + SECRETSTREAM_ABYTES,
+            "`out` must be exactly `SECRETSTREAM_ABYTES` longer than `msg`"
+        );
+        let (mut chacha, mut poly) = self.state.init(aad);
+        let mut block = [0; 64];
+        block[0] = tag.0;
+        chacha.xor(&mut block);
+        poly.update(&block);
+
+        let (head, rest) = out.split_first_mut().unwrap();
+        let (ciphertext, mac) = rest.split_at_mut(msg.len());
+        *head = block[0];
+        ciphertext.copy_from_slice(msg);
+        chacha.xor(ciphertext);
+        poly.update(ciphertext);
+        poly.update(&[0; 16][..quirky_padding(msg.len())]);
+        let computed = finish(poly, aad.len() as u64, (block.len() + msg.len()) as u64).finalize();
+        mac.copy_from_slice(&computed);
+        self.state.advance(&computed, tag);
+    }
+
+    /// Replaces the key, exactly as if the last message had [`SecretStreamTag::REKEY`].
+    ///
+    /// Must be matched by a call to [`SecretStreamPull::rekey`] at the same point in the stream.
+    #[inline]
+    pub fn rekey(&mut self) {
+        self.state.rekey();
+    }
+}
+
+/// Decrypting half of a secretstream.
+///
+/// See [`SecretStreamPush`] for an example.
+pub struct SecretStreamPull {
+    state: State,
+}
+
+impl SecretStreamPull {
+    /// Creates a new `SecretStreamPull` instance using `key` and the `header`
+    /// sent ahead of the stream.
+    #[inline]
+    pub fn new(key: &[u8; SECRETSTREAM_KEY_LEN], header: &[u8; SECRETSTREAM_HEADER_LEN]) -> Self {
+        Self {
+            state: State::new(key, header),
+        }
+    }
+
+    /// Authenticates the encrypted message `input` along with `aad`, and if
+    /// successful decrypts it into `out` and returns its tag.
+    ///
+    /// Returns [`Error::InvalidLength`] if `input` is shorter than `SECRETSTREAM_ABYTES`,
+    /// and [`Error::TagMismatch`] if authentication fails. In both cases neither `out`
+    /// nor the state of the stream are modified.
+    ///
+    /// # Panics
+    ///
+    /// If `input` is valid but the length of `out` isn't exactly
+    /// `input.len() - SECRETSTREAM_ABYTES`.
+    #[inline]
+    pub fn pull(
+        &mut self,
+        input: &[u8],
+        aad: &[u8],
+        out: &mut [u8],
+    ) -> Result<SecretStreamTag, Error> {
+        let Some((head, rest)) = input.split_first() else {
+            return Err(Error::InvalidLength);
+        };
+        let Some((ciphertext, mac)) = rest.split_last_chunk::<POLY1305_TAG_LEN>() else {
+            return Err(Error::InvalidLength);
+        };
+        assert_eq!(
+            out.len(),
+            ciphertext.len(),
+            "`out` must be exactly `SECRETSTREAM_ABYTES` shorter than `input`"
+        );
+        let (mut chacha, mut poly) = self.state.init(aad);
+        let mut block = [0; 64];
+        block[0] = *head;
+        chacha.xor(&mut block);
+        let tag = SecretStreamTag(block[0]);
+        block[0] = *head;
+        poly.update(&block);
+
+        poly.update(ciphertext);
+        poly.update(&[0; 16][..quirky_padding(ciphertext.len())]);
+        let computed = finish(
+            poly,
+            aad.len() as u64,
+            (block.len() + ciphertext.len()) as u64,
+        );
+        if !computed.verify(mac) {
+            return Err(Error::TagMismatch);
+        }
+        out.copy_from_slice(ciphertext);
+        chacha.xor(out);
+        self.state.advance(mac, tag);
+        Ok(tag)
+    }
+
+    /// Replaces the key, exactly as if the last message had [`SecretStreamTag::REKEY`].
+    ///
+    /// Must be matched by a call to [`SecretStreamPush::rekey`] at the same point in the stream.
+    #[inline]
+    pub fn rekey(&mut self) {
+        self.state.rekey();
+    }
+}
+
+/// Amount of zeros libsodium feeds into Poly1305 after a ciphertext of `len` bytes.
+///
+/// This is meant to pad to a multiple of 16 bytes, but due to a misplaced parenthesis
+/// it's `(0x10 - 64 + len) & 0xf` instead of `(0x10 - (64 + len)) & 0xf`, which we
+/// have to replicate to remain compatible.
+#[inline]
+fn quirky_padding(len: usize) -> usize {
+    (0x10usize.wrapping_sub(64).wrapping_add(len)) & 0xf
+}
+
+/// State shared by both halves of a secretstream.
+struct State {
+    key: [u32; 8],
+    counter: u32,
+    inonce: [u32; 2],
+}
+
+impl State {
+    #[inline]
+    fn new(key: &[u8; SECRETSTREAM_KEY_LEN], header: &[u8; SECRETSTREAM_HEADER_LEN]) -> Self {
+        Self {
+            key: hchacha::<R20>(&le_words(key), &le_words(&header[..16])),
+            counter: 1,
+            inonce: le_words(&header[16..]),
+        }
+    }
+
+    #[inline]
+    fn chacha(&self) -> ChaCha<R20, Ietf> {
+        let [inonce0, inonce1] = self.inonce;
+        ChaCha::new(self.key, 0, [self.counter, inonce0, inonce1])
+    }
+
+    /// Creates the ChaCha instance (positioned at block 1) and the Poly1305
+    /// instance (keyed with block 0, and having absorbed `aad`) for the next message.
+    #[inline]
+    fn init(&self, aad: &[u8]) -> (ChaCha<R20, Ietf>, Poly1305) {
+        let mut chacha = self.chacha();
+        let mut poly_key = [0; POLY1305_KEY_LEN];
+        chacha.fill(&mut poly_key);
+        let mut poly = Poly1305::new(&poly_key);
+        poly_key.fill(0);
+        poly.update_padded(aad);
+        (chacha, poly)
+    }
+
+    /// Moves on to the next message after one with `mac` and `tag` has been processed.
+    #[inline]
+    fn advance(&mut self, mac: &[u8; POLY1305_TAG_LEN], tag: SecretStreamTag) {
+        let mac: [u32; 2] = le_words(mac);
+        self.inonce[0] ^= mac[0];
+        self.inonce[1] ^= mac[1];
+        self.counter = self.counter.wrapping_add(1);
+        if tag.0 & SecretStreamTag::REKEY.0 != 0 || self.counter == 0 {
+            self.rekey();
+        }
+    }
+
+    #[inline]
+    fn rekey(&mut self) {
+        let mut buf = [0; 40];
+        for (dst, src) in buf
+            .chunks_exact_mut(size_of::<u32>())
+            .zip(self.key.iter().chain(&self.inonce))
+        {
+            dst.copy_from_slice(&src.to_le_bytes());
+        }
+        self.chacha().xor(&mut buf);
+        self.key = le_words(&buf[..32]);
+        self.inonce = le_words(&buf[32..]);
+        self.counter = 1;
+        buf.fill(0);
+    }
+}
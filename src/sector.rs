@@ -0,0 +1,120 @@
+/*!
+Module containing [`SectorCipher`], which encrypts fixed-size sectors in place for block devices,
+database pages, and anything else that needs random access to encrypted data.
+
+Every sector is encrypted independently with the original 64-bit nonce variant of ChaCha, using
+the little-endian sector index as the nonce and starting at block 0. Sectors can therefore be
+read and written in any order, and each one is processed by the batched backends exactly like a
+regular buffer.
+
+Since the nonce is fixed for each sector, rewriting a sector reuses its keystream: anyone who
+sees both the old and new contents learns their xor. This is inherent to length-preserving sector
+encryption without a wide-block mode (such as Adiantum), and there's no authentication either, so
+this is only suitable where an attacker gets a single snapshot of the data at rest.
+*/
+
+use crate::rounds::*;
+use crate::util::le_words;
+use crate::variations::Djb;
+use crate::{ChaCha, Error};
+use core::marker::PhantomData;
+
+/// Sector encryption with `R` rounds of ChaCha.
+///
+/// ```
+/// use chachacha::SectorCipher20;
+///
+/// let cipher = SectorCipher20::new(&[69; 32], 512);
+/// let mut disk = [0; 4 * 512];
+/// // Sectors can be processed one at a time or in consecutive runs.
+/// cipher.encrypt_sectors(0, &mut disk).unwrap();
+/// cipher.decrypt_sector(2, &mut disk[1024..1536]).unwrap();
+/// assert!(disk[1024..1536].iter().all(|&x| x == 0));
+/// ```
+pub struct SectorCipher<R> {
+    key: [u32; 8],
+    sector_size: usize,
+    _phantom: PhantomData<R>,
+}
+
+/// Sector encryption with 20 rounds of ChaCha.
+pub type SectorCipher20 = SectorCipher<R20>;
+/// Sector encryption with 12 rounds of ChaCha.
+pub type SectorCipher12 = SectorCipher<R12>;
+/// Sector encryption with 8 rounds of ChaCha.
+pub type SectorCipher8 = SectorCipher<R8>;
+
+impl<R: DoubleRounds> SectorCipher<R> {
+    /// Creates a new `SectorCipher` instance using `key`, for sectors of `sector_size` bytes.
+    ///
+    /// # Panics
+    ///
+    /// If `sector_size` is 0.
+    #[inline]
+    pub fn new(key: &[u8; 32], sector_size: usize) -> Self {
+        assert!(sector_size > 0, "sector size must be non-zero");
+        Self {
+            key: le_words(key),
+            sector_size,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns the size (in bytes) of a sector.
+    #[inline]
+    pub fn sector_size(&self) -> usize {
+        self.sector_size
+    }
+
+    /// Encrypts sector number `index` in place.
+    ///
+    /// Returns [`Error::InvalidLength`] if `sector` isn't exactly one sector long,
+    /// in which case it's left untouched.
+    #[inline]
+    pub fn encrypt_sector(&self, index: u64, sector: &mut [u8]) -> Result<(), Error> {
+        if sector.len() != self.sector_size {
+            return Err(Error::InvalidLength);
+        }
+        self.chacha(index).xor(sector);
+        Ok(())
+    }
+
+    /// Decrypts sector number `index` in place.
+    ///
+    /// Returns [`Error::InvalidLength`] if `sector` isn't exactly one sector long,
+    /// in which case it's left untouched.
+    #[inline]
+    pub fn decrypt_sector(&self, index: u64, sector: &mut [u8]) -> Result<(), Error> {
+        self.encrypt_sector(index, sector)
+    }
+
+    /// Encrypts consecutive sectors in place, the first of which is number `first_index`.
+    ///
+    /// Returns [`Error::InvalidLength`] if `sectors` isn't a whole number of sectors long,
+    /// in which case it's left untouched.
+    #[inline]
+    pub fn encrypt_sectors(&self, first_index: u64, sectors: &mut [u8]) -> Result<(), Error> {
+        if !sectors.len().is_multiple_of(self.sector_size) {
+            return Err(Error::InvalidLength);
+        }
+        sectors
+            .chunks_exact_mut(self.sector_size)
+            .zip(first_index..)
+            .for_each(|(sector, index)| self.chacha(index).xor(sector));
+        Ok(())
+    }
+
+    /// Decrypts consecutive sectors in place, the first of which is number `first_index`.
+    ///
+    /// Returns [`Error::InvalidLength`] if `sectors` isn't a whole number of sectors long,
+    /// in which case it's left untouched.
+    #[inline]
+    pub fn decrypt_sectors(&self, first_index: u64, sectors: &mut [u8]) -> Result<(), Error> {
+        self.encrypt_sectors(first_index, sectors)
+    }
+
+    #[inline]
+    fn chacha(&self, index: u64) -> ChaCha<R, Djb> {
+        ChaCha::new(self.key, 0, [index as u32, (index >> 32) as u32, 0])
+    }
+}
@@ -0,0 +1,78 @@
+/*!
+Module containing [`Seed`], the structured form of the flat [`SEED_LEN_U8`] byte seed accepted by
+the `From<[u8; SEED_LEN_U8]>` implementations of the ChaCha types.
+
+The flat seed is simply the last three rows of the ChaCha matrix: the key, followed by the
+counter and nonce. It's read in native byte order, so the conversions here are too.
+*/
+
+use crate::ChaChaCore;
+use crate::key::{Key, Nonce64};
+use crate::util::SEED_LEN_U8;
+
+/// Seed of a ChaCha instance, split into its components.
+///
+/// `counter` and `nonce` are laid out like the [`Djb`](crate::ChaCha20Djb) variants expect.
+/// The [`Ietf`](crate::ChaCha20Ietf) variants instead treat the low 32 bits of `counter`
+/// as their counter, and the high 32 bits followed by `nonce` as their 96-bit nonce.
+///
+/// ```
+/// use chachacha::{ChaCha20Djb, Key, Nonce64, Seed};
+///
+/// let seed = Seed {
+///     key: Key::new([1; 32]),
+///     counter: 69,
+///     nonce: Nonce64::new([2; 8]),
+/// };
+/// let flat: [u8; 48] = seed.clone().into();
+/// let mut chacha = ChaCha20Djb::from(seed);
+/// assert_eq!(chacha.get_counter(), 69);
+/// assert_eq!(chacha.get_block(), ChaCha20Djb::from(flat).get_block());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Seed {
+    /// 256-bit key.
+    pub key: Key,
+    /// 64-bit block counter.
+    pub counter: u64,
+    /// 64-bit nonce.
+    pub nonce: Nonce64,
+}
+
+// The key and nonce wipe themselves.
+#[cfg(feature = "zeroize")]
+impl zeroize::ZeroizeOnDrop for Seed {}
+
+impl From<[u8; SEED_LEN_U8]> for Seed {
+    #[inline]
+    fn from(mut value: [u8; SEED_LEN_U8]) -> Self {
+        let result = Self {
+            key: Key::new(value[..32].try_into().unwrap()),
+            counter: u64::from_ne_bytes(value[32..40].try_into().unwrap()),
+            nonce: Nonce64::new(value[40..].try_into().unwrap()),
+        };
+        value.fill(0);
+        result
+    }
+}
+
+impl From<Seed> for [u8; SEED_LEN_U8] {
+    #[inline]
+    fn from(value: Seed) -> Self {
+        let mut result = [0; SEED_LEN_U8];
+        result[..32].copy_from_slice(value.key.as_bytes());
+        result[32..40].copy_from_slice(&value.counter.to_ne_bytes());
+        result[40..].copy_from_slice(value.nonce.as_bytes());
+        result
+    }
+}
+
+impl<M, R, V> From<Seed> for ChaChaCore<M, R, V> {
+    #[inline]
+    fn from(value: Seed) -> Self {
+        let mut seed: [u8; SEED_LEN_U8] = value.into();
+        let result = seed.into();
+        seed.fill(0);
+        result
+    }
+}
@@ -0,0 +1,256 @@
+/*!
+Module containing [`Backend`], which names the implementations of ChaCha built into this crate,
+along with [`force_backend`] for overriding which one is used at runtime, and [`active_backend`]
+for finding out which one that is (worth logging, to confirm a deployment got the fast path).
+
+Every instance using the default backend (all of the `ChaCha*` aliases without a backend suffix)
+produces its bulk output with whichever implementation is active. That's normally the widest one
+the running CPU supports, but forcing a different one is useful for benchmarking, narrowing down
+miscompiles, and avoiding CPUs with poor implementations of wide instructions. With the `std`
+feature, the `CHACHACHA_BACKEND` environment variable (holding a [`Backend::name`]) is checked
+the first time a backend is needed, so this can also be done without touching any code.
+
+The widest backend isn't always the fastest one, e.g. on early AVX-512 CPUs which downclock when
+using it, or hybrid CPUs whose efficiency cores split wide instructions in two. With the
+`autotune` feature, every supported backend is timed on a couple of KiB of output (taking tens of
+microseconds) the first time a backend is needed, and the fastest one is used.
+
+Every backend produces exactly the same output, so switching between them at any point never
+changes the keystream, only how fast it's produced. To pin an instance to a single backend at
+compile time instead, use one of the backend-specific aliases (`ChaCha20DjbSoft`, etc.).
+*/
+
+use crate::Error;
+use crate::backends::dispatch;
+use core::fmt;
+use core::str::FromStr;
+
+/// Implementations of ChaCha built into this crate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+#[repr(u8)]
+pub enum Backend {
+    /// Portable implementation, available everywhere.
+    Soft = 1,
+    /// 128-bit SSE2 implementation, available on every x86 CPU.
+    Sse2,
+    /// 128-bit SSSE3 implementation, for x86 CPUs from Core 2/Bobcat onwards.
+    Ssse3,
+    /// 128-bit AVX implementation, for x86 CPUs from Sandy Bridge/Bulldozer onwards. The same
+    /// as [`Ssse3`](Self::Ssse3), but with the three operand forms of its instructions.
+    Avx,
+    /// 256-bit AVX2 implementation, for x86 CPUs from Haswell/Excavator onwards.
+    Avx2,
+    /// 512-bit AVX-512 implementation, for x86 CPUs with AVX-512F.
+    Avx512,
+    /// 128-bit Neon implementation, available on every AArch64 CPU, and on 32-bit Arm CPUs
+    /// with Neon when using the (nightly only) `unstable-arm-neon` feature.
+    Neon,
+}
+
+impl Backend {
+    /// Every backend, supported or not.
+    pub const ALL: [Self; 7] = [
+        Self::Soft,
+        Self::Sse2,
+        Self::Ssse3,
+        Self::Avx,
+        Self::Avx2,
+        Self::Avx512,
+        Self::Neon,
+    ];
+
+    /// Returns the lowercase name of `self`, as accepted by [`FromStr`].
+    #[inline]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Soft => "soft",
+            Self::Sse2 => "sse2",
+            Self::Ssse3 => "ssse3",
+            Self::Avx => "avx",
+            Self::Avx2 => "avx2",
+            Self::Avx512 => "avx512",
+            Self::Neon => "neon",
+        }
+    }
+
+    /// Returns the width in bits of the registers `self` operates on.
+    ///
+    /// [`Soft`](Self::Soft) doesn't use any SIMD instructions, so its width is that of a `u32`.
+    #[inline]
+    pub const fn width(self) -> u32 {
+        match self {
+            Self::Soft => 32,
+            Self::Sse2 | Self::Ssse3 | Self::Avx | Self::Neon => 128,
+            Self::Avx2 => 256,
+            Self::Avx512 => 512,
+        }
+    }
+
+    /// Returns `true` if `self` can be used on the running CPU.
+    #[inline]
+    pub fn is_supported(self) -> bool {
+        dispatch::is_supported(self)
+    }
+
+    #[inline]
+    pub(crate) fn from_u8(value: u8) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|backend| *backend as u8 == value)
+    }
+}
+
+impl fmt::Display for Backend {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Backend {
+    type Err = Error;
+
+    /// Parses the [`name`](Backend::name) of a backend, ignoring case.
+    ///
+    /// Returns [`Error::UnsupportedBackend`] for anything else.
+    #[inline]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|backend| backend.name().eq_ignore_ascii_case(s))
+            .ok_or(Error::UnsupportedBackend)
+    }
+}
+
+/// Reasons a [`Backend`] can be active.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+#[repr(u8)]
+pub enum BackendReason {
+    /// It's the widest backend supported by the running CPU.
+    Detected = 1,
+    /// It was named by the `CHACHACHA_BACKEND` environment variable.
+    Environment,
+    /// It was passed to [`force_backend`].
+    Forced,
+    /// It was the fastest backend supported by the running CPU,
+    /// when they were timed with the `autotune` feature enabled.
+    Tuned,
+}
+
+impl BackendReason {
+    #[inline]
+    pub(crate) fn from_u8(value: u8) -> Option<Self> {
+        [Self::Detected, Self::Environment, Self::Forced, Self::Tuned]
+            .into_iter()
+            .find(|reason| *reason as u8 == value)
+    }
+}
+
+impl fmt::Display for BackendReason {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Detected => "detected",
+            Self::Environment => "set by CHACHACHA_BACKEND",
+            Self::Forced => "forced",
+            Self::Tuned => "tuned",
+        })
+    }
+}
+
+/// Description of the active backend, as returned by [`active_backend`].
+///
+/// Its [`Display`](fmt::Display) implementation is meant for logging,
+/// and looks like `avx2 (256-bit, detected)`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BackendInfo {
+    backend: Backend,
+    reason: BackendReason,
+}
+
+impl BackendInfo {
+    /// Returns the backend producing bulk output.
+    #[inline]
+    pub const fn backend(&self) -> Backend {
+        self.backend
+    }
+
+    /// Returns the [`width`](Backend::width) of the backend.
+    #[inline]
+    pub const fn width(&self) -> u32 {
+        self.backend.width()
+    }
+
+    /// Returns why the backend is the one in use.
+    #[inline]
+    pub const fn reason(&self) -> BackendReason {
+        self.reason
+    }
+}
+
+impl fmt::Display for BackendInfo {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({}-bit, {})",
+            self.backend,
+            self.width(),
+            self.reason
+        )
+    }
+}
+
+/// Returns which backend instances using the default backend currently produce their
+/// output with, and why. The first call picks the backend if nothing has needed one yet.
+///
+/// ```
+/// use chachacha::{Backend, BackendReason, active_backend, clear_forced_backend, force_backend};
+///
+/// force_backend(Backend::Soft).unwrap();
+/// let info = active_backend();
+/// clear_forced_backend();
+/// assert_eq!(info.backend(), Backend::Soft);
+/// assert_eq!(info.reason(), BackendReason::Forced);
+/// assert_eq!(info.to_string(), "soft (32-bit, forced)");
+/// ```
+#[inline]
+pub fn active_backend() -> BackendInfo {
+    let (backend, reason) = dispatch::active_with_reason();
+    BackendInfo { backend, reason }
+}
+
+/// Makes every instance using the default backend produce its output with `backend`,
+/// until [`clear_forced_backend`] is called.
+///
+/// Returns [`Error::UnsupportedBackend`] (leaving the active backend as is)
+/// if `backend` can't be used on the running CPU.
+///
+/// ```
+/// use chachacha::{Backend, ChaCha20Djb, clear_forced_backend, force_backend};
+///
+/// let mut expected = [0; 1000];
+/// ChaCha20Djb::new([69; 8], 0, [0; 3]).fill(&mut expected);
+///
+/// force_backend(Backend::Soft).unwrap();
+/// let mut actual = [0; 1000];
+/// ChaCha20Djb::new([69; 8], 0, [0; 3]).fill(&mut actual);
+/// clear_forced_backend();
+/// assert_eq!(actual, expected);
+/// ```
+#[inline]
+pub fn force_backend(backend: Backend) -> Result<(), Error> {
+    if !backend.is_supported() {
+        return Err(Error::UnsupportedBackend);
+    }
+    dispatch::set_active(Some(backend));
+    Ok(())
+}
+
+/// Undoes [`force_backend`], going back to the backend that would've been used without it.
+#[inline]
+pub fn clear_forced_backend() {
+    dispatch::set_active(None);
+}
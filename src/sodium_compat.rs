@@ -0,0 +1,177 @@
+/*!
+Module containing drop-in replacements for libsodium's `crypto_stream_*` functions, which produce
+byte-identical output to ease porting libsodium-based code.
+
+The names and argument order match libsodium, with the lengths being implied by the slices.
+Functions taking both an input and output panic if their lengths differ, instead of reading or
+writing out of bounds.
+
+```
+use chachacha::sodium_compat::*;
+
+let key = [69; CRYPTO_STREAM_CHACHA20_IETF_KEYBYTES];
+let nonce = [0; CRYPTO_STREAM_CHACHA20_IETF_NONCEBYTES];
+let mut c = [0; 14];
+crypto_stream_chacha20_ietf_xor(&mut c, b"attack at dawn", &nonce, &key);
+let mut m = [0; 14];
+crypto_stream_chacha20_ietf_xor(&mut m, &c, &nonce, &key);
+assert_eq!(&m, b"attack at dawn");
+```
+*/
+
+use crate::util::le_words;
+use crate::{XChaCha20Djb, chacha20_djb_xor, chacha20_ietf_xor};
+
+/// Size (in 8-bit integers) of a `crypto_stream_chacha20` key.
+pub const CRYPTO_STREAM_CHACHA20_KEYBYTES: usize = 32;
+/// Size (in 8-bit integers) of a `crypto_stream_chacha20` nonce.
+pub const CRYPTO_STREAM_CHACHA20_NONCEBYTES: usize = 8;
+/// Size (in 8-bit integers) of a `crypto_stream_chacha20_ietf` key.
+pub const CRYPTO_STREAM_CHACHA20_IETF_KEYBYTES: usize = 32;
+/// Size (in 8-bit integers) of a `crypto_stream_chacha20_ietf` nonce.
+pub const CRYPTO_STREAM_CHACHA20_IETF_NONCEBYTES: usize = 12;
+/// Size (in 8-bit integers) of a `crypto_stream_xchacha20` key.
+pub const CRYPTO_STREAM_XCHACHA20_KEYBYTES: usize = 32;
+/// Size (in 8-bit integers) of a `crypto_stream_xchacha20` nonce.
+pub const CRYPTO_STREAM_XCHACHA20_NONCEBYTES: usize = 24;
+
+/// Fills `c` with the ChaCha20 keystream for `n` and `k`.
+#[inline]
+pub fn crypto_stream_chacha20(
+    c: &mut [u8],
+    n: &[u8; CRYPTO_STREAM_CHACHA20_NONCEBYTES],
+    k: &[u8; CRYPTO_STREAM_CHACHA20_KEYBYTES],
+) {
+    c.fill(0);
+    chacha20_djb_xor(k, n, 0, c);
+}
+
+/// Encrypts (or decrypts) `m` into `c` with ChaCha20.
+///
+/// # Panics
+///
+/// If `c` and `m` have different lengths.
+#[inline]
+pub fn crypto_stream_chacha20_xor(
+    c: &mut [u8],
+    m: &[u8],
+    n: &[u8; CRYPTO_STREAM_CHACHA20_NONCEBYTES],
+    k: &[u8; CRYPTO_STREAM_CHACHA20_KEYBYTES],
+) {
+    crypto_stream_chacha20_xor_ic(c, m, n, 0, k);
+}
+
+/// Encrypts (or decrypts) `m` into `c` with ChaCha20, starting at block `ic`.
+///
+/// # Panics
+///
+/// If `c` and `m` have different lengths.
+#[inline]
+pub fn crypto_stream_chacha20_xor_ic(
+    c: &mut [u8],
+    m: &[u8],
+    n: &[u8; CRYPTO_STREAM_CHACHA20_NONCEBYTES],
+    ic: u64,
+    k: &[u8; CRYPTO_STREAM_CHACHA20_KEYBYTES],
+) {
+    c.copy_from_slice(m);
+    chacha20_djb_xor(k, n, ic, c);
+}
+
+/// Fills `c` with the ChaCha20 (IETF) keystream for `n` and `k`.
+#[inline]
+pub fn crypto_stream_chacha20_ietf(
+    c: &mut [u8],
+    n: &[u8; CRYPTO_STREAM_CHACHA20_IETF_NONCEBYTES],
+    k: &[u8; CRYPTO_STREAM_CHACHA20_IETF_KEYBYTES],
+) {
+    c.fill(0);
+    chacha20_ietf_xor(k, n, 0, c);
+}
+
+/// Encrypts (or decrypts) `m` into `c` with ChaCha20 (IETF).
+///
+/// # Panics
+///
+/// If `c` and `m` have different lengths.
+#[inline]
+pub fn crypto_stream_chacha20_ietf_xor(
+    c: &mut [u8],
+    m: &[u8],
+    n: &[u8; CRYPTO_STREAM_CHACHA20_IETF_NONCEBYTES],
+    k: &[u8; CRYPTO_STREAM_CHACHA20_IETF_KEYBYTES],
+) {
+    crypto_stream_chacha20_ietf_xor_ic(c, m, n, 0, k);
+}
+
+/// Encrypts (or decrypts) `m` into `c` with ChaCha20 (IETF), starting at block `ic`.
+///
+/// # Panics
+///
+/// If `c` and `m` have different lengths.
+#[inline]
+pub fn crypto_stream_chacha20_ietf_xor_ic(
+    c: &mut [u8],
+    m: &[u8],
+    n: &[u8; CRYPTO_STREAM_CHACHA20_IETF_NONCEBYTES],
+    ic: u32,
+    k: &[u8; CRYPTO_STREAM_CHACHA20_IETF_KEYBYTES],
+) {
+    c.copy_from_slice(m);
+    chacha20_ietf_xor(k, n, ic, c);
+}
+
+/// Fills `c` with the XChaCha20 keystream for `n` and `k`.
+#[inline]
+pub fn crypto_stream_xchacha20(
+    c: &mut [u8],
+    n: &[u8; CRYPTO_STREAM_XCHACHA20_NONCEBYTES],
+    k: &[u8; CRYPTO_STREAM_XCHACHA20_KEYBYTES],
+) {
+    c.fill(0);
+    xchacha20_xor(c, n, 0, k);
+}
+
+/// Encrypts (or decrypts) `m` into `c` with XChaCha20.
+///
+/// # Panics
+///
+/// If `c` and `m` have different lengths.
+#[inline]
+pub fn crypto_stream_xchacha20_xor(
+    c: &mut [u8],
+    m: &[u8],
+    n: &[u8; CRYPTO_STREAM_XCHACHA20_NONCEBYTES],
+    k: &[u8; CRYPTO_STREAM_XCHACHA20_KEYBYTES],
+) {
+    crypto_stream_xchacha20_xor_ic(c, m, n, 0, k);
+}
+
+/// Encrypts (or decrypts) `m` into `c` with XChaCha20, starting at block `ic`.
+///
+/// # Panics
+///
+/// If `c` and `m` have different lengths.
+#[inline]
+pub fn crypto_stream_xchacha20_xor_ic(
+    c: &mut [u8],
+    m: &[u8],
+    n: &[u8; CRYPTO_STREAM_XCHACHA20_NONCEBYTES],
+    ic: u64,
+    k: &[u8; CRYPTO_STREAM_XCHACHA20_KEYBYTES],
+) {
+    c.copy_from_slice(m);
+    xchacha20_xor(c, n, ic, k);
+}
+
+#[inline]
+fn xchacha20_xor(
+    c: &mut [u8],
+    n: &[u8; CRYPTO_STREAM_XCHACHA20_NONCEBYTES],
+    ic: u64,
+    k: &[u8; CRYPTO_STREAM_XCHACHA20_KEYBYTES],
+) {
+    let mut key = le_words(k);
+    XChaCha20Djb::new_extended(key, ic, le_words(n)).xor(c);
+    key.fill(0);
+}
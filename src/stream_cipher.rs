@@ -0,0 +1,215 @@
+/*!
+Module containing implementations of the RustCrypto [`cipher`] traits, which allows the ChaCha
+types in this crate to be used anywhere those traits are expected.
+
+`ChaChaCore` implements the "core" traits ([`StreamCipherCore`], [`StreamCipherSeekCore`], and
+[`KeyIvInit`]), which operate on whole 64-byte blocks. Wrapping it in a [`ChaChaCipher`] provides
+the byte-oriented [`StreamCipher`] and [`StreamCipherSeek`] traits, with any partially consumed
+block buffered so successive calls produce one continuous keystream.
+
+[`cipher`]: https://crates.io/crates/cipher
+[`StreamCipher`]: cipher::StreamCipher
+[`StreamCipherSeek`]: cipher::StreamCipherSeek
+*/
+
+use crate::chacha::ChaChaCore;
+use crate::rounds::*;
+use crate::util::*;
+use crate::variations::*;
+use cipher::consts::{U4, U8, U12, U24, U32, U64};
+use cipher::{
+    Block, BlockSizeUser, Iv, IvSizeUser, Key, KeyIvInit, KeySizeUser, ParBlocks,
+    ParBlocksSizeUser, StreamBackend, StreamCipherCore, StreamCipherCoreWrapper,
+    StreamCipherSeekCore, StreamClosure,
+};
+use core::mem::transmute;
+
+/// Byte-oriented wrapper implementing [`StreamCipher`] and [`StreamCipherSeek`]
+/// for any of the ChaCha types in this crate.
+///
+/// ```
+/// use chachacha::{ChaCha20Ietf, ChaChaCipher};
+/// use cipher::{KeyIvInit, StreamCipher, StreamCipherSeek};
+///
+/// let mut chacha = ChaChaCipher::<ChaCha20Ietf>::new(&[69; 32].into(), &[0; 12].into());
+/// let mut buf = *b"attack at dawn";
+/// chacha.apply_keystream(&mut buf);
+/// chacha.seek(0);
+/// chacha.apply_keystream(&mut buf);
+/// assert_eq!(&buf, b"attack at dawn");
+/// ```
+///
+/// [`StreamCipher`]: cipher::StreamCipher
+/// [`StreamCipherSeek`]: cipher::StreamCipherSeek
+pub type ChaChaCipher<C> = StreamCipherCoreWrapper<C>;
+
+impl<M, R, V> KeySizeUser for ChaChaCore<M, R, V> {
+    type KeySize = U32;
+}
+
+impl<M, R> IvSizeUser for ChaChaCore<M, R, Djb> {
+    type IvSize = U8;
+}
+
+impl<M, R> IvSizeUser for ChaChaCore<M, R, Ietf> {
+    type IvSize = U12;
+}
+
+impl<M, R> IvSizeUser for ChaChaCore<M, R, XDjb> {
+    type IvSize = U24;
+}
+
+impl<M, R> KeyIvInit for ChaChaCore<M, R, Djb>
+where
+    M: Machine,
+    R: DoubleRounds,
+{
+    #[inline]
+    fn new(key: &Key<Self>, iv: &Iv<Self>) -> Self {
+        let [n0, n1] = le_words(iv);
+        let mut key_words = le_words(key);
+        let result = Self::new(key_words, 0, [n0, n1, 0]);
+        key_words.fill(0);
+        result
+    }
+}
+
+impl<M, R> KeyIvInit for ChaChaCore<M, R, Ietf>
+where
+    M: Machine,
+    R: DoubleRounds,
+{
+    #[inline]
+    fn new(key: &Key<Self>, iv: &Iv<Self>) -> Self {
+        let mut key_words = le_words(key);
+        let result = Self::new(key_words, 0, le_words(iv));
+        key_words.fill(0);
+        result
+    }
+}
+
+impl<M, R> KeyIvInit for ChaChaCore<M, R, XDjb>
+where
+    M: Machine,
+    R: DoubleRounds,
+{
+    #[inline]
+    fn new(key: &Key<Self>, iv: &Iv<Self>) -> Self {
+        let mut key_words = le_words(key);
+        let result = Self::new_extended(key_words, 0, le_words(iv));
+        key_words.fill(0);
+        result
+    }
+}
+
+impl<M, R, V> BlockSizeUser for ChaChaCore<M, R, V> {
+    type BlockSize = U64;
+}
+
+impl<M, R, V> StreamCipherCore for ChaChaCore<M, R, V>
+where
+    M: Machine,
+    R: DoubleRounds,
+    V: Variant,
+{
+    #[inline]
+    fn remaining_blocks(&self) -> Option<usize> {
+        let counter = self.get_counter();
+        let remaining = match V::VAR {
+            Variants::Djb => u64::MAX - counter,
+            Variants::Ietf => u32::MAX as u64 - counter,
+        };
+        remaining.try_into().ok()
+    }
+
+    #[inline]
+    fn process_with_backend(&mut self, f: impl StreamClosure<BlockSize = Self::BlockSize>) {
+        f.call(&mut Backend(self));
+    }
+}
+
+impl<M, R> StreamCipherSeekCore for ChaChaCore<M, R, Djb>
+where
+    M: Machine,
+    R: DoubleRounds,
+{
+    type Counter = u64;
+
+    #[inline]
+    fn get_block_pos(&self) -> Self::Counter {
+        self.get_counter()
+    }
+
+    #[inline]
+    fn set_block_pos(&mut self, pos: Self::Counter) {
+        self.set_counter(pos);
+    }
+}
+
+impl<M, R> StreamCipherSeekCore for ChaChaCore<M, R, Ietf>
+where
+    M: Machine,
+    R: DoubleRounds,
+{
+    type Counter = u32;
+
+    #[inline]
+    fn get_block_pos(&self) -> Self::Counter {
+        self.get_counter() as u32
+    }
+
+    #[inline]
+    fn set_block_pos(&mut self, pos: Self::Counter) {
+        self.set_counter(pos as u64);
+    }
+}
+
+impl<M, R> StreamCipherSeekCore for ChaChaCore<M, R, XDjb>
+where
+    M: Machine,
+    R: DoubleRounds,
+{
+    type Counter = u64;
+
+    #[inline]
+    fn get_block_pos(&self) -> Self::Counter {
+        self.get_counter()
+    }
+
+    #[inline]
+    fn set_block_pos(&mut self, pos: Self::Counter) {
+        self.set_counter(pos);
+    }
+}
+
+struct Backend<'a, M, R, V>(&'a mut ChaChaCore<M, R, V>);
+
+impl<M, R, V> BlockSizeUser for Backend<'_, M, R, V> {
+    type BlockSize = U64;
+}
+
+impl<M, R, V> ParBlocksSizeUser for Backend<'_, M, R, V> {
+    type ParBlocksSize = U4;
+}
+
+impl<M, R, V> StreamBackend for Backend<'_, M, R, V>
+where
+    M: Machine,
+    R: DoubleRounds,
+    V: Variant,
+{
+    #[inline]
+    fn gen_ks_block(&mut self, block: &mut Block<Self>) {
+        // Filling exactly one block's worth of bytes only advances the counter by one.
+        self.0.fill(block);
+    }
+
+    #[inline]
+    fn gen_par_ks_blocks(&mut self, blocks: &mut ParBlocks<Self>) {
+        const {
+            assert!(size_of::<ParBlocks<Self>>() == BUF_LEN_U8);
+        }
+        let buf: &mut [u8; BUF_LEN_U8] = unsafe { transmute(blocks) };
+        self.0.fill_block(buf);
+    }
+}
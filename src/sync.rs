@@ -0,0 +1,122 @@
+/*!
+Module containing [`SyncChaCha`], which allows a single keyed ChaCha instance to be shared
+between multiple threads.
+
+Taking a lock for every request would make contention the bottleneck, so output is handed out
+in batches: each [`SyncChaChaHandle`] keeps a caller-local buffer, and only takes the lock when
+that buffer runs dry, grabbing an entire `BUF_LEN_U8` block of output at a time.
+*/
+
+use crate::ChaCha;
+use crate::rounds::*;
+use crate::util::*;
+use crate::variations::*;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// A ChaCha instance behind an internal lock, which can be shared between threads.
+///
+/// ```
+/// use chachacha::{ChaCha12Djb, SyncChaCha};
+///
+/// let shared = SyncChaCha::new(ChaCha12Djb::new([69; 8], 0, [0; 3]));
+/// std::thread::scope(|s| {
+///     for _ in 0..4 {
+///         s.spawn(|| {
+///             let mut handle = shared.handle();
+///             let mut buf = [0; 100];
+///             handle.fill(&mut buf);
+///         });
+///     }
+/// });
+/// ```
+pub struct SyncChaCha<C> {
+    inner: Mutex<C>,
+}
+
+/// Caller-local handle to a [`SyncChaCha`] instance.
+///
+/// Output is drawn from a local buffer, which is refilled a block
+/// at a time from the shared instance as needed.
+pub struct SyncChaChaHandle<'a, C> {
+    shared: &'a SyncChaCha<C>,
+    buf: [u8; BUF_LEN_U8],
+    index: usize,
+}
+
+impl<R, V> SyncChaCha<ChaCha<R, V>>
+where
+    R: DoubleRounds,
+    V: Variant,
+{
+    /// Creates a new `SyncChaCha` instance wrapping `chacha`.
+    #[inline]
+    pub const fn new(chacha: ChaCha<R, V>) -> Self {
+        Self {
+            inner: Mutex::new(chacha),
+        }
+    }
+
+    /// Consumes `self`, returning the wrapped instance.
+    #[inline]
+    pub fn into_inner(self) -> ChaCha<R, V> {
+        self.inner
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Creates a new handle with an empty local buffer.
+    #[inline]
+    pub fn handle(&self) -> SyncChaChaHandle<'_, ChaCha<R, V>> {
+        SyncChaChaHandle {
+            shared: self,
+            buf: [0; BUF_LEN_U8],
+            index: BUF_LEN_U8,
+        }
+    }
+
+    /// Fills `dst` with bytes from the output of the shared instance,
+    /// holding the lock for the duration of the call.
+    #[inline]
+    pub fn fill(&self, dst: &mut [u8]) {
+        self.lock().fill(dst);
+    }
+
+    #[inline]
+    fn lock(&self) -> MutexGuard<'_, ChaCha<R, V>> {
+        // The state of a ChaCha instance is always valid, even if some
+        // other thread panicked while holding the lock.
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<R, V> SyncChaChaHandle<'_, ChaCha<R, V>>
+where
+    R: DoubleRounds,
+    V: Variant,
+{
+    /// Fills `dst` with bytes, drawing from the local buffer first and
+    /// only taking the shared lock when more output is needed.
+    #[inline]
+    pub fn fill(&mut self, mut dst: &mut [u8]) {
+        let buffered = dst.len().min(BUF_LEN_U8 - self.index);
+        let (cur, rest) = dst.split_at_mut(buffered);
+        cur.copy_from_slice(&self.buf[self.index..self.index + buffered]);
+        self.index += buffered;
+        dst = rest;
+        if dst.is_empty() {
+            return;
+        }
+        // Anything left over requires the lock, so grab all the full
+        // blocks we need and a fresh local buffer in one go.
+        let full = dst.len() - (dst.len() % BUF_LEN_U8);
+        let (cur, rest) = dst.split_at_mut(full);
+        let mut chacha = self.shared.lock();
+        chacha.fill(cur);
+        if !rest.is_empty() {
+            chacha.fill_block(&mut self.buf);
+            drop(chacha);
+            rest.copy_from_slice(&self.buf[..rest.len()]);
+            self.index = rest.len();
+        }
+    }
+}
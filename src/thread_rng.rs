@@ -0,0 +1,75 @@
+/*!
+Module containing [`ThreadRng`], a lazily-initialized, thread-local ChaCha instance for users
+who just want random bytes without having to thread an instance through their program.
+
+Each thread gets its own [`ChaCha8Djb`] seeded from OS entropy the first time it's used. It's
+wrapped in a [`Reseeding`] instance, so it also picks up fresh entropy periodically and after
+a `fork()`.
+*/
+
+use crate::{ChaCha8Djb, Reseeding};
+use core::cell::RefCell;
+use std::rc::Rc;
+
+/// Amount of output (in 8-bit integers) between reseeds of the thread-local instance.
+const THREAD_RNG_RESEED_THRESHOLD: u64 = 1 << 16;
+
+std::thread_local! {
+    static THREAD_RNG: Rc<RefCell<Reseeding<ChaCha8Djb>>> =
+        Rc::new(RefCell::new(Reseeding::new(THREAD_RNG_RESEED_THRESHOLD)));
+}
+
+/// Handle to the thread-local ChaCha instance.
+///
+/// Obtained via [`thread_rng`]. Handles are cheap to clone, and all handles
+/// on the same thread share the same underlying instance.
+#[derive(Clone)]
+pub struct ThreadRng {
+    rng: Rc<RefCell<Reseeding<ChaCha8Djb>>>,
+}
+
+impl ThreadRng {
+    /// Fills `dst` with bytes from the output of the thread-local instance.
+    #[inline]
+    pub fn fill(&mut self, dst: &mut [u8]) {
+        self.rng.borrow_mut().fill(dst);
+    }
+
+    /// Xors `dst` with bytes from the output of the thread-local instance.
+    #[inline]
+    pub fn xor(&mut self, dst: &mut [u8]) {
+        self.rng.borrow_mut().xor(dst);
+    }
+}
+
+/// Returns a handle to the thread-local ChaCha instance, initializing
+/// it from OS entropy if this is the first use on the current thread.
+///
+/// ```
+/// let mut rng = chachacha::thread_rng();
+/// let mut buf = [0; 32];
+/// rng.fill(&mut buf);
+/// ```
+///
+/// # Panics
+///
+/// If the operating system is unable to provide entropy.
+#[inline]
+pub fn thread_rng() -> ThreadRng {
+    ThreadRng {
+        rng: THREAD_RNG.with(Rc::clone),
+    }
+}
+
+/// Fills `dst` with bytes from the output of the thread-local ChaCha instance.
+///
+/// Shorthand for `thread_rng().fill(dst)`.
+///
+/// ```
+/// let mut buf = [0; 32];
+/// chachacha::fill(&mut buf);
+/// ```
+#[inline]
+pub fn fill(dst: &mut [u8]) {
+    THREAD_RNG.with(|rng| rng.borrow_mut().fill(dst));
+}
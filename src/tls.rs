@@ -0,0 +1,113 @@
+/*!
+Module containing [`TlsRecordCipher`], which manages the per-record nonces of [TLS 1.3] on top of
+ChaCha20-Poly1305.
+
+Every record is protected with a nonce formed by xoring the 64-bit record sequence number
+(big-endian, left-padded with zeros) into the static IV derived alongside the key. The sequence
+number starts at 0 for each new key and is incremented after every record, and must never wrap,
+so a key can protect at most 2<sup>64</sup> records.
+
+Stacks which track sequence numbers themselves (such as DTLS, where they're explicit) can use
+[`tls13_nonce`] directly.
+
+[TLS 1.3]: https://datatracker.ietf.org/doc/html/rfc8446#section-5.3
+*/
+
+use crate::Error;
+use crate::chacha_poly1305::*;
+use crate::poly1305::POLY1305_TAG_LEN;
+
+/// Forms the nonce of the record with sequence number `seq` from the static `iv`.
+#[inline]
+pub fn tls13_nonce(iv: &[u8; AEAD_NONCE_LEN], seq: u64) -> [u8; AEAD_NONCE_LEN] {
+    let mut nonce = *iv;
+    nonce[4..]
+        .iter_mut()
+        .zip(seq.to_be_bytes())
+        .for_each(|(n, s)| *n ^= s);
+    nonce
+}
+
+/// ChaCha20-Poly1305 record protection for one direction of a TLS 1.3 connection.
+///
+/// ```
+/// use chachacha::TlsRecordCipher;
+///
+/// let mut client_write = TlsRecordCipher::new(&[69; 32], &[7; 12]);
+/// let mut server_read = TlsRecordCipher::new(&[69; 32], &[7; 12]);
+/// let header = [0x17, 0x03, 0x03, 0x00, 0x1b];
+/// let mut record = *b"hello world";
+/// let tag = client_write.seal(&header, &mut record).unwrap();
+/// assert!(server_read.open(&header, &mut record, &tag).is_ok());
+/// assert_eq!(&record, b"hello world");
+/// assert_eq!(server_read.sequence_number(), Some(1));
+/// ```
+pub struct TlsRecordCipher {
+    aead: ChaCha20Poly1305,
+    iv: [u8; AEAD_NONCE_LEN],
+    seq: Option<u64>,
+}
+
+impl TlsRecordCipher {
+    /// Creates a new `TlsRecordCipher` instance using the traffic `key` and static `iv`,
+    /// with the sequence number starting at 0.
+    #[inline]
+    pub fn new(key: &[u8; AEAD_KEY_LEN], iv: &[u8; AEAD_NONCE_LEN]) -> Self {
+        Self {
+            aead: ChaCha20Poly1305::new(key),
+            iv: *iv,
+            seq: Some(0),
+        }
+    }
+
+    /// Returns the sequence number of the next record, or `None` if they've all been used up.
+    #[inline]
+    pub fn sequence_number(&self) -> Option<u64> {
+        self.seq
+    }
+
+    /// Encrypts the record in `buf` in place, returning the tag authenticating
+    /// it and `aad` (the record header), then advances the sequence number.
+    ///
+    /// Returns [`Error::CounterExhausted`] if every sequence number has already been used,
+    /// and [`Error::InvalidLength`] if `buf` is longer than [`AEAD_MAX_MESSAGE_LEN`]. In both
+    /// cases `buf` is left untouched and the sequence number isn't advanced.
+    #[inline]
+    pub fn seal(&mut self, aad: &[u8], buf: &mut [u8]) -> Result<[u8; POLY1305_TAG_LEN], Error> {
+        let nonce = self.nonce()?;
+        let tag = self.aead.encrypt_detached(&nonce, aad, buf)?;
+        self.advance();
+        Ok(tag)
+    }
+
+    /// Authenticates the record in `buf` and `aad` (the record header) against `tag`,
+    /// and if successful decrypts `buf` in place and advances the sequence number.
+    ///
+    /// Returns [`Error::CounterExhausted`] if every sequence number has already been used,
+    /// and [`Error::TagMismatch`] if authentication fails. In both cases `buf` is left untouched
+    /// and the sequence number isn't advanced.
+    #[inline]
+    pub fn open(
+        &mut self,
+        aad: &[u8],
+        buf: &mut [u8],
+        tag: &[u8; POLY1305_TAG_LEN],
+    ) -> Result<(), Error> {
+        let nonce = self.nonce()?;
+        self.aead.decrypt_detached(&nonce, aad, buf, tag)?;
+        self.advance();
+        Ok(())
+    }
+
+    #[inline]
+    fn nonce(&self) -> Result<[u8; AEAD_NONCE_LEN], Error> {
+        self.seq
+            .map(|seq| tls13_nonce(&self.iv, seq))
+            .ok_or(Error::CounterExhausted)
+    }
+
+    #[inline]
+    fn advance(&mut self) {
+        self.seq = self.seq.and_then(|seq| seq.checked_add(1));
+    }
+}
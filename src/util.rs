@@ -2,6 +2,8 @@
 Module containing useful constants/structs and the core [`Machine`] trait.
 */
 
+use crate::hchacha::{hchacha, permute};
+use crate::rounds::*;
 use crate::variations::*;
 use core::ops::Add;
 
@@ -19,6 +21,8 @@ pub const SEED_LEN_U8: usize = (ROWS - 1) * size_of::<Row>();
 pub const SEED_LEN_U32: usize = SEED_LEN_U8 / size_of::<u32>();
 /// Size (in 64-bit integers) of the raw seed for a ChaCha instance.
 pub const SEED_LEN_U64: usize = SEED_LEN_U8 / size_of::<u64>();
+/// Size (in 8-bit integers) of the serialized state of a ChaCha instance.
+pub const STATE_LEN_U8: usize = MATRIX_SIZE_U8 + 1;
 /// Size (in 8-bit integers) of a reference ChaCha matrix.
 pub const MATRIX_SIZE_U8: usize = MATRIX_SIZE_U32 * size_of::<u32>();
 /// Size (in 32-bit integers) of a reference ChaCha matrix.
@@ -30,6 +34,27 @@ pub const DEPTH: usize = 4;
 pub const ROW_A: Row = Row {
     u8x16: *b"expand 32-byte k",
 };
+/// Constant used in place of [`ROW_A`] by instances with a 128-bit key.
+pub const ROW_A_128: Row = Row {
+    u8x16: *b"expand 16-byte k",
+};
+
+/// Returns `true` if `a` and `b` are equal, in constant time with respect to their contents.
+#[inline]
+pub fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+    let diff = a.iter().zip(b).fold(0, |acc, (a, b)| acc | (a ^ b));
+    // Keep the compiler from turning the fold into an early exit.
+    a.len() == b.len() && core::hint::black_box(diff) == 0
+}
+
+/// Reads `N` little-endian `u32` values from the start of `bytes`.
+#[inline]
+pub fn le_words<const N: usize>(bytes: &[u8]) -> [u32; N] {
+    core::array::from_fn(|i| {
+        let word = &bytes[i * size_of::<u32>()..][..size_of::<u32>()];
+        u32::from_le_bytes(word.try_into().unwrap())
+    })
+}
 
 /// Wrapper for the raw data of a ChaCha row. In a reference
 /// implementation this would just be the `u32x4` field, but having
@@ -38,9 +63,13 @@ pub const ROW_A: Row = Row {
 #[derive(Clone, Copy)]
 #[repr(C, align(16))]
 pub union Row {
+    /// Row as bytes.
     pub u8x16: [u8; 16],
+    /// Row as 16-bit integers.
     pub u16x8: [u16; 8],
+    /// Row as 32-bit integers, exactly like the reference implementation.
     pub u32x4: [u32; 4],
+    /// Row as 64-bit integers.
     pub u64x2: [u64; 2],
 }
 
@@ -49,8 +78,13 @@ pub union Row {
 /// Makes concrete implementations of `Machine` less verbose.
 #[repr(C)]
 pub struct ChaChaNaked {
+    /// Constants.
+    pub row_a: Row,
+    /// First half of the key.
     pub row_b: Row,
+    /// Second half of the key.
     pub row_c: Row,
+    /// Counter and nonce.
     pub row_d: Row,
 }
 
@@ -90,11 +124,61 @@ pub trait Machine: Add<Output = Self> + Clone {
     /// Performs the standard ChaCha double round operation.
     fn double_round(&mut self);
 
+    /// Derives the subkey used by the extended nonce variant ([`XDjb`]) from `key`
+    /// and the first 128 bits of the nonce.
+    ///
+    /// This is HChaCha for every ChaCha implementation, so only needs to
+    /// be overridden by machines implementing a different algorithm.
+    #[inline]
+    fn subkey<R: DoubleRounds>(key: &[u32; 8], input: &[u32; 4]) -> [u32; 8] {
+        hchacha::<R>(key, input)
+    }
+
+    /// Runs the block function once, on exactly the state in `state`, returning the
+    /// words of the block in output order (for callers which only ever need one block).
+    ///
+    /// This is plain scalar ChaCha for every ChaCha implementation, so only needs to
+    /// be overridden by machines implementing a different algorithm.
+    #[inline]
+    fn single_block<R: DoubleRounds>(state: &ChaChaNaked) -> [u32; MATRIX_SIZE_U32] {
+        let mut initial = [0; MATRIX_SIZE_U32];
+        for (dst, row) in
+            initial
+                .chunks_exact_mut(4)
+                .zip([state.row_a, state.row_b, state.row_c, state.row_d])
+        {
+            dst.copy_from_slice(unsafe { &row.u32x4 });
+        }
+        let mut result = initial;
+        permute::<R>(&mut result);
+        result
+            .iter_mut()
+            .zip(initial)
+            .for_each(|(a, b)| *a = a.wrapping_add(b));
+        initial.fill(0);
+        result
+    }
+
+    /// Runs the block function on `bufs.len()` consecutive batches, starting at the counter of
+    /// `state`, and fills (or xors, if `XOR`) each of `bufs` with the output of a single batch.
+    ///
+    /// This is the bulk path used by the slice methods (`fill`, `xor`, etc.), so only needs to
+    /// be overridden by machines which choose their implementation at runtime.
+    ///
+    /// Always inlined, so it's compiled with the target features of whatever calls it.
+    #[inline(always)]
+    fn apply<R: DoubleRounds, V: Variant, const XOR: bool>(
+        state: &ChaChaNaked,
+        bufs: &mut [[u8; BUF_LEN_U8]],
+    ) {
+        apply_interleaved::<Self, R, V, XOR, 1>(state, bufs);
+    }
+
     /// Converts the current `Machine` into raw bytes.
     fn fetch_result(self, buf: &mut [u8; BUF_LEN_U8]);
 
     /// Xors the current `Machine` raw bytes with `buf`.
-    #[inline]
+    #[inline(always)]
     fn xor_result(self, buf: &mut [u8; BUF_LEN_U8]) {
         let mut tmp = [0; BUF_LEN_U8];
         self.fetch_result(&mut tmp);
@@ -102,4 +186,66 @@ pub trait Machine: Add<Output = Self> + Clone {
             buf[i] ^= tmp[i];
         }
     }
+
+    /// Xors the current `Machine` raw bytes with `src`, storing the result in `dst`.
+    #[inline]
+    fn xor_result_b2b(self, src: &[u8; BUF_LEN_U8], dst: &mut [u8; BUF_LEN_U8]) {
+        let mut tmp = [0; BUF_LEN_U8];
+        self.fetch_result(&mut tmp);
+        for i in 0..BUF_LEN_U8 {
+            dst[i] = src[i] ^ tmp[i];
+        }
+    }
+}
+
+/// Implementation of [`Machine::apply`] which runs the block function on `N` machines (so on
+/// `N` batches) at once, for backends with enough registers to hide the latency of the dependency
+/// chains within a quarter round behind the other machines. Any leftover batches are produced
+/// one at a time.
+#[inline(always)]
+pub fn apply_interleaved<
+    M: Machine,
+    R: DoubleRounds,
+    V: Variant,
+    const XOR: bool,
+    const N: usize,
+>(
+    state: &ChaChaNaked,
+    bufs: &mut [[u8; BUF_LEN_U8]],
+) {
+    #[inline(always)]
+    fn output<M: Machine, const XOR: bool>(result: M, buf: &mut [u8; BUF_LEN_U8]) {
+        if XOR {
+            result.xor_result(buf);
+        } else {
+            result.fetch_result(buf);
+        }
+    }
+
+    let mut machine = M::new::<V>(state);
+    let (wide, rest) = bufs.as_chunks_mut::<N>();
+    for bufs in wide {
+        let originals: [M; N] = core::array::from_fn(|_| {
+            let original = machine.clone();
+            machine.increment::<V>();
+            original
+        });
+        let mut cur = originals.clone();
+        for _ in 0..R::COUNT {
+            for cur in cur.iter_mut() {
+                cur.double_round();
+            }
+        }
+        for ((cur, original), buf) in cur.into_iter().zip(originals).zip(bufs) {
+            output::<M, XOR>(cur + original, buf);
+        }
+    }
+    for buf in rest {
+        let mut cur = machine.clone();
+        for _ in 0..R::COUNT {
+            cur.double_round();
+        }
+        output::<M, XOR>(cur + machine.clone(), buf);
+        machine.increment::<V>();
+    }
 }
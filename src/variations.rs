@@ -2,6 +2,9 @@
 Module containing the variants of ChaCha (awfully descriptive, I know).
 */
 
+/// The variants of ChaCha, which differ only in how the last row
+/// of the matrix is split between the counter and nonce.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Variants {
     /// Original variant proposed by the author of the salsa
     /// and chacha algorithms: Daniel J. Bernstein.
@@ -11,16 +14,33 @@ pub enum Variants {
     Ietf,
 }
 
+/// Variant of ChaCha used by an instance.
 pub trait Variant {
+    /// Layout of the counter and nonce.
     const VAR: Variants;
+    /// Size (in 8-bit integers) of the nonce taken by the variant.
+    const NONCE_LEN: usize;
 }
 
+/// Original variant, with a 64-bit counter and a 64-bit nonce.
 pub struct Djb;
 impl Variant for Djb {
     const VAR: Variants = Variants::Djb;
+    const NONCE_LEN: usize = 8;
 }
 
+/// IETF variant, with a 32-bit counter and a 96-bit nonce.
 pub struct Ietf;
 impl Variant for Ietf {
     const VAR: Variants = Variants::Ietf;
+    const NONCE_LEN: usize = 12;
+}
+
+/// XChaCha, which derives the key and nonce of a [`Djb`] instance from
+/// the original key and a 192-bit nonce using HChaCha. Once created it
+/// behaves exactly like [`Djb`], so it shares the same `Variants` value.
+pub struct XDjb;
+impl Variant for XDjb {
+    const VAR: Variants = Variants::Djb;
+    const NONCE_LEN: usize = 24;
 }
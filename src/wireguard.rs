@@ -0,0 +1,137 @@
+/*!
+Module containing helpers for the transport messages of [WireGuard], which encrypt every packet
+with ChaCha20-Poly1305 using a 64-bit message counter as the nonce.
+
+The counter is encoded as 4 zero bytes followed by its little-endian representation, and
+transport messages have no additional data. A keepalive is simply a message with an empty
+payload (so just a tag). Keys must be replaced after [`REKEY_AFTER_MESSAGES`] messages and
+can't be used at all after [`REJECT_AFTER_MESSAGES`].
+
+Replay protection (the sliding window over received counters) is left up to the caller,
+since it depends on how packets are routed to each session.
+
+[WireGuard]: https://www.wireguard.com/papers/wireguard.pdf
+*/
+
+use crate::Error;
+use crate::chacha_poly1305::*;
+use crate::poly1305::POLY1305_TAG_LEN;
+
+/// Amount of messages after which a new handshake should be initiated.
+pub const REKEY_AFTER_MESSAGES: u64 = 1 << 60;
+/// Amount of messages after which a key must never be used again.
+pub const REJECT_AFTER_MESSAGES: u64 = u64::MAX - (1 << 13);
+
+/// Encodes `counter` as a WireGuard nonce.
+#[inline]
+pub fn wireguard_nonce(counter: u64) -> [u8; AEAD_NONCE_LEN] {
+    let mut nonce = [0; AEAD_NONCE_LEN];
+    nonce[4..].copy_from_slice(&counter.to_le_bytes());
+    nonce
+}
+
+/// Sending half of a WireGuard transport session.
+///
+/// ```
+/// use chachacha::{WireGuardReceiver, WireGuardSender};
+///
+/// let mut sender = WireGuardSender::new(&[69; 32]);
+/// let receiver = WireGuardReceiver::new(&[69; 32]);
+/// let mut packet = *b"hello world";
+/// let (counter, tag) = sender.seal(&mut packet).unwrap();
+/// assert!(receiver.open(counter, &mut packet, &tag).is_ok());
+/// assert_eq!(&packet, b"hello world");
+///
+/// let (counter, tag) = sender.keepalive().unwrap();
+/// assert_eq!(counter, 1);
+/// assert!(receiver.open(counter, &mut [], &tag).is_ok());
+/// ```
+pub struct WireGuardSender {
+    aead: ChaCha20Poly1305,
+    counter: u64,
+}
+
+impl WireGuardSender {
+    /// Creates a new `WireGuardSender` instance using the sending `key`
+    /// derived by the handshake, with the counter starting at 0.
+    #[inline]
+    pub fn new(key: &[u8; AEAD_KEY_LEN]) -> Self {
+        Self {
+            aead: ChaCha20Poly1305::new(key),
+            counter: 0,
+        }
+    }
+
+    /// Returns the counter which will be used for the next message.
+    #[inline]
+    pub fn counter(&self) -> u64 {
+        self.counter
+    }
+
+    /// Returns `true` if enough messages have been sent that a new handshake should be initiated.
+    #[inline]
+    pub fn needs_rekey(&self) -> bool {
+        self.counter >= REKEY_AFTER_MESSAGES
+    }
+
+    /// Encrypts `buf` in place, returning the counter (which must be sent
+    /// along with the message) and the tag.
+    ///
+    /// Returns [`Error::CounterExhausted`] once [`REJECT_AFTER_MESSAGES`] messages have been
+    /// sent, and [`Error::InvalidLength`] if `buf` is longer than [`AEAD_MAX_MESSAGE_LEN`].
+    /// In both cases `buf` is left untouched and the counter isn't advanced.
+    #[inline]
+    pub fn seal(&mut self, buf: &mut [u8]) -> Result<(u64, [u8; POLY1305_TAG_LEN]), Error> {
+        let counter = self.counter;
+        if counter >= REJECT_AFTER_MESSAGES {
+            return Err(Error::CounterExhausted);
+        }
+        let tag = self
+            .aead
+            .encrypt_detached(&wireguard_nonce(counter), &[], buf)?;
+        self.counter += 1;
+        Ok((counter, tag))
+    }
+
+    /// Creates a keepalive message (an empty payload), returning its counter and tag.
+    #[inline]
+    pub fn keepalive(&mut self) -> Result<(u64, [u8; POLY1305_TAG_LEN]), Error> {
+        self.seal(&mut [])
+    }
+}
+
+/// Receiving half of a WireGuard transport session.
+///
+/// See [`WireGuardSender`] for an example.
+pub struct WireGuardReceiver {
+    aead: ChaCha20Poly1305,
+}
+
+impl WireGuardReceiver {
+    /// Creates a new `WireGuardReceiver` instance using the receiving `key` derived by the handshake.
+    #[inline]
+    pub fn new(key: &[u8; AEAD_KEY_LEN]) -> Self {
+        Self {
+            aead: ChaCha20Poly1305::new(key),
+        }
+    }
+
+    /// Authenticates `buf` against `tag` using the `counter` sent along with it,
+    /// and if successful decrypts `buf` in place. An empty `buf` is a keepalive.
+    ///
+    /// Returns [`Error::CounterExhausted`] if `counter` is at or beyond [`REJECT_AFTER_MESSAGES`],
+    /// and [`Error::TagMismatch`] if authentication fails. In both cases `buf` is left untouched.
+    #[inline]
+    pub fn open(
+        &self,
+        counter: u64,
+        buf: &mut [u8],
+        tag: &[u8; POLY1305_TAG_LEN],
+    ) -> Result<(), Error> {
+        if counter >= REJECT_AFTER_MESSAGES {
+            return Err(Error::CounterExhausted);
+        }
+        self.aead
+            .decrypt_detached(&wireguard_nonce(counter), &[], buf, tag)
+    }
+}
@@ -0,0 +1,103 @@
+/*!
+Module containing [`EncryptingWriter`], which encrypts everything written through it with the
+keystream of a ChaCha instance before passing it on to an inner [`Write`].
+
+Writers are allowed to accept fewer bytes than they're given, so each write encrypts into a
+scratch buffer first, and only advances the keystream by however much the inner writer actually
+took. That keeps the position byte-exact no matter how the data is split up, so the output is
+always identical to xoring the whole stream in one call.
+*/
+
+use crate::ChaCha;
+use crate::rounds::*;
+use crate::util::*;
+use crate::variations::*;
+use std::io::{Result, Write};
+
+/// Writer which xors the data written to it with the keystream of a ChaCha
+/// instance with `R` rounds of the `V` variant, then writes it to `W`.
+///
+/// ```
+/// use chachacha::{ChaCha20Ietf, EncryptingWriter};
+/// use std::io::Write;
+///
+/// let mut expected = *b"attack at dawn, unless it rains";
+/// ChaCha20Ietf::new([69; 8], 1, [7; 3]).xor(&mut expected);
+///
+/// let mut writer = EncryptingWriter::new(Vec::new(), ChaCha20Ietf::new([69; 8], 1, [7; 3]));
+/// writer.write_all(b"attack at dawn").unwrap();
+/// writer.write_all(b", unless it rains").unwrap();
+/// let (ciphertext, _) = writer.into_parts();
+/// assert_eq!(ciphertext, expected);
+/// ```
+pub struct EncryptingWriter<W, R, V> {
+    inner: W,
+    core: ChaCha<R, V>,
+}
+
+impl<W, R, V> EncryptingWriter<W, R, V>
+where
+    W: Write,
+    R: DoubleRounds,
+    V: Variant,
+{
+    /// Creates a new `EncryptingWriter` instance, which writes to `inner`
+    /// starting from the current position of `core`.
+    #[inline]
+    pub fn new(inner: W, core: ChaCha<R, V>) -> Self {
+        Self { inner, core }
+    }
+
+    /// Returns the position (in bytes) in the keystream that the next write will start at.
+    #[inline]
+    pub fn position(&self) -> u128 {
+        self.core.position()
+    }
+
+    /// Returns a reference to the inner writer.
+    #[inline]
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the inner writer.
+    ///
+    /// Anything written directly to it bypasses the encryption.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Consumes `self`, returning the inner writer along with the ChaCha
+    /// instance positioned exactly where the next write would have started.
+    #[inline]
+    pub fn into_parts(self) -> (W, ChaCha<R, V>) {
+        (self.inner, self.core)
+    }
+}
+
+impl<W, R, V> Write for EncryptingWriter<W, R, V>
+where
+    W: Write,
+    R: DoubleRounds,
+    V: Variant,
+{
+    #[inline]
+    fn write(&mut self, src: &[u8]) -> Result<usize> {
+        let mut scratch = [0; BUF_LEN_U8];
+        let scratch = &mut scratch[..src.len().min(BUF_LEN_U8)];
+        scratch.copy_from_slice(&src[..scratch.len()]);
+        let start = self.core.position();
+        self.core.xor(scratch);
+        // Only advance past what the inner writer accepted, even if that's nothing.
+        let result = self.inner.write(scratch);
+        let written = *result.as_ref().unwrap_or(&0);
+        self.core.seek(start + written as u128);
+        result
+    }
+
+    #[inline]
+    fn flush(&mut self) -> Result<()> {
+        self.inner.flush()
+    }
+}